@@ -0,0 +1,318 @@
+//! Error reporting shared by every stage, renders the offending source line with the span
+//! underlined, like `rustc`:
+//!
+//! ```text
+//! error: Operands must be numbers.
+//!  --> script.lux:2:11
+//!   |
+//! 2 | println(1 + nil);
+//!   |           ^
+//! ```
+pub(crate) mod codes;
+
+use crate::types::{
+    ParserError,
+    RuntimeError,
+    span::{
+        FileId,
+        Span,
+    },
+};
+use codes::ErrorCode;
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    fmt::Write,
+    io::IsTerminal,
+};
+
+///A source registered so diagnostics can quote it
+struct SourceFile {
+    name: String,
+    text: String,
+}
+
+///How diagnostics are printed, picked on the command line
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ErrorFormat {
+    ///Source snippets with carets, for people
+    #[default]
+    Human,
+    ///One JSON object per line, for editors and CI
+    Json,
+}
+
+thread_local! {
+    ///Set once from the command line
+    static FORMAT: Cell<ErrorFormat> = const { Cell::new(ErrorFormat::Human) };
+
+    ///Every source seen this session, indexed by `FileId`, kept around so an error raised later
+    ///(a REPL line calling a function defined lines ago) can still show the code it points at
+    static SOURCES: RefCell<Vec<SourceFile>> = const { RefCell::new(vec![]) };
+}
+
+///Switches how every diagnostic from here on is printed
+pub(crate) fn set_format(format: ErrorFormat) {
+    FORMAT.set(format);
+}
+
+///Registers a source under `name`, spans produced while scanning it should carry the returned id
+pub(crate) fn add_source(name: &str, text: &str) -> FileId {
+    SOURCES.with_borrow_mut(|sources| {
+        sources.push(SourceFile {
+            name: name.to_string(),
+            text: text.to_string(),
+        });
+        (sources.len() - 1)
+            .try_into()
+            .expect("Registered more sources than FileId can count")
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Severity {
+    Error,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+        }
+    }
+}
+
+///Something wrong with the user's program, anchored to the span it is about
+#[derive(Clone, Debug)]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: Severity,
+    pub(crate) code: Option<&'static ErrorCode>,
+    pub(crate) message: String,
+    pub(crate) span: Span,
+    ///Printed next to the underline
+    pub(crate) label: Option<String>,
+    pub(crate) notes: Vec<String>,
+    pub(crate) help: Option<String>,
+}
+
+//ANSI escapes, only used when printing to a terminal
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Diagnostic {
+    pub(crate) fn error(message: impl Into<String>, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            span,
+            label: None,
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub(crate) fn with_code(mut self, code: Option<&'static ErrorCode>) -> Diagnostic {
+        self.code = code;
+        self
+    }
+
+    pub(crate) fn with_label(mut self, label: impl Into<String>) -> Diagnostic {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub(crate) fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help = Some(help.into());
+        self
+    }
+
+    ///Prints the diagnostic to stderr in the chosen format, colorized if stderr is a terminal
+    pub(crate) fn emit(&self) {
+        match FORMAT.get() {
+            ErrorFormat::Human => eprint!("{}", self.render(std::io::stderr().is_terminal())),
+            ErrorFormat::Json => eprintln!("{}", self.render_json()),
+        }
+    }
+
+    ///Renders the diagnostic as a single line JSON object
+    pub(crate) fn render_json(&self) -> String {
+        let span = self.span;
+        let file = SOURCES.with_borrow(|sources| {
+            sources
+                .get(span.file as usize)
+                .map_or(String::from("null"), |file| json_string(&file.name))
+        });
+        let severity = self.severity.name();
+        let code = self
+            .code
+            .map_or(String::from("null"), |code| json_string(code.code));
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+
+        format!(
+            "{{\"severity\":\"{severity}\",\"code\":{code},\"message\":{},\"file\":{file},\
+             \"line\":{},\"column\":{},\"span\":{{\"start\":{},\"end\":{}}},\"label\":{},\
+             \"notes\":[{}],\"help\":{}}}",
+            json_string(&self.message),
+            span.line,
+            span.column,
+            span.start,
+            span.end,
+            self.label
+                .as_deref()
+                .map_or(String::from("null"), json_string),
+            notes.join(","),
+            self.help
+                .as_deref()
+                .map_or(String::from("null"), json_string),
+        )
+    }
+
+    ///Renders the header, the quoted source line with the span underlined, then notes and help
+    pub(crate) fn render(&self, color: bool) -> String {
+        let paint = |style: &'static str| if color { style } else { "" };
+        let (red, blue, bold, reset) = (paint(RED), paint(BLUE), paint(BOLD), paint(RESET));
+        let severity = self.severity.name();
+
+        let code = self
+            .code
+            .map_or(String::new(), |code| format!("[{}]", code.code));
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{red}{severity}{code}{reset}{bold}: {}{reset}",
+            self.message
+        );
+
+        let span = self.span;
+        let gutter = " ".repeat(span.line.to_string().len());
+        let quoted = SOURCES.with_borrow(|sources| {
+            sources
+                .get(span.file as usize)
+                .map(|file| (file.name.clone(), source_line(&file.text, span)))
+        });
+
+        match quoted {
+            Some((name, Some((line, padding, width)))) => {
+                let _ = writeln!(
+                    out,
+                    "{gutter}{blue}-->{reset} {name}:{}:{}",
+                    span.line, span.column
+                );
+                let _ = writeln!(out, "{gutter} {blue}|{reset}");
+                let _ = writeln!(out, "{blue}{} |{reset} {line}", span.line);
+                let _ = write!(
+                    out,
+                    "{gutter} {blue}|{reset} {padding}{red}{}",
+                    "^".repeat(width)
+                );
+                if let Some(ref label) = self.label {
+                    let _ = write!(out, " {label}");
+                }
+                let _ = writeln!(out, "{reset}");
+            }
+            //Nothing to quote, still say where it happened
+            Some((name, None)) => {
+                let _ = writeln!(
+                    out,
+                    "{gutter}{blue}-->{reset} {name}:{}:{}",
+                    span.line, span.column
+                );
+            }
+            None => {
+                let _ = writeln!(out, "{gutter}{blue}-->{reset} line {}", span.line);
+            }
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(out, "{gutter} {blue}={reset} {bold}note{reset}: {note}");
+        }
+        if let Some(ref help) = self.help {
+            let _ = writeln!(out, "{gutter} {blue}={reset} {bold}help{reset}: {help}");
+        }
+
+        out
+    }
+}
+
+///Prints a batch of diagnostics ordered by where they point, rather than by which stage found them
+pub(crate) fn emit_all(mut diagnostics: Vec<Diagnostic>) {
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.span.file, diagnostic.span.start));
+    for diagnostic in &diagnostics {
+        diagnostic.emit();
+    }
+}
+
+///Finds the line `span` starts on, returning it along with the whitespace that lines a caret up
+///under `span` and how many carets to draw. Spans running past the end of the line are cut short
+fn source_line(text: &str, span: Span) -> Option<(String, String, usize)> {
+    let (start, end) = (span.start as usize, span.end as usize);
+    if start > text.len() {
+        return None;
+    }
+
+    let line_start = text[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = text[start..]
+        .find('\n')
+        .map_or(text.len(), |index| start + index);
+    let line = text[line_start..line_end].trim_end_matches('\r');
+
+    //Tabs are kept so the carets line up however wide the terminal draws them
+    let padding = text[line_start..start]
+        .chars()
+        .map(|character| if character == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = text[start..end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    Some((line.to_string(), padding, width))
+}
+
+///Quotes and escapes `text` as a JSON string
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            control if control.is_control() => {
+                let _ = write!(out, "\\u{:04x}", control as u32);
+            }
+            other => out.push(other),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        Diagnostic::error(error.cause.clone(), error.source.span).with_code(error.code)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        let mut diagnostic =
+            Diagnostic::error(error.message.clone(), error.span).with_code(Some(error.code));
+        for note in &error.notes {
+            diagnostic = diagnostic.with_note(note.clone());
+        }
+        diagnostic
+    }
+}
@@ -0,0 +1,784 @@
+//! Stable error codes, once published a code keeps its meaning so people can search for it
+use crate::types::TokenType;
+
+///Identifies one kind of error, `rlux --explain <code>` prints the long form
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ErrorCode {
+    pub(crate) code: &'static str,
+    ///One sentence, also used as the message when the error has nothing more specific to say
+    pub(crate) title: &'static str,
+    ///What causes the error and an example of fixing it
+    pub(crate) explanation: &'static str,
+}
+
+pub(crate) const UNEXPECTED_CHARACTER: ErrorCode = ErrorCode {
+    code: "E0001",
+    title: "Unexpected character.",
+    explanation: "\
+The scanner found a character that can't start any token, usually a stray symbol or one
+copied in from another language.
+
+    var total = 1 @ 2;
+
+Remove the character or replace it with an operator rlux understands:
+
+    var total = 1 + 2;",
+};
+
+pub(crate) const UNTERMINATED_STRING: ErrorCode = ErrorCode {
+    code: "E0002",
+    title: "Unterminated string.",
+    explanation: "\
+A string was opened with `\"` but the file ended before a closing `\"` was found.
+
+    var greeting = \"hello;
+
+Close the string where it should end:
+
+    var greeting = \"hello\";",
+};
+
+pub(crate) const EXPECTED_SEMICOLON: ErrorCode = ErrorCode {
+    code: "E0003",
+    title: "Expected `;`.",
+    explanation: "\
+Every statement that isn't a block ends with a semicolon, the parser reached the end of one
+without finding it.
+
+    var a = 1
+    println(a);
+
+Add the semicolon:
+
+    var a = 1;
+    println(a);",
+};
+
+pub(crate) const UNBALANCED_PARENTHESES: ErrorCode = ErrorCode {
+    code: "E0004",
+    title: "Expected `(` or `)`.",
+    explanation: "\
+A parenthesis is missing, either around the condition of an `if`, `while` or `for`, around
+a function's parameters, or at the end of a call's arguments or a grouping.
+
+    if a < 2 {
+        println(a;
+    }
+
+Conditions and argument lists are always wrapped in a matching pair:
+
+    if (a < 2) {
+        println(a);
+    }",
+};
+
+pub(crate) const UNBALANCED_BRACES: ErrorCode = ErrorCode {
+    code: "E0005",
+    title: "Expected `{` or `}`.",
+    explanation: "\
+Function, method and class bodies must be wrapped in braces, and every `{` needs a `}`.
+
+    fun greet() println(\"hi\");
+
+Wrap the body in a block:
+
+    fun greet() { println(\"hi\"); }",
+};
+
+pub(crate) const EXPECTED_NAME: ErrorCode = ErrorCode {
+    code: "E0006",
+    title: "Expected a name.",
+    explanation: "\
+A variable, function, parameter, class, method or property name was expected, but something
+else was found, often a keyword or a number.
+
+    var class = 1;
+    fun add(1, b) { return b; }
+
+Names start with a letter or `_` and can't be keywords:
+
+    var kind = 1;
+    fun add(a, b) { return a + b; }",
+};
+
+pub(crate) const EXPECTED_COLON: ErrorCode = ErrorCode {
+    code: "E0007",
+    title: "Expected `:` in a ternary.",
+    explanation: "\
+A ternary needs both branches, separated by `:`.
+
+    var sign = n < 0 ? -1;
+
+Give it an else branch:
+
+    var sign = n < 0 ? -1 : 1;",
+};
+
+pub(crate) const EXPECTED_DOT: ErrorCode = ErrorCode {
+    code: "E0008",
+    title: "Expected `.` after `super`.",
+    explanation: "\
+`super` can only be used to look up a method on the superclass, it is not a value on its own.
+
+    return super;
+
+Name the method you want:
+
+    return super.describe();",
+};
+
+pub(crate) const EXPECTED_EXPRESSION: ErrorCode = ErrorCode {
+    code: "E0009",
+    title: "Expected an expression.",
+    explanation: "\
+The parser needed a value, like a literal, a variable or a call, but found something that
+can't start one, often an operator with a missing operand.
+
+    var a = * 2;
+
+Supply the missing operand:
+
+    var a = 3 * 2;",
+};
+
+pub(crate) const INVALID_ASSIGNMENT_TARGET: ErrorCode = ErrorCode {
+    code: "E0010",
+    title: "Invalid assignment target.",
+    explanation: "\
+Only variables and properties can be assigned to.
+
+    a + b = 3;
+
+Assign to a variable instead:
+
+    a = 3 - b;",
+};
+
+pub(crate) const OPERANDS_MUST_BE_NUMBERS: ErrorCode = ErrorCode {
+    code: "E0011",
+    title: "Operands must be numbers.",
+    explanation: "\
+`-`, `*`, `/`, `<`, `<=`, `>` and `>=` only work on numbers, rlux never converts other values
+to numbers for you.
+
+    var half = \"10\" / 2;
+
+Use numbers on both sides:
+
+    var half = 10 / 2;",
+};
+
+pub(crate) const INVALID_ADDITION: ErrorCode = ErrorCode {
+    code: "E0012",
+    title: "Operands must be two numbers or start with a string.",
+    explanation: "\
+`+` adds two numbers, or joins anything onto the end of a string. A non-string on the left
+can't be joined, so the order matters.
+
+    println(1 + \" apple\");
+
+Start with a string to join, or convert the number another way:
+
+    println(\"\" + 1 + \" apple\");",
+};
+
+pub(crate) const INVALID_CONCATENATION: ErrorCode = ErrorCode {
+    code: "E0013",
+    title: "Only strings, numbers, booleans and nil can be added to a string.",
+    explanation: "\
+Functions, classes and instances have no text form that `+` can join onto a string.
+
+    println(\"result: \" + compute);
+
+Call the function, or join one of the instance's fields:
+
+    println(\"result: \" + compute());",
+};
+
+pub(crate) const INVALID_NUMBER: ErrorCode = ErrorCode {
+    code: "E0014",
+    title: "Invalid number literal.",
+    explanation: "\
+A number literal couldn't be read. Numbers are written in decimal, optionally with a fraction
+and an exponent, or in hexadecimal, binary or octal after a `0x`, `0b` or `0o` prefix. `_` can
+separate digits but has to be followed by one.
+
+    var mask = 0xFG;
+    var big = 1_000_;
+    var tiny = 1e-;
+
+Use only digits the base allows and finish what was started:
+
+    var mask = 0xFF;
+    var big = 1_000;
+    var tiny = 1e-9;",
+};
+
+pub(crate) const INVALID_ESCAPE: ErrorCode = ErrorCode {
+    code: "E0015",
+    title: "Invalid escape sequence.",
+    explanation: "\
+A backslash in a string starts an escape, and only `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`,
+`\\'`, `\\$`, `\\x41` (ASCII only) and `\\u{1F600}` are understood.
+
+    var path = \"C:\\Users\\me\";
+
+Double the backslashes, or use a raw string where backslashes are kept as written:
+
+    var path = \"C:\\\\Users\\\\me\";
+    var path = r\"C:\\Users\\me\";",
+};
+
+pub(crate) const UNBALANCED_BRACKETS: ErrorCode = ErrorCode {
+    code: "E0016",
+    title: "Expected `[` or `]`.",
+    explanation: "\
+List literals, indexes and slices are wrapped in square brackets, and every `[` needs a `]`.
+
+    var numbers = [1, 2, 3;
+    println(numbers[0);
+
+Close the bracket where the list or index ends:
+
+    var numbers = [1, 2, 3];
+    println(numbers[0]);",
+};
+
+pub(crate) const UNCAUGHT_EXCEPTION: ErrorCode = ErrorCode {
+    code: "E0017",
+    title: "Uncaught exception.",
+    explanation: "\
+A value was thrown with `throw` and no `try` around it caught it, so the program stopped.
+
+    fun parse(text) {
+        if (text == \"\") throw \"empty input\";
+        return text;
+    }
+    parse(\"\");
+
+Wrap the call in a `try` and handle the value in `catch`, runtime errors can be caught the
+same way and arrive as a map with a `message` and a `line`:
+
+    try {
+        parse(\"\");
+    } catch (e) {
+        println(\"could not parse: \" + e);
+    }",
+};
+
+pub(crate) const UNDEFINED_VARIABLE: ErrorCode = ErrorCode {
+    code: "E0018",
+    title: "Undefined variable.",
+    explanation: "\
+A variable was read or assigned before any `var` declared it. Globals are looked up when the
+line runs, so a typo in a name only shows up then.
+
+    var count = 0;
+    cuont = cuont + 1;
+
+Declare the variable first, or fix the spelling:
+
+    var count = 0;
+    count = count + 1;",
+};
+
+pub(crate) const NOT_CALLABLE: ErrorCode = ErrorCode {
+    code: "E0019",
+    title: "Can only call functions and classes.",
+    explanation: "\
+Parentheses after a value call it, and only functions, methods and classes can be called.
+
+    var name = \"rlux\";
+    name();
+
+Call a function, or leave the parentheses off to use the value:
+
+    println(name);",
+};
+
+pub(crate) const WRONG_ARGUMENT_COUNT: ErrorCode = ErrorCode {
+    code: "E0020",
+    title: "Wrong number of arguments.",
+    explanation: "\
+A function has to be called with exactly as many arguments as it has parameters, there are
+no default or optional parameters. A class is called with the arguments its `init` takes.
+
+    fun add(a, b) { return a + b; }
+    add(1);
+
+Pass every argument:
+
+    add(1, 2);",
+};
+
+pub(crate) const OPERAND_MUST_BE_NUMBER: ErrorCode = ErrorCode {
+    code: "E0021",
+    title: "Operand must be a number.",
+    explanation: "\
+Unary `-` negates a number, it doesn't convert anything else into one.
+
+    var flipped = -true;
+
+Negate a number instead:
+
+    var flipped = -1;",
+};
+
+pub(crate) const INVALID_STEP: ErrorCode = ErrorCode {
+    code: "E0022",
+    title: "Only numbers can be incremented or decremented.",
+    explanation: "\
+`++` and `--` add or take away one, so the variable, property or element they change has to
+hold a number.
+
+    var label = \"a\";
+    label++;
+
+Step a number, or use `+=` to join onto a string:
+
+    var count = 1;
+    count++;
+    label += 1;",
+};
+
+pub(crate) const BOOLEAN_REQUIRED: ErrorCode = ErrorCode {
+    code: "E0023",
+    title: "Expected a boolean.",
+    explanation: "\
+rlux has no truthiness. Conditions, `!`, `and` and `or` only accept `true` or `false`, a
+number, string or `nil` is never treated as one.
+
+    var items = 3;
+    if (items) println(\"some\");
+
+Compare to get a boolean:
+
+    if (items > 0) println(\"some\");",
+};
+
+pub(crate) const NOT_AN_INSTANCE: ErrorCode = ErrorCode {
+    code: "E0024",
+    title: "Only instances have properties.",
+    explanation: "\
+`.` reads or writes a property, and only instances of a class have them.
+
+    var point = 1;
+    point.x = 2;
+
+Create an instance first:
+
+    class Point {}
+    var point = Point();
+    point.x = 2;",
+};
+
+pub(crate) const UNDEFINED_PROPERTY: ErrorCode = ErrorCode {
+    code: "E0025",
+    title: "Undefined property.",
+    explanation: "\
+The instance has no field and its class no method with that name. Fields only exist once
+something assigns them.
+
+    class Point { init() { this.x = 0; } }
+    println(Point().y);
+
+Assign the field first, for example in `init`, or fix the name:
+
+    println(Point().x);",
+};
+
+pub(crate) const INVALID_SUPERCLASS: ErrorCode = ErrorCode {
+    code: "E0026",
+    title: "Superclass must be a class.",
+    explanation: "\
+The name after `<` in a class declaration has to be another class, and a class can't inherit
+from itself.
+
+    var Base = 1;
+    class Derived < Base {}
+
+Inherit from a class:
+
+    class Base {}
+    class Derived < Base {}",
+};
+
+pub(crate) const STACK_OVERFLOW: ErrorCode = ErrorCode {
+    code: "E0027",
+    title: "Stack overflow.",
+    explanation: "\
+Too many calls were running at once, usually a recursive function that never reaches the
+case that stops it.
+
+    fun count(n) { return count(n + 1); }
+    count(0);
+
+Give the recursion a way to stop, or use a loop:
+
+    fun count(n) {
+        if (n == 10) return n;
+        return count(n + 1);
+    }",
+};
+
+pub(crate) const NOT_INDEXABLE: ErrorCode = ErrorCode {
+    code: "E0028",
+    title: "Can only index lists and maps.",
+    explanation: "\
+Square brackets after a value read or write one of its elements, which only lists and maps
+have. Only lists can be sliced.
+
+    var name = \"rlux\";
+    println(name[0]);
+
+Index a list or map instead:
+
+    var names = [\"rlux\"];
+    println(names[0]);",
+};
+
+pub(crate) const INVALID_INDEX: ErrorCode = ErrorCode {
+    code: "E0029",
+    title: "List indexes must be whole numbers that aren't negative.",
+    explanation: "\
+Lists are indexed from 0 by whole numbers, and slice bounds follow the same rule. Counting
+back from the end with negative indexes isn't supported.
+
+    var xs = [1, 2, 3];
+    println(xs[-1]);
+
+Use the length to reach the end:
+
+    println(xs[len(xs) - 1]);",
+};
+
+pub(crate) const INDEX_OUT_OF_RANGE: ErrorCode = ErrorCode {
+    code: "E0030",
+    title: "Index out of range.",
+    explanation: "\
+An index has to be smaller than the list's length, and a slice has to start before it ends
+and stay inside the list. Assigning past the end doesn't grow a list.
+
+    var xs = [1, 2];
+    xs[2] = 3;
+
+Use `push` to add elements:
+
+    push(xs, 3);",
+};
+
+pub(crate) const INVALID_KEY: ErrorCode = ErrorCode {
+    code: "E0031",
+    title: "Map keys must be strings, numbers or booleans.",
+    explanation: "\
+Map keys are compared by value, so only strings, numbers and booleans can be used. Lists,
+maps, functions, instances and `nil` can't.
+
+    var seen = {};
+    seen[[1, 2]] = true;
+
+Use a value that describes the key instead:
+
+    seen[\"1,2\"] = true;",
+};
+
+pub(crate) const MISSING_KEY: ErrorCode = ErrorCode {
+    code: "E0032",
+    title: "Key isn't in the map.",
+    explanation: "\
+Reading a key, or removing one, fails when the map doesn't hold it. Assigning to a missing
+key is fine and adds it.
+
+    var ages = {\"ada\": 36};
+    println(ages[\"bob\"]);
+
+Check with `has` first:
+
+    if (has(ages, \"bob\")) println(ages[\"bob\"]);",
+};
+
+pub(crate) const INVALID_ARGUMENT: ErrorCode = ErrorCode {
+    code: "E0033",
+    title: "Invalid argument to a built-in function.",
+    explanation: "\
+A built-in function was handed a value it doesn't work on. `len` takes a list, map or string,
+`push` and `pop` a list, and `has`, `remove` and `keys` a map. `pop` also needs the list to
+hold something.
+
+    var total = len(42);
+
+Pass a value of the right kind:
+
+    var total = len([4, 2]);",
+};
+
+pub(crate) const NOT_ITERABLE: ErrorCode = ErrorCode {
+    code: "E0034",
+    title: "Can only iterate over lists, maps, strings, ranges, functions and objects with a \
+            'next' method.",
+    explanation: "\
+`for in` walks the elements of a list, the keys of a map, the characters of a string, the
+numbers of a range, the values a function returns until `nil`, or what an object's `next`
+method returns until `nil`. Anything else can't be walked.
+
+    for (var i in 3) println(i);
+
+Iterate over a range instead:
+
+    for (var i in 0..3) println(i);",
+};
+
+pub(crate) const INVALID_RANGE: ErrorCode = ErrorCode {
+    code: "E0035",
+    title: "Range bounds must be numbers.",
+    explanation: "\
+Both ends of `..` and `..=` have to be numbers.
+
+    for (var i in 0..\"5\") println(i);
+
+Use numbers for both bounds:
+
+    for (var i in 0..5) println(i);",
+};
+
+pub(crate) const ALREADY_DECLARED: ErrorCode = ErrorCode {
+    code: "E0036",
+    title: "Already declared in this scope.",
+    explanation: "\
+A block, function or loop declared two variables with the same name. Globals can be
+redeclared, locals can't.
+
+    {
+        var total = 1;
+        var total = 2;
+    }
+
+Assign to the variable instead, or pick a new name:
+
+    {
+        var total = 1;
+        total = 2;
+    }",
+};
+
+pub(crate) const OWN_INITIALIZER: ErrorCode = ErrorCode {
+    code: "E0037",
+    title: "Can't read local variable in its own initializer.",
+    explanation: "\
+A local variable doesn't exist until its initializer has run, so it can't be used to work
+out its own value, even when an outer variable has the same name.
+
+    var a = 1;
+    {
+        var a = a + 1;
+    }
+
+Use a different name for the new variable:
+
+    var a = 1;
+    {
+        var b = a + 1;
+    }",
+};
+
+pub(crate) const INVALID_RETURN: ErrorCode = ErrorCode {
+    code: "E0038",
+    title: "Invalid return.",
+    explanation: "\
+`return` only makes sense inside a function, and an `init` method always gives back the new
+instance so it can't return a value.
+
+    class Point {
+        init() { return 1; }
+    }
+
+Leave the value off, or move the code into a function:
+
+    class Point {
+        init() { return; }
+    }",
+};
+
+pub(crate) const INVALID_THIS: ErrorCode = ErrorCode {
+    code: "E0039",
+    title: "Can't use 'this' outside of a class.",
+    explanation: "\
+`this` is the instance a method was called on, so it only exists inside methods.
+
+    fun describe() { return this.name; }
+
+Move the function into a class, or pass the instance in:
+
+    fun describe(item) { return item.name; }",
+};
+
+pub(crate) const INVALID_SUPER: ErrorCode = ErrorCode {
+    code: "E0040",
+    title: "Invalid use of 'super'.",
+    explanation: "\
+`super` calls a method of the superclass, so it only exists in methods of a class that
+inherits from another.
+
+    class Base {
+        describe() { return super.describe(); }
+    }
+
+Inherit from a class that has the method:
+
+    class Named { describe() { return \"named\"; } }
+    class Base < Named {
+        describe() { return super.describe(); }
+    }",
+};
+
+pub(crate) const INVALID_JUMP: ErrorCode = ErrorCode {
+    code: "E0041",
+    title: "`break` and `continue` need an enclosing loop.",
+    explanation: "\
+`break` and `continue` apply to the loop around them, and a label has to name one of those
+loops. A label can only be put in front of a `while` or `for`, and a function body starts
+with no loops around it.
+
+    outer: while (true) {}
+    while (true) { break outer; }
+
+Label the loop the jump is inside:
+
+    outer: while (true) {
+        while (true) { break outer; }
+    }",
+};
+
+pub(crate) const EXPECTED_HANDLER: ErrorCode = ErrorCode {
+    code: "E0042",
+    title: "Expected `catch` or `finally` after a try block.",
+    explanation: "\
+A `try` block has to be followed by a `catch` clause, a `finally` clause or both.
+
+    try { risky(); }
+
+Handle the error, or clean up after the block:
+
+    try { risky(); } catch (e) { println(e); }",
+};
+
+pub(crate) const UNSUPPORTED_BY_LLVM: ErrorCode = ErrorCode {
+    code: "E0043",
+    title: "Not supported by the LLVM backend.",
+    explanation: "\
+`--emit-llvm` compiles a smaller part of the language than the interpreters run: numbers,
+strings, booleans, functions, loops over ranges and the `clock`, `print` and `println`
+built-ins. Closures, classes, lists, maps and `try` aren't compiled yet.
+
+    var xs = [1, 2, 3];
+
+Run the program with `-b tree` or `-b vm` instead, or keep to what the backend supports:
+
+    var first = 1;",
+};
+
+pub(crate) const UNTERMINATED_COMMENT: ErrorCode = ErrorCode {
+    code: "E0044",
+    title: "Unterminated block comment.",
+    explanation: "\
+A block comment was opened with `/*` but the file ended before a closing `*/` was found, so
+everything after it would silently be ignored.
+
+    /* helpers
+    fun double(x) { return x * 2; }
+
+Close the comment where it should end:
+
+    /* helpers */
+    fun double(x) { return x * 2; }",
+};
+
+///Every code, `--explain` looks things up here
+pub(crate) const ALL: &[ErrorCode] = &[
+    UNEXPECTED_CHARACTER,
+    UNTERMINATED_STRING,
+    EXPECTED_SEMICOLON,
+    UNBALANCED_PARENTHESES,
+    UNBALANCED_BRACES,
+    EXPECTED_NAME,
+    EXPECTED_COLON,
+    EXPECTED_DOT,
+    EXPECTED_EXPRESSION,
+    INVALID_ASSIGNMENT_TARGET,
+    OPERANDS_MUST_BE_NUMBERS,
+    INVALID_ADDITION,
+    INVALID_CONCATENATION,
+    INVALID_NUMBER,
+    INVALID_ESCAPE,
+    UNBALANCED_BRACKETS,
+    UNCAUGHT_EXCEPTION,
+    UNDEFINED_VARIABLE,
+    NOT_CALLABLE,
+    WRONG_ARGUMENT_COUNT,
+    OPERAND_MUST_BE_NUMBER,
+    INVALID_STEP,
+    BOOLEAN_REQUIRED,
+    NOT_AN_INSTANCE,
+    UNDEFINED_PROPERTY,
+    INVALID_SUPERCLASS,
+    STACK_OVERFLOW,
+    NOT_INDEXABLE,
+    INVALID_INDEX,
+    INDEX_OUT_OF_RANGE,
+    INVALID_KEY,
+    MISSING_KEY,
+    INVALID_ARGUMENT,
+    NOT_ITERABLE,
+    INVALID_RANGE,
+    ALREADY_DECLARED,
+    OWN_INITIALIZER,
+    INVALID_RETURN,
+    INVALID_THIS,
+    INVALID_SUPER,
+    INVALID_JUMP,
+    EXPECTED_HANDLER,
+    UNSUPPORTED_BY_LLVM,
+    UNTERMINATED_COMMENT,
+];
+
+///A message along with its code, what the helpers both backends share hand back when they fail
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Failure {
+    pub(crate) code: &'static ErrorCode,
+    pub(crate) message: String,
+}
+
+impl Failure {
+    pub(crate) fn new(code: &'static ErrorCode, message: impl Into<String>) -> Failure {
+        Failure {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<&'static ErrorCode> for Failure {
+    fn from(code: &'static ErrorCode) -> Self {
+        Failure::new(code, code.title)
+    }
+}
+
+///Finds a code by name, ignoring case so `e0012` works too
+pub(crate) fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    ALL.iter()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
+
+///The code for failing to find `token_type` where the grammar requires it
+pub(crate) fn expected(token_type: TokenType) -> Option<&'static ErrorCode> {
+    match token_type {
+        TokenType::Semicolon => Some(&EXPECTED_SEMICOLON),
+        TokenType::LeftParen | TokenType::RightParen => Some(&UNBALANCED_PARENTHESES),
+        TokenType::LeftBrace | TokenType::RightBrace => Some(&UNBALANCED_BRACES),
+        TokenType::LeftBracket | TokenType::RightBracket => Some(&UNBALANCED_BRACKETS),
+        TokenType::Identifier => Some(&EXPECTED_NAME),
+        TokenType::Colon => Some(&EXPECTED_COLON),
+        TokenType::Dot => Some(&EXPECTED_DOT),
+        _ => None,
+    }
+}
@@ -1,63 +1,122 @@
-use crate::types::LiteralType;
+use crate::{
+    diagnostics::codes::{
+        self,
+        Failure,
+    },
+    types::LiteralType,
+};
 use std::{
+    cell::RefCell,
     collections::HashMap,
     env::VarError,
     fmt::{
         Debug,
         Formatter,
     },
+    rc::Rc,
 };
+
+///Shared handle to an enviroment, closures and blocks hold these so they all see the same
+///variables rather than copies of them
+pub(crate) type EnviromentRef = Rc<RefCell<Enviroment>>;
+
 #[derive(Clone)]
 ///Enclosing Enviroment for Rlux runtime
 pub struct Enviroment {
-    pub(crate) enclosing: Option<Box<Enviroment>>,
+    pub(crate) enclosing: Option<EnviromentRef>,
+    ///Only the global enviroment looks variables up by name
     pub(crate) variable_map: HashMap<String, LiteralType>,
+    ///Locals, in the order they were declared, indexed by the resolver's slot numbers
+    pub(crate) slots: Vec<LiteralType>,
 }
 
 impl Debug for Enviroment {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         write!(
             f,
-            "Current: \n\tHashMap:{:?}\nNext:\n\t{:?}\n\n",
-            self.variable_map, self.enclosing
+            "Current: \n\tHashMap:{:?}\n\tSlots:{:?}\nNext:\n\t{:?}\n\n",
+            self.variable_map, self.slots, self.enclosing
         )
     }
 }
 
 impl Enviroment {
-    ///Defines a new variable and maps the value to the Literal Provided
+    ///Creates a fresh scope nested inside `enclosing`, ready to be shared
+    pub(crate) fn new(enclosing: Option<EnviromentRef>) -> EnviromentRef {
+        Rc::new(RefCell::new(Enviroment {
+            enclosing,
+            variable_map: HashMap::new(),
+            slots: vec![],
+        }))
+    }
+
+    ///Defines a new variable and maps the value to the Literal Provided, locals take the next slot
+    ///so definitions must happen in the same order the resolver declared them
     pub(crate) fn define(&mut self, name: &str, value: LiteralType) {
-        {
+        if self.enclosing.is_none() {
             self.variable_map.insert(name.to_string(), value);
+        } else {
+            self.slots.push(value);
         }
         // println!("Enviroment: {:?} defined: {name}",self);
     }
 
+    ///Reads a resolved local, `depth` enviroments up the chain
+    pub(crate) fn get_at(&self, depth: usize, slot: usize) -> LiteralType {
+        if depth == 0 {
+            self.slots[slot].clone()
+        } else {
+            self.enclosing
+                .as_ref()
+                .expect("Resolver produced a depth deeper than the enviroment chain")
+                .borrow()
+                .get_at(depth - 1, slot)
+        }
+    }
+
+    ///Overwrites a resolved local, `depth` enviroments up the chain
+    pub(crate) fn assign_at(&mut self, depth: usize, slot: usize, value: LiteralType) {
+        if depth == 0 {
+            self.slots[slot] = value;
+        } else {
+            self.enclosing
+                .as_ref()
+                .expect("Resolver produced a depth deeper than the enviroment chain")
+                .borrow_mut()
+                .assign_at(depth - 1, slot, value);
+        }
+    }
+
     ///Gets a defined variable, throws a runtime error if non is found
-    pub(crate) fn get(&self, name: &str) -> Result<&LiteralType, VarError> {
+    pub(crate) fn get(&self, name: &str) -> Result<LiteralType, VarError> {
         if let Some(lit) = self.variable_map.get(name) {
             // println!("Gave {lit}");
-            return Ok(lit);
+            return Ok(lit.clone());
         }
 
         if let Some(ref underlying) = self.enclosing {
             // println!("Enclosing Checked");
-            underlying.get(name)
+            underlying.borrow().get(name)
         } else {
             Err(VarError::NotPresent)
         }
     }
 
-    /// Assigns value to variable, may be used to redfine existing varibles
-    pub(crate) fn assign(&mut self, name: &str, value: LiteralType, line: u32) {
+    /// Assigns value to variable, may be used to redfine existing varibles, fails if the variable
+    /// was never defined
+    pub(crate) fn assign(&mut self, name: &str, value: LiteralType) -> Result<(), Failure> {
         use std::collections::hash_map::*;
 
         if let Entry::Occupied(mut entry) = self.variable_map.entry(name.to_string()) {
             entry.insert(value);
-        } else if self.enclosing.is_some() {
-            self.enclosing.as_mut().unwrap().assign(name, value, line);
+            Ok(())
+        } else if let Some(ref enclosing) = self.enclosing {
+            enclosing.borrow_mut().assign(name, value)
         } else {
-            crate::error(line, format!("Assignement failed on {name}."));
+            Err(Failure::new(
+                &codes::UNDEFINED_VARIABLE,
+                format!("Undefined variable '{name}'."),
+            ))
         }
     }
 }
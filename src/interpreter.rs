@@ -1,23 +1,47 @@
 use crate::{
-    enviroment::Enviroment,
+    enviroment::EnviromentRef,
     types::{
         Expression,
         LiteralType,
+        RuntimeError,
     },
 };
-use interpreter_traits::InterpreterVisitor;
+pub(crate) use interpreter_traits::{
+    InterpreterVisitor,
+    Visitable,
+};
 
 pub(crate) struct Interpreter {
-    pub(crate) enviroment: Box<Enviroment>,
-    pub(crate) globals: Enviroment,
+    ///Innermost scope currently executing
+    pub(crate) enviroment: EnviromentRef,
+    ///Outermost scope, holds the native functions and top-level declarations
+    pub(crate) globals: EnviromentRef,
+    ///When set, `print` and `println` append here instead of writing to stdout
+    pub(crate) captured: Option<String>,
+    ///User function calls currently running, a stack overflow is reported at the same depth
+    ///as the VM's
+    pub(crate) depth: usize,
+}
+
+///Outcome of executing a statement, non-`Normal` completions unwind through enclosing blocks
+///until something (a function call, the top level) handles them
+#[derive(Clone, Debug)]
+pub(crate) enum Completion {
+    Normal,
+    Return(LiteralType),
+    ///Carries the label named by `break label;`, if any
+    Break(Option<String>),
+    Continue(Option<String>),
 }
 
-mod interpret_ir;
+///Evaluation either produces a value or aborts the whole program with the first error
+pub(crate) type RuntimeResult<T> = Result<T, RuntimeError>;
+
 mod interpreter_impl;
 mod interpreter_traits;
 
-impl interpreter_traits::Visitable<LiteralType> for Expression {
-    fn accept(&mut self, visitor: &mut dyn InterpreterVisitor<LiteralType>) -> LiteralType {
+impl<T> interpreter_traits::Visitable<T> for Expression {
+    fn accept(&mut self, visitor: &mut dyn InterpreterVisitor<T>) -> T {
         match self {
             Expression::Binary(bin) => bin.accept(visitor),
             Expression::Literal(lit) => lit.accept(visitor),
@@ -29,6 +53,17 @@ impl interpreter_traits::Visitable<LiteralType> for Expression {
             Expression::Logical(logic) => logic.accept(visitor),
             Expression::Call(call) => call.accept(visitor),
             Expression::Lambda(lambda) => lambda.accept(visitor),
+            Expression::Interpolation(interpolation) => interpolation.accept(visitor),
+            Expression::List(list) => list.accept(visitor),
+            Expression::Map(map) => map.accept(visitor),
+            Expression::Index(index) => index.accept(visitor),
+            Expression::SetIndex(set) => set.accept(visitor),
+            Expression::Compound(compound) => compound.accept(visitor),
+            Expression::Slice(slice) => slice.accept(visitor),
+            Expression::Get(get) => get.accept(visitor),
+            Expression::Set(set) => set.accept(visitor),
+            Expression::This(this) => this.accept(visitor),
+            Expression::Super(sup) => sup.accept(visitor),
         }
     }
 }
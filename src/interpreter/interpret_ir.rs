@@ -1,8 +0,0 @@
-// use super::Interpreter;
-// use crate::ir::OpCode as IrBlock;
-
-// impl Interpreter {
-// pub fn interpret_ir(self, ir: Vec<IrBlock>) {
-// dbg!(ir);
-// }
-// }
@@ -1,14 +1,20 @@
 use crate::{
-    enviroment::Enviroment,
+    diagnostics::codes,
+    enviroment::{
+        Enviroment,
+        EnviromentRef,
+    },
     interpreter::{
+        Completion,
         Interpreter,
         InterpreterVisitor,
+        RuntimeResult,
     },
     types::{
         Expression,
         LiteralType,
+        RuntimeError,
         TokenType,
-        token::Token,
         expression::{
             Call,
             *,
@@ -16,303 +22,646 @@ use crate::{
         lux_functions::{
             Callable as CallableTrait,
             Functions,
+            class::LuxInstance,
             clock::Clock,
+            list::{
+                self,
+                Len,
+                Pop,
+                Push,
+            },
+            map::{
+                self,
+                Has,
+                Keys,
+                MapKey,
+                Remove,
+            },
             print::{
                 Print,
                 Println,
             },
+            user::UserFunction,
         },
         statement::{
-            ReturnStatement,
+            FunctionStatement,
             Statement,
         },
-    },
-};
-use rand_chacha::{
-    self,
-    rand_core::{
-        RngCore,
-        SeedableRng,
+        token::Token,
     },
 };
 use std::{
-    collections::HashMap,
-    time::SystemTime,
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::Rc,
 };
 // fun -> LiteralType | fun
 
 impl Interpreter {
-    pub(crate) fn evaluate(&mut self, expr: &mut Expression) -> LiteralType {
+    pub(crate) fn evaluate(&mut self, expr: &mut Expression) -> RuntimeResult<LiteralType> {
         use crate::interpreter::interpreter_traits::Visitable;
         expr.accept(self)
     }
 
     pub(crate) fn new() -> Interpreter {
-        let map = HashMap::new();
-        let mut globals = Enviroment {
-            enclosing: None,
-            variable_map: map,
-        };
+        let globals = Enviroment::new(None);
         //Inject built-ins (native functions) into enviroment
         let clock = Functions::Clock(Clock {});
         let print = Functions::Print(Print {});
         let println = Functions::Println(Println {});
+        let len = Functions::Len(Len {});
+        let push = Functions::Push(Push {});
+        let pop = Functions::Pop(Pop {});
+        let has = Functions::Has(Has {});
+        let remove = Functions::Remove(Remove {});
+        let keys = Functions::Keys(Keys {});
 
-        globals.define("clock", LiteralType::Callable(clock));
-        globals.define("print", LiteralType::Callable(print));
-        globals.define("println", LiteralType::Callable(println));
+        {
+            let mut globals = globals.borrow_mut();
+            globals.define("clock", LiteralType::Callable(clock));
+            globals.define("print", LiteralType::Callable(print));
+            globals.define("println", LiteralType::Callable(println));
+            globals.define("len", LiteralType::Callable(len));
+            globals.define("push", LiteralType::Callable(push));
+            globals.define("pop", LiteralType::Callable(pop));
+            globals.define("has", LiteralType::Callable(has));
+            globals.define("remove", LiteralType::Callable(remove));
+            globals.define("keys", LiteralType::Callable(keys));
+        }
 
-        let enviroment = Box::new(globals.clone());
         Interpreter {
-            enviroment,
+            enviroment: Rc::clone(&globals),
             globals,
+            captured: None,
+            depth: 0,
         }
     }
 
-    ///Hand over between the Parser and the Interpreter
-    pub(crate) fn execute(&mut self, mut statement: Statement) -> Option<Expression> {
-        use crate::parser::statement::Visitable as ParserVisitable;
-        //If we come back up and it's a return statement then return the expression
-        if let Statement::Return(ReturnStatement {
-            keyword: _token,
-            value: expr,
-        }) = statement.accept(self)
-        {
-            // println!("Returned from second: {:?}", expr);
-            return expr;
+    ///Where `print` and `println` send their text
+    pub(crate) fn output(&mut self, text: &str) {
+        match self.captured {
+            Some(ref mut captured) => captured.push_str(text),
+            None => print!("{text}"),
         }
-        None
     }
 
-    pub(crate) fn execute_block_in_env(
-        statements: Vec<Statement>,
-        enviroment: Enviroment,
-    ) -> Option<Expression> {
-        //Wrap
-        let mut temporary_int: Interpreter = Interpreter::new();
-        temporary_int.enviroment = Box::new(enviroment);
-
-        //Execute
-        for statement in statements {
-            temporary_int.execute(statement);
+    ///Calls any callable value, `for-in` loops also use this to step user iterators
+    pub(crate) fn call_value(
+        &mut self,
+        callee: LiteralType,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let mut function: Box<dyn CallableTrait> = match callee {
+            LiteralType::Callable(function) => match function {
+                Functions::Print(function) => Box::new(function),
+                Functions::Clock(function) => Box::new(function),
+                Functions::User(function) => Box::new(function),
+                Functions::Println(function) => Box::new(function),
+                Functions::Class(class) => Box::new(class),
+                Functions::Len(function) => Box::new(function),
+                Functions::Push(function) => Box::new(function),
+                Functions::Pop(function) => Box::new(function),
+                Functions::Has(function) => Box::new(function),
+                Functions::Remove(function) => Box::new(function),
+                Functions::Keys(function) => Box::new(function),
+            },
+            _ => {
+                return Err(RuntimeError::coded(paren, &codes::NOT_CALLABLE));
+            }
+        };
+
+        let arity: u64 = function.arity();
+        if arity
+            != arguments
+                .len()
+                .try_into()
+                .expect("Expected a length in u64 range")
+        {
+            return Err(RuntimeError::new(
+                paren,
+                &codes::WRONG_ARGUMENT_COUNT,
+                format!("Expected {} arguments but got {}.", arity, arguments.len()),
+            ));
         }
+        function.call(self, paren, arguments)
+    }
 
-        None
+    ///Hand over between the Parser and the Interpreter
+    pub(crate) fn execute(&mut self, statement: &mut Statement) -> RuntimeResult<Completion> {
+        use crate::parser::statement::Visitable as ParserVisitable;
+        statement.accept(self)
     }
-    pub(crate) fn execute_block(&mut self, statements: Vec<Statement>) -> Option<ReturnStatement> {
-        //Wrap
-        self.enviroment = Box::new(Enviroment {
-            enclosing: Some(self.enviroment.clone()),
-            variable_map: HashMap::new(),
-        });
 
-        //Execute
+    ///Runs `statements` inside `enviroment`, restoring the current enviroment afterwards even if
+    ///one of them fails
+    pub(crate) fn execute_block_in_env(
+        &mut self,
+        statements: &mut [Statement],
+        enviroment: EnviromentRef,
+    ) -> RuntimeResult<Completion> {
+        let previous = std::mem::replace(&mut self.enviroment, enviroment);
+
+        let mut completion = Ok(Completion::Normal);
         for statement in statements {
-            self.execute(statement);
+            completion = self.execute(statement);
+            if !matches!(completion, Ok(Completion::Normal)) {
+                break;
+            }
         }
 
-        self.enviroment = self.enviroment.enclosing.clone().unwrap();
-        None
+        self.enviroment = previous;
+        completion
     }
-}
 
-///Logic for how the Interpreter acts with each operator or Token
-impl InterpreterVisitor<LiteralType> for Interpreter {
-    fn visit_binary(&mut self, bin: &mut Binary) -> LiteralType {
-        let left = self.evaluate(&mut bin.left);
-        let right = self.evaluate(&mut bin.right);
-        let operator = &bin.operator;
+    pub(crate) fn execute_block(
+        &mut self,
+        statements: &mut [Statement],
+    ) -> RuntimeResult<Completion> {
+        let enviroment = Enviroment::new(Some(Rc::clone(&self.enviroment)));
+        self.execute_block_in_env(statements, enviroment)
+    }
 
+    ///Applies a binary arithmetic, comparison or equality operator
+    fn operate(
+        left: LiteralType,
+        operator: &Token,
+        right: LiteralType,
+    ) -> RuntimeResult<LiteralType> {
         //We can abstract all this logic away to rust's traits
-        match operator.token_type {
+        let result = match operator.token_type {
             TokenType::Plus => left + right,
             TokenType::Star => left * right,
             TokenType::Slash => left / right,
             TokenType::Minus => left - right,
-            TokenType::Greater => LiteralType::Boolean(left > right),
-            TokenType::GreaterEqual => LiteralType::Boolean(left >= right),
-            TokenType::Less => LiteralType::Boolean(left < right),
-            TokenType::LessEqual => LiteralType::Boolean(left <= right),
-            TokenType::EqualEqual => LiteralType::Boolean(left == right),
-            TokenType::BangEqual => LiteralType::Boolean(left != right),
-            _ => {
-                crate::error(
-                    operator.line,
-                    "Operator not defined for this operation".to_string(),
-                );
-                LiteralType::Nil
-            }
+            TokenType::Percent => left % right,
+            TokenType::StarStar => left.power(right),
+            TokenType::Greater => left.greater(&right),
+            TokenType::GreaterEqual => left.greater_equal(&right),
+            TokenType::Less => left.less(&right),
+            TokenType::LessEqual => left.less_equal(&right),
+            TokenType::EqualEqual => Ok(LiteralType::Boolean(left == right)),
+            TokenType::BangEqual => Ok(LiteralType::Boolean(left != right)),
+            _ => unreachable!("The parser only builds binary expressions from binary operators"),
+        };
+
+        result.map_err(|code| RuntimeError::coded(operator, code))
+    }
+
+    ///Applies a compound assignment's operator, `++` and `--` only step numbers
+    fn apply(
+        step: bool,
+        old: LiteralType,
+        operator: &Token,
+        right: LiteralType,
+    ) -> RuntimeResult<LiteralType> {
+        if step && !matches!(old, LiteralType::Number(_)) {
+            return Err(RuntimeError::coded(operator, &codes::INVALID_STEP));
         }
+        Self::operate(old, operator, right)
     }
-    fn visit_grouping(&mut self, group: &mut Grouping) -> LiteralType {
-        self.evaluate(&mut group.expression)
+
+    fn assign_variable(
+        &mut self,
+        name: &Token,
+        binding: Option<Binding>,
+        value: LiteralType,
+    ) -> RuntimeResult<()> {
+        if let Some(binding) = binding {
+            self.enviroment
+                .borrow_mut()
+                .assign_at(binding.depth, binding.slot, value);
+        } else {
+            self.globals
+                .borrow_mut()
+                .assign(&name.lexeme, value)
+                .map_err(|failure| RuntimeError::failed(name, failure))?;
+        }
+        Ok(())
     }
-    fn visit_literal(&mut self, lit: &mut Literal) -> LiteralType {
-        lit.value.clone()
+
+    fn key_operand(key: &LiteralType, bracket: &Token) -> RuntimeResult<MapKey> {
+        MapKey::try_from(key).map_err(|failure| RuntimeError::failed(bracket, failure))
     }
-    fn visit_ternary(&mut self, tern: &mut Ternary) -> LiteralType {
-        let evaluator = self.evaluate(&mut tern.evaluator);
-        let left = &mut tern.left;
-        let right = &mut tern.right;
 
-        match evaluator {
-            LiteralType::Boolean(truthy) => {
-                if truthy {
-                    self.evaluate(left)
-                } else {
-                    self.evaluate(right)
-                }
-            }
-            _ => evaluator,
+    fn index_operand(index: LiteralType, length: usize, bracket: &Token) -> RuntimeResult<usize> {
+        match index {
+            LiteralType::Number(index) => list::position(index, length)
+                .map_err(|failure| RuntimeError::failed(bracket, failure)),
+            _ => Err(RuntimeError::new(
+                bracket,
+                &codes::INVALID_INDEX,
+                "List index must be a number.",
+            )),
+        }
+    }
+
+    ///Conditions must be booleans, `source` is the token the error is reported on
+    pub(crate) fn evaluate_condition(
+        &mut self,
+        condition: &mut Expression,
+        source: &Token,
+    ) -> RuntimeResult<bool> {
+        match self.evaluate(condition)? {
+            LiteralType::Boolean(truthy) => Ok(truthy),
+            _ => Err(RuntimeError::new(
+                source,
+                &codes::BOOLEAN_REQUIRED,
+                "Condition must be a boolean.",
+            )
+            .with_note("rlux has no truthiness, conditions must be `true` or `false`")),
+        }
+    }
+}
+
+///Logic for how the Interpreter acts with each operator or Token
+impl InterpreterVisitor<RuntimeResult<LiteralType>> for Interpreter {
+    fn visit_binary(&mut self, bin: &mut Binary) -> RuntimeResult<LiteralType> {
+        let left = self.evaluate(&mut bin.left)?;
+        let right = self.evaluate(&mut bin.right)?;
+        Self::operate(left, &bin.operator, right)
+    }
+    fn visit_grouping(&mut self, group: &mut Grouping) -> RuntimeResult<LiteralType> {
+        self.evaluate(&mut group.expression)
+    }
+    fn visit_literal(&mut self, lit: &mut Literal) -> RuntimeResult<LiteralType> {
+        Ok(lit.value.clone())
+    }
+    fn visit_ternary(&mut self, tern: &mut Ternary) -> RuntimeResult<LiteralType> {
+        if self.evaluate_condition(&mut tern.evaluator, &tern.question)? {
+            self.evaluate(&mut tern.left)
+        } else {
+            self.evaluate(&mut tern.right)
         }
     }
-    fn visit_unary(&mut self, unary: &mut Unary) -> LiteralType {
-        let right = self.evaluate(&mut unary.operand);
+    fn visit_unary(&mut self, unary: &mut Unary) -> RuntimeResult<LiteralType> {
+        let right = self.evaluate(&mut unary.operand)?;
 
         match unary.operator.token_type {
             TokenType::Minus => match right {
-                LiteralType::Number(num) => LiteralType::Number(-num),
-                _ => right,
+                LiteralType::Number(num) => Ok(LiteralType::Number(-num)),
+                _ => Err(RuntimeError::coded(
+                    &unary.operator,
+                    &codes::OPERAND_MUST_BE_NUMBER,
+                )),
             },
             TokenType::Bang => match right {
-                LiteralType::Boolean(boolean) => LiteralType::Boolean(!boolean),
-                _ => right,
+                LiteralType::Boolean(boolean) => Ok(LiteralType::Boolean(!boolean)),
+                _ => Err(RuntimeError::new(
+                    &unary.operator,
+                    &codes::BOOLEAN_REQUIRED,
+                    "Operand must be a boolean.",
+                )),
             },
-            _ => {
-                crate::error(
-                    unary.operator.line,
-                    "Operator not defined for this operation".to_string(),
-                );
-                LiteralType::Nil
-            }
+            _ => unreachable!("Parser only builds unary expressions from unary operators"),
         }
     }
 
-    fn visit_variable(&mut self, var: &mut Variable) -> LiteralType {
-        //!Returns the value of a variable, will return NIL if nothing is found
-        let name = &var.name.lexeme.clone();
-        let result: Result<LiteralType, std::env::VarError> = self.enviroment.get(name).cloned();
-        // println!("{:?}", self.enviroment);
-        if let Ok(item) = result {
-            item
-        } else {
-            //TODO: This branch keeps getting called twice, need to investigate why
-            //Nothing was found so we return nothing
-            // println!("{:?}",self.enviroment.clone());
-            crate::error(
-                var.name.line,
-                "Variable not found: ".to_owned() + &var.name.lexeme,
-            );
-            LiteralType::Nil
+    fn visit_variable(&mut self, var: &mut Variable) -> RuntimeResult<LiteralType> {
+        //!Returns the value of a variable, fails if nothing is found
+        if let Some(binding) = var.binding {
+            return Ok(self.enviroment.borrow().get_at(binding.depth, binding.slot));
         }
-    }
 
-    fn visit_assignment(&mut self, assign: &mut Assignment) -> LiteralType {
-        //Decompose assignment to avoid excess cloning
-        let (name, value) = (assign.name.clone(), &mut assign.value);
+        //Anything the resolver couldn't place is a global
+        self.globals.borrow().get(&var.name.lexeme).map_err(|_| {
+            RuntimeError::new(
+                &var.name,
+                &codes::UNDEFINED_VARIABLE,
+                "Variable not found: ".to_owned() + &var.name.lexeme,
+            )
+        })
+    }
 
+    fn visit_assignment(&mut self, assign: &mut Assignment) -> RuntimeResult<LiteralType> {
         //Evaluate expression inside
-        let value = self.evaluate(value);
+        let value = self.evaluate(&mut assign.value)?;
 
         //Copy the value then echo out for the rest of the syntax tress
-        self.enviroment
-            .assign(&name.lexeme, value.clone(), name.line);
+        self.assign_variable(&assign.name, assign.binding, value.clone())?;
 
-        value
+        Ok(value)
     }
 
-    fn visit_logical(&mut self, logical: &mut Logical) -> LiteralType {
-        let left: LiteralType = self.evaluate(&mut logical.left);
-
-        let left_bool = match left {
+    fn visit_logical(&mut self, logical: &mut Logical) -> RuntimeResult<LiteralType> {
+        let left_bool = match self.evaluate(&mut logical.left)? {
             LiteralType::Boolean(val) => val,
-            _ => panic!("Cannot coerce Non-Boolean to Boolean"),
+            _ => {
+                return Err(RuntimeError::new(
+                    &logical.operator,
+                    &codes::BOOLEAN_REQUIRED,
+                    "Operands must be booleans.",
+                )
+                .with_note("rlux has no truthiness, `and` and `or` only work on booleans"));
+            }
         };
 
         //Short Cirucuit if we can
         if logical.operator.token_type == TokenType::Or {
             // True or X will alway be True, so if True, then return True
             if left_bool {
-                return LiteralType::Boolean(left_bool);
+                return Ok(LiteralType::Boolean(left_bool));
             }
         } else {
             // False AND X will always be False, so return False if is_and && is_false
             if !(left_bool) {
-                return LiteralType::Boolean(left_bool);
+                return Ok(LiteralType::Boolean(left_bool));
             }
         }
 
         //traverse it otherwise
         self.evaluate(&mut logical.right)
     }
-    fn visit_call(&mut self, call: &mut Call) -> LiteralType {
-        //Taking Ownership here isn't a bad thing because we are decomposing to produce an output,
-        //plus the original data is still stored in a file
-        let deref = call.clone();
-        let (paren, mut callee, mut arguments) = (deref.paren, deref.callee, deref.arguments);
-        let callee: LiteralType = self.evaluate(&mut callee);
-        let error_line = paren.line;
+    fn visit_call(&mut self, call: &mut Call) -> RuntimeResult<LiteralType> {
+        let callee: LiteralType = self.evaluate(&mut call.callee)?;
+
+        //Arguments are evaluated exactly once, left to right, before the call happens
         let mut eval_args = vec![];
-        for argument in &mut arguments {
-            eval_args.push(self.evaluate(argument));
+        for argument in &mut call.arguments {
+            eval_args.push(self.evaluate(argument)?);
         }
 
-        let function: Option<Box<dyn CallableTrait>> = match callee {
-            LiteralType::Callable(function) => match function {
-                Functions::Print(function) => Some(Box::new(function)),
-                Functions::Clock(function) => Some(Box::new(function)),
-                Functions::User(function) => Some(Box::new(function)),
-                Functions::Println(function) => Some(Box::new(function)),
-            },
-            _ => None,
-        };
+        self.call_value(callee, &call.paren, eval_args)
+    }
+
+    fn visit_interpolation(
+        &mut self,
+        interpolation: &mut Interpolation,
+    ) -> RuntimeResult<LiteralType> {
+        //! Every part is formatted the way `print` shows it, so any value can be embedded
+        let mut result = String::new();
+        for part in &mut interpolation.parts {
+            result.push_str(&self.evaluate(part)?.to_string());
+        }
+        Ok(LiteralType::String(result))
+    }
+
+    fn visit_list(&mut self, list: &mut List) -> RuntimeResult<LiteralType> {
+        let mut elements = vec![];
+        for element in &mut list.elements {
+            elements.push(self.evaluate(element)?);
+        }
+        Ok(LiteralType::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_map(&mut self, map: &mut Map) -> RuntimeResult<LiteralType> {
+        //!Later entries win when a key is written twice
+        let mut entries = BTreeMap::new();
+        for (key, value) in &mut map.entries {
+            let key = self.evaluate(key)?;
+            let value = self.evaluate(value)?;
+            entries.insert(Self::key_operand(&key, &map.brace)?, value);
+        }
+        Ok(LiteralType::Map(Rc::new(RefCell::new(entries))))
+    }
+
+    fn visit_index(&mut self, index: &mut Index) -> RuntimeResult<LiteralType> {
+        let object = self.evaluate(&mut index.object)?;
+        let position = self.evaluate(&mut index.index)?;
+
+        match object {
+            LiteralType::List(elements) => {
+                let position =
+                    Self::index_operand(position, elements.borrow().len(), &index.bracket)?;
+                Ok(elements.borrow()[position].clone())
+            }
+            LiteralType::Map(map) => {
+                let key = Self::key_operand(&position, &index.bracket)?;
+                let value = map.borrow().get(&key).cloned();
+                value.ok_or_else(|| RuntimeError::failed(&index.bracket, map::missing(&key)))
+            }
+            _ => Err(RuntimeError::coded(&index.bracket, &codes::NOT_INDEXABLE)),
+        }
+    }
+
+    fn visit_setindex(&mut self, set: &mut SetIndex) -> RuntimeResult<LiteralType> {
+        let object = self.evaluate(&mut set.object)?;
+        let position = self.evaluate(&mut set.index)?;
+        let value = self.evaluate(&mut set.value)?;
+
+        match object {
+            LiteralType::List(elements) => {
+                let position =
+                    Self::index_operand(position, elements.borrow().len(), &set.bracket)?;
+                elements.borrow_mut()[position] = value.clone();
+            }
+            //Assigning to a missing key adds it
+            LiteralType::Map(map) => {
+                let key = Self::key_operand(&position, &set.bracket)?;
+                map.borrow_mut().insert(key, value.clone());
+            }
+            _ => {
+                return Err(RuntimeError::coded(&set.bracket, &codes::NOT_INDEXABLE));
+            }
+        }
+        Ok(value)
+    }
+
+    fn visit_compound(&mut self, compound: &mut Compound) -> RuntimeResult<LiteralType> {
+        //!Reads the target, applies the operator and writes the result back, only evaluating the
+        //!target's object and index once
+        let operator = &compound.operator;
+        let step = compound.step;
+        let value = &mut compound.value;
+        let (old, new) = match &mut compound.target {
+            Expression::Variable(var) => {
+                let old = self.visit_variable(var)?;
+                let right = self.evaluate(value)?;
+                let new = Self::apply(step, old.clone(), operator, right)?;
+                self.assign_variable(&var.name, var.binding, new.clone())?;
+                (old, new)
+            }
+            Expression::Get(get) => {
+                let LiteralType::Instance(instance) = self.evaluate(&mut get.object)? else {
+                    return Err(RuntimeError::new(
+                        &get.name,
+                        &codes::NOT_AN_INSTANCE,
+                        "Only instances have fields.",
+                    ));
+                };
+                let old = LuxInstance::get(&instance, &get.name.lexeme).ok_or_else(|| {
+                    RuntimeError::new(
+                        &get.name,
+                        &codes::UNDEFINED_PROPERTY,
+                        format!("Undefined property '{}'.", get.name.lexeme),
+                    )
+                })?;
+                let right = self.evaluate(value)?;
+                let new = Self::apply(step, old.clone(), operator, right)?;
+                instance.borrow_mut().set(&get.name.lexeme, new.clone());
+                (old, new)
+            }
+            Expression::Index(index) => {
+                let object = self.evaluate(&mut index.object)?;
+                let position = self.evaluate(&mut index.index)?;
+                let bracket = &index.bracket;
 
-        if let Some(func) = function {
-            let mut function = func;
-            let arity: u64 = function.arity();
-            if arity
-                != eval_args
-                    .len()
-                    .try_into()
-                    .expect("Expected a length in u64 range")
-            {
-                crate::error(
-                    error_line,
-                    format!("Expected {} but got {}", arity, eval_args.len()),
-                );
+                match object {
+                    LiteralType::List(elements) => {
+                        let at = Self::index_operand(
+                            position.clone(),
+                            elements.borrow().len(),
+                            bracket,
+                        )?;
+                        let old = elements.borrow()[at].clone();
+                        let right = self.evaluate(value)?;
+                        let new = Self::apply(step, old.clone(), operator, right)?;
+                        //The value may have resized the list, so check the index again
+                        let at = Self::index_operand(position, elements.borrow().len(), bracket)?;
+                        elements.borrow_mut()[at] = new.clone();
+                        (old, new)
+                    }
+                    LiteralType::Map(map) => {
+                        let key = Self::key_operand(&position, bracket)?;
+                        let old = map.borrow().get(&key).cloned();
+                        let old =
+                            old.ok_or_else(|| RuntimeError::failed(bracket, map::missing(&key)))?;
+                        let right = self.evaluate(value)?;
+                        let new = Self::apply(step, old.clone(), operator, right)?;
+                        map.borrow_mut().insert(key, new.clone());
+                        (old, new)
+                    }
+                    _ => {
+                        return Err(RuntimeError::coded(bracket, &codes::NOT_INDEXABLE));
+                    }
+                }
             }
-            let result = function.call(self, arguments);
-            if let Some(mut to_eval) = result {
-                self.evaluate(&mut to_eval)
-            } else {
-                LiteralType::Nil
+            _ => unreachable!("The parser only builds compound assignments to assignable targets"),
+        };
+
+        Ok(if compound.postfix { old } else { new })
+    }
+
+    fn visit_slice(&mut self, slice: &mut Slice) -> RuntimeResult<LiteralType> {
+        let object = self.evaluate(&mut slice.object)?;
+        let mut bounds = [None, None];
+        for (bound, expression) in bounds.iter_mut().zip([&mut slice.start, &mut slice.end]) {
+            if let Some(expression) = expression {
+                match self.evaluate(expression)? {
+                    LiteralType::Number(number) => *bound = Some(number),
+                    //Same as leaving the bound out, the VM relies on this
+                    LiteralType::Nil => {}
+                    _ => {
+                        return Err(RuntimeError::new(
+                            &slice.bracket,
+                            &codes::INVALID_INDEX,
+                            "Slice bounds must be numbers.",
+                        ));
+                    }
+                }
             }
+        }
+
+        let LiteralType::List(elements) = object else {
+            return Err(RuntimeError::new(
+                &slice.bracket,
+                &codes::NOT_INDEXABLE,
+                "Can only slice lists.",
+            ));
+        };
+        let elements = elements.borrow();
+        let range = list::slice_bounds(bounds[0], bounds[1], elements.len())
+            .map_err(|failure| RuntimeError::failed(&slice.bracket, failure))?;
+        //Slices are copies, changing one leaves the original alone
+        Ok(LiteralType::List(Rc::new(RefCell::new(
+            elements[range].to_vec(),
+        ))))
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda) -> RuntimeResult<LiteralType> {
+        //! Lambdas become ordinary user functions that close over the enviroment they appear in
+        let declaration = FunctionStatement {
+            keyword: Some(lambda.keyword.clone()),
+            //Not a valid identifier, so a lambda can never shadow a user variable
+            name: Token {
+                token_type: TokenType::Identifier,
+                lexeme: String::from("<lambda>"),
+                literal: None,
+                span: lambda.keyword.span,
+            },
+            body: lambda.body.clone(),
+            parameters: lambda.parameters.clone(),
+            span: lambda.span,
+        };
+
+        Ok(LiteralType::Callable(Functions::User(UserFunction {
+            closure: Rc::clone(&self.enviroment),
+            declaration: Box::new(declaration),
+            is_initializer: false,
+        })))
+    }
+
+    fn visit_get(&mut self, get: &mut Get) -> RuntimeResult<LiteralType> {
+        let object = self.evaluate(&mut get.object)?;
+
+        if let LiteralType::Instance(instance) = object {
+            LuxInstance::get(&instance, &get.name.lexeme).ok_or_else(|| {
+                RuntimeError::new(
+                    &get.name,
+                    &codes::UNDEFINED_PROPERTY,
+                    format!("Undefined property '{}'.", get.name.lexeme),
+                )
+            })
+        } else {
+            Err(RuntimeError::coded(&get.name, &codes::NOT_AN_INSTANCE))
+        }
+    }
+
+    fn visit_set(&mut self, set: &mut Set) -> RuntimeResult<LiteralType> {
+        let object = self.evaluate(&mut set.object)?;
+
+        if let LiteralType::Instance(instance) = object {
+            let value = self.evaluate(&mut set.value)?;
+            instance.borrow_mut().set(&set.name.lexeme, value.clone());
+            Ok(value)
         } else {
-            LiteralType::Nil
+            Err(RuntimeError::new(
+                &set.name,
+                &codes::NOT_AN_INSTANCE,
+                "Only instances have fields.",
+            ))
         }
     }
 
-    fn visit_lambda(&mut self, lambda: &mut Lambda) -> LiteralType {
-        let mut rand = rand_chacha::ChaCha8Rng::seed_from_u64(
-            SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .expect("Failure converting from SystemTime")
-                .as_nanos() as u64,
-        );
-        let string_buf: &mut [u8] = &mut [0; 4];
-        rand.fill_bytes(string_buf);
-        let name: String = String::from_utf8_lossy(string_buf).to_string();
-        let name = Token {
-            token_type: TokenType::Identifier,
-            lexeme: name,
-            literal: None,
-            line: lambda.paren.line,
+    fn visit_this(&mut self, this: &mut This) -> RuntimeResult<LiteralType> {
+        match this.binding {
+            Some(binding) => Ok(self.enviroment.borrow().get_at(binding.depth, binding.slot)),
+            None => Err(RuntimeError::coded(&this.keyword, &codes::INVALID_THIS)),
+        }
+    }
+
+    fn visit_super(&mut self, sup: &mut Super) -> RuntimeResult<LiteralType> {
+        //`super` is injected by the class declaration, `this` by method binding one scope below
+        let Some(binding) = sup.binding else {
+            return Err(RuntimeError::new(
+                &sup.keyword,
+                &codes::INVALID_SUPER,
+                "Can't use 'super' in a class with no superclass.",
+            ));
         };
-        let (paren, mut arguments) = (lambda.paren.clone(), lambda.arguments.clone());
-        let mut eval_args = vec![];
-        for argument in &mut arguments {
-            eval_args.push(self.evaluate(argument));
+
+        let enviroment = self.enviroment.borrow();
+        let (superclass, instance) = match (
+            enviroment.get_at(binding.depth, binding.slot),
+            enviroment.get_at(binding.depth - 1, 0),
+        ) {
+            (
+                LiteralType::Callable(Functions::Class(superclass)),
+                LiteralType::Instance(instance),
+            ) => (superclass, instance),
+            _ => unreachable!("The resolver only binds super inside subclass methods"),
+        };
+
+        if let Some(method) = superclass.find_method(&sup.method.lexeme) {
+            Ok(LiteralType::Callable(Functions::User(
+                method.bind(instance),
+            )))
+        } else {
+            Err(RuntimeError::new(
+                &sup.method,
+                &codes::UNDEFINED_PROPERTY,
+                format!("Undefined property '{}'.", sup.method.lexeme),
+            ))
         }
-        todo!("Lambdas");
     }
 }
@@ -1,17 +1,14 @@
-use crate::types::{
-    LiteralType,
-    expression::{
-        Call,
-        *,
-    },
+use crate::types::expression::{
+    Call,
+    *,
 };
 
-///Shorthand to internally generate accept() functions for the Enum Variant and type passed in,
-///internally will result in performing the corresponding instruction
+///Shorthand to internally generate accept() functions for the Enum Variant passed in, for any
+///visitor output type, internally will result in performing the corresponding instruction
 macro_rules! visitable_trait {
-    ($trait_type:ty,$enum_variant:ty, $enum_parent:ty) => {
-        impl Visitable<$trait_type> for $enum_variant {
-            fn accept(&mut self, visitor: &mut dyn InterpreterVisitor<$trait_type>) -> $trait_type {
+    ($enum_variant:ty) => {
+        impl<T> Visitable<T> for $enum_variant {
+            fn accept(&mut self, visitor: &mut dyn InterpreterVisitor<T>) -> T {
                 paste::item! {visitor.[<visit_ $enum_variant:lower>](self)}
             }
         }
@@ -30,19 +27,41 @@ pub(crate) trait InterpreterVisitor<T> {
     fn visit_logical(&mut self, logical: &mut Logical) -> T;
     fn visit_call(&mut self, call: &mut Call) -> T;
     fn visit_lambda(&mut self, lambda: &mut Lambda) -> T;
+    fn visit_interpolation(&mut self, interpolation: &mut Interpolation) -> T;
+    fn visit_list(&mut self, list: &mut List) -> T;
+    fn visit_map(&mut self, map: &mut Map) -> T;
+    fn visit_index(&mut self, index: &mut Index) -> T;
+    fn visit_setindex(&mut self, set: &mut SetIndex) -> T;
+    fn visit_compound(&mut self, compound: &mut Compound) -> T;
+    fn visit_slice(&mut self, slice: &mut Slice) -> T;
+    fn visit_get(&mut self, get: &mut Get) -> T;
+    fn visit_set(&mut self, set: &mut Set) -> T;
+    fn visit_this(&mut self, this: &mut This) -> T;
+    fn visit_super(&mut self, sup: &mut Super) -> T;
 }
 
 pub(crate) trait Visitable<T> {
     fn accept(&mut self, visitor: &mut dyn InterpreterVisitor<T>) -> T;
 }
 
-visitable_trait! {LiteralType,Binary,Expression}
-visitable_trait! {LiteralType,Literal,Expression}
-visitable_trait! {LiteralType,Grouping,Expression}
-visitable_trait! {LiteralType,Unary,Expression}
-visitable_trait! {LiteralType,Ternary,Expression}
-visitable_trait! {LiteralType,Variable,Expression}
-visitable_trait! {LiteralType,Assignment,Expression}
-visitable_trait! {LiteralType,Logical,Expression}
-visitable_trait! {LiteralType,Call,Expression}
-visitable_trait! {LiteralType,Lambda,Expression}
+visitable_trait! {Binary}
+visitable_trait! {Literal}
+visitable_trait! {Grouping}
+visitable_trait! {Unary}
+visitable_trait! {Ternary}
+visitable_trait! {Variable}
+visitable_trait! {Assignment}
+visitable_trait! {Logical}
+visitable_trait! {Call}
+visitable_trait! {Lambda}
+visitable_trait! {Interpolation}
+visitable_trait! {List}
+visitable_trait! {Map}
+visitable_trait! {Index}
+visitable_trait! {SetIndex}
+visitable_trait! {Compound}
+visitable_trait! {Slice}
+visitable_trait! {Get}
+visitable_trait! {Set}
+visitable_trait! {This}
+visitable_trait! {Super}
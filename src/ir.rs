@@ -0,0 +1,12 @@
+//! Compiled backends: the resolved syntax tree is either compiled into chunks of `OpCode`s which a
+//! stack based virtual machine then executes, avoiding the tree-walker's per-node cloning and
+//! lookups, or lowered to textual LLVM IR for `llc`
+
+pub(crate) mod chunk;
+pub(crate) mod compiler;
+pub(crate) mod llvm;
+pub(crate) mod opcode;
+pub(crate) mod value;
+pub(crate) mod vm;
+
+pub(crate) use opcode::OpCode;
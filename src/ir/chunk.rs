@@ -0,0 +1,31 @@
+use super::{
+    OpCode,
+    value::Value,
+};
+use crate::types::span::Span;
+
+///A compiled block of bytecode along with everything it refers to
+#[derive(Default)]
+pub(crate) struct Chunk {
+    pub(crate) code: Vec<OpCode>,
+    pub(crate) constants: Vec<Value>,
+    ///Source span for each instruction in `code`, used when reporting runtime errors
+    pub(crate) spans: Vec<Span>,
+}
+
+impl Chunk {
+    ///Appends an instruction, returning its offset so jumps can be patched later
+    pub(crate) fn write(&mut self, op: OpCode, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    ///Adds a value to the constant pool, returning its index
+    pub(crate) fn add_constant(&mut self, value: Value) -> u32 {
+        self.constants.push(value);
+        (self.constants.len() - 1)
+            .try_into()
+            .expect("Constant pool exceeded u32 range")
+    }
+}
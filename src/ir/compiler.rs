@@ -0,0 +1,1107 @@
+use super::{
+    OpCode,
+    chunk::Chunk,
+    value::{
+        Function,
+        UpvalueDescriptor,
+        Value,
+    },
+};
+use crate::{
+    interpreter::{
+        InterpreterVisitor,
+        Visitable,
+    },
+    parser::statement::{
+        StatementVisitor,
+        Visitable as StatementVisitable,
+    },
+    types::{
+        Expression,
+        LiteralType,
+        TokenType,
+        expression::{
+            Call,
+            *,
+        },
+        span::Span,
+        statement::*,
+        token::Token,
+    },
+};
+use std::{
+    collections::HashMap,
+    rc::Rc,
+};
+
+///What kind of function body is being compiled, decides what slot 0 holds and what an implicit
+///return hands back
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+///A local variable, `depth` is `None` until its initializer has been compiled
+struct Local {
+    name: String,
+    depth: Option<usize>,
+    captured: bool,
+}
+
+///A loop whose body is being compiled, `break` and `continue` jumps are patched once its end and
+///increment are known
+struct Loop {
+    label: Option<String>,
+    ///Locals past this many were declared inside the loop and are dropped by a jump out of it
+    locals: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+///A `try` whose handler is active while its body is compiled, jumps and returns leaving it have
+///to drop the handler and run its `finally` on the way out
+#[derive(Clone)]
+struct Protected {
+    finally: Option<Vec<Statement>>,
+    ///Loops enclosing the `try`, a jump to any loop past these leaves it
+    loops: usize,
+}
+
+///Everything tracked while compiling one function body
+struct FunctionState {
+    kind: FunctionKind,
+    function: Function,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueDescriptor>,
+    scope_depth: usize,
+    ///Enclosing loops, innermost last
+    loops: Vec<Loop>,
+    ///Enclosing `try` statements of this function, innermost last
+    tries: Vec<Protected>,
+}
+
+impl FunctionState {
+    fn new(kind: FunctionKind, name: &str, arity: usize) -> FunctionState {
+        //Slot 0 holds the callee, methods reach it as `this`
+        let receiver = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            FunctionKind::Script | FunctionKind::Function => "",
+        };
+
+        FunctionState {
+            kind,
+            function: Function {
+                name: name.to_string(),
+                arity,
+                chunk: Chunk::default(),
+                upvalues: vec![],
+            },
+            locals: vec![Local {
+                name: receiver.to_string(),
+                depth: Some(0),
+                captured: false,
+            }],
+            upvalues: vec![],
+            scope_depth: 0,
+            loops: vec![],
+            tries: vec![],
+        }
+    }
+}
+
+///Compiles resolved statements into bytecode, one `Function` per function body with the top
+///level wrapped in an implicit `<script>` function
+pub(crate) struct Compiler<'a> {
+    states: Vec<FunctionState>,
+    ///Global name to slot table, owned by the VM so slots stay stable between REPL lines
+    globals: &'a mut HashMap<String, u32>,
+    ///Span of the last token seen, stamped onto every instruction emitted
+    span: Span,
+    echo: bool,
+}
+
+impl<'a> Compiler<'a> {
+    pub(crate) fn new(globals: &'a mut HashMap<String, u32>, echo: bool) -> Compiler<'a> {
+        Compiler {
+            states: vec![FunctionState::new(FunctionKind::Script, "<script>", 0)],
+            globals,
+            span: Span::default(),
+            echo,
+        }
+    }
+
+    ///Compiles a whole program, when `echo` was set bare top-level expressions print their value
+    pub(crate) fn compile(mut self, statements: &mut [Statement]) -> Function {
+        for statement in statements {
+            statement.accept(&mut self);
+        }
+        self.emit_return();
+
+        let state = self.states.pop().expect("Script state is always present");
+        state.function
+    }
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.states
+            .last_mut()
+            .expect("Compiler has no function state")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().function.chunk
+    }
+
+    fn emit(&mut self, op: OpCode) -> usize {
+        let span = self.span;
+        self.chunk().write(op, span)
+    }
+
+    fn binary_op(operator: &Token) -> OpCode {
+        match operator.token_type {
+            TokenType::Plus => OpCode::Add,
+            TokenType::Minus => OpCode::Subtract,
+            TokenType::Star => OpCode::Multiply,
+            TokenType::Slash => OpCode::Divide,
+            TokenType::Percent => OpCode::Modulo,
+            TokenType::StarStar => OpCode::Power,
+            TokenType::Greater => OpCode::Greater,
+            TokenType::GreaterEqual => OpCode::GreaterEqual,
+            TokenType::Less => OpCode::Less,
+            TokenType::LessEqual => OpCode::LessEqual,
+            TokenType::EqualEqual => OpCode::Equal,
+            TokenType::BangEqual => OpCode::NotEqual,
+            _ => unreachable!("Parser only builds binary expressions from binary operators"),
+        }
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let index = self.chunk().add_constant(value);
+        self.emit(OpCode::Constant(index));
+    }
+
+    fn name_constant(&mut self, name: &Token) -> u32 {
+        self.chunk()
+            .add_constant(Value::String(Rc::from(name.lexeme.as_str())))
+    }
+
+    fn emit_return(&mut self) {
+        self.emit_implicit_result();
+        self.emit(OpCode::Return);
+    }
+
+    ///Pushes what a function hands back when it returns without a value
+    fn emit_implicit_result(&mut self) {
+        if self.state().kind == FunctionKind::Initializer {
+            self.emit(OpCode::GetLocal(0));
+        } else {
+            self.emit(OpCode::Nil);
+        }
+    }
+
+    ///Emits a forward jump with a placeholder distance, returning where to patch it
+    fn emit_jump(&mut self, op: fn(u32) -> OpCode) -> usize {
+        self.emit(op(0))
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        let chunk = self.chunk();
+        let distance: u32 = (chunk.code.len() - offset - 1)
+            .try_into()
+            .expect("Jump distance exceeded u32 range");
+
+        chunk.code[offset] = match chunk.code[offset] {
+            OpCode::Jump(_) => OpCode::Jump(distance),
+            OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(distance),
+            OpCode::JumpIfNil(_) => OpCode::JumpIfNil(distance),
+            OpCode::IterNext(_) => OpCode::IterNext(distance),
+            OpCode::Try(_) => OpCode::Try(distance),
+            op => unreachable!("Patched a non-jump instruction {op:?}"),
+        };
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        let distance: u32 = (self.chunk().code.len() + 1 - loop_start)
+            .try_into()
+            .expect("Loop body exceeded u32 range");
+        self.emit(OpCode::Loop(distance));
+    }
+
+    fn global_slot(&mut self, name: &str) -> u32 {
+        let next: u32 = self
+            .globals
+            .len()
+            .try_into()
+            .expect("Global table exceeded u32 range");
+        *self.globals.entry(name.to_string()).or_insert(next)
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state().scope_depth -= 1;
+
+        loop {
+            let state = self.state();
+            let Some(local) = state.locals.last() else {
+                break;
+            };
+            if local.depth.is_none_or(|depth| depth <= state.scope_depth) {
+                break;
+            }
+
+            //Captured locals move off the stack so closures keep seeing them
+            let op = if local.captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            };
+            state.locals.pop();
+            self.emit(op);
+        }
+    }
+
+    fn jump_out_of_loop(&mut self, label: &Option<Token>, is_break: bool) {
+        //!Drops the locals the jump leaves behind, then jumps to a spot patched when the loop ends
+        let state = self.state();
+        let target = state
+            .loops
+            .iter()
+            .rposition(|enclosing| match label {
+                Some(label) => enclosing.label.as_ref() == Some(&label.lexeme),
+                None => true,
+            })
+            .expect("The parser rejects jumps without a matching loop");
+        let from = state
+            .tries
+            .iter()
+            .position(|protected| protected.loops > target)
+            .unwrap_or(state.tries.len());
+        self.leave_tries(from);
+
+        let state = self.state();
+        //The locals stay declared, code after the jump in the same block still refers to them
+        let discarded: Vec<OpCode> = state.locals[state.loops[target].locals..]
+            .iter()
+            .rev()
+            .map(|local| {
+                if local.captured {
+                    OpCode::CloseUpvalue
+                } else {
+                    OpCode::Pop
+                }
+            })
+            .collect();
+        for op in discarded {
+            self.emit(op);
+        }
+
+        let jump = self.emit_jump(OpCode::Jump);
+        let enclosing = &mut self.state().loops[target];
+        if is_break {
+            enclosing.breaks.push(jump);
+        } else {
+            enclosing.continues.push(jump);
+        }
+    }
+
+    fn leave_tries(&mut self, from: usize) {
+        //!Drops the handlers of the `try` statements past the first `from`, innermost first,
+        //!running each one's `finally` with only the tries around it still active
+        let span = self.span;
+        let tries = std::mem::take(&mut self.state().tries);
+        for index in (from..tries.len()).rev() {
+            self.state().tries = tries[..index].to_vec();
+            self.emit(OpCode::EndTry);
+            if let Some(ref finally) = tries[index].finally {
+                self.finally_block(finally);
+            }
+        }
+        self.state().tries = tries;
+        self.span = span;
+    }
+
+    ///Compiles a copy of a `finally` block, it is emitted once for every way out of its `try`
+    fn finally_block(&mut self, finally: &[Statement]) {
+        self.begin_scope();
+        for statement in &mut finally.to_vec() {
+            statement.accept(self);
+        }
+        self.end_scope();
+    }
+
+    ///Declares a local for a value the VM already left on the stack, the caller forgets it with
+    ///`forget_hidden_local` once the code using it ends in a return or throw
+    fn hidden_local(&mut self, name: &str) {
+        self.begin_scope();
+        self.add_local(name);
+        self.mark_initialized();
+    }
+
+    fn forget_hidden_local(&mut self) {
+        let state = self.state();
+        state.locals.pop();
+        state.scope_depth -= 1;
+    }
+
+    fn add_local(&mut self, name: &str) {
+        self.state().locals.push(Local {
+            name: name.to_string(),
+            depth: None,
+            captured: false,
+        });
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.state();
+        let depth = state.scope_depth;
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = Some(depth);
+        }
+    }
+
+    ///Declares `name` in the current scope, globals need no declaration
+    fn declare_variable(&mut self, name: &Token) {
+        if self.state().scope_depth > 0 {
+            self.add_local(&name.lexeme);
+        }
+    }
+
+    ///Finishes a declaration whose value is on top of the stack
+    fn define_variable(&mut self, name: &Token) {
+        if self.state().scope_depth > 0 {
+            self.mark_initialized();
+            return;
+        }
+
+        let slot = self.global_slot(&name.lexeme);
+        self.emit(OpCode::DefineGlobal(slot));
+    }
+
+    fn resolve_local(&self, state: usize, name: &str) -> Option<u32> {
+        self.states[state]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u32)
+    }
+
+    fn add_upvalue(&mut self, state: usize, is_local: bool, index: u32) -> u32 {
+        let upvalues = &mut self.states[state].upvalues;
+        if let Some(existing) = upvalues
+            .iter()
+            .position(|upvalue| upvalue.is_local == is_local && upvalue.index == index)
+        {
+            return existing as u32;
+        }
+
+        upvalues.push(UpvalueDescriptor { is_local, index });
+        (upvalues.len() - 1) as u32
+    }
+
+    ///Looks for `name` in the functions enclosing `state`, threading it through each one in between
+    fn resolve_upvalue(&mut self, state: usize, name: &str) -> Option<u32> {
+        if state == 0 {
+            return None;
+        }
+
+        if let Some(local) = self.resolve_local(state - 1, name) {
+            self.states[state - 1].locals[local as usize].captured = true;
+            return Some(self.add_upvalue(state, true, local));
+        }
+
+        let upvalue = self.resolve_upvalue(state - 1, name)?;
+        Some(self.add_upvalue(state, false, upvalue))
+    }
+
+    ///Emits a read of `name`, or a write of the value on top of the stack when `assign` is set
+    fn named_variable(&mut self, name: &str, assign: bool) {
+        let current = self.states.len() - 1;
+
+        let op = if let Some(slot) = self.resolve_local(current, name) {
+            if assign {
+                OpCode::SetLocal(slot)
+            } else {
+                OpCode::GetLocal(slot)
+            }
+        } else if let Some(index) = self.resolve_upvalue(current, name) {
+            if assign {
+                OpCode::SetUpvalue(index)
+            } else {
+                OpCode::GetUpvalue(index)
+            }
+        } else {
+            let slot = self.global_slot(name);
+            if assign {
+                OpCode::SetGlobal(slot)
+            } else {
+                OpCode::GetGlobal(slot)
+            }
+        };
+
+        self.emit(op);
+    }
+
+    ///Compiles a function body into its own `Function` and leaves a closure over it on the stack
+    fn function(
+        &mut self,
+        kind: FunctionKind,
+        name: &str,
+        parameters: &[Token],
+        body: &mut [Statement],
+    ) {
+        self.states
+            .push(FunctionState::new(kind, name, parameters.len()));
+        self.begin_scope();
+
+        for parameter in parameters {
+            self.add_local(&parameter.lexeme);
+            self.mark_initialized();
+        }
+        for statement in body {
+            statement.accept(self);
+        }
+        self.emit_return();
+
+        //No end_scope, returning discards the whole frame
+        let mut state = self.states.pop().expect("Function state was just pushed");
+        state.function.upvalues = state.upvalues;
+
+        let index = self
+            .chunk()
+            .add_constant(Value::Function(Rc::new(state.function)));
+        self.emit(OpCode::Closure(index));
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        expression.accept(self);
+    }
+}
+
+impl StatementVisitor<()> for Compiler<'_> {
+    fn visit_expression_statement(&mut self, expression: &mut ExpressionStatement) {
+        self.expression(&mut expression.expression);
+
+        //Only top-level expressions of a REPL line echo, and assignments are just noise
+        let top_level = self.states.len() == 1 && self.state().scope_depth == 0;
+        if self.echo
+            && top_level
+            && !matches!(
+                expression.expression,
+                Expression::Assignment(_)
+                    | Expression::Set(_)
+                    | Expression::SetIndex(_)
+                    | Expression::Compound(_)
+            )
+        {
+            self.emit(OpCode::Echo);
+        } else {
+            self.emit(OpCode::Pop);
+        }
+    }
+
+    fn visit_variable_statement(&mut self, var: &mut VariableStatement) {
+        self.span = var.name.span;
+        self.declare_variable(&var.name);
+
+        match var.initalizer {
+            Some(ref mut initalizer) => self.expression(initalizer),
+            None => {
+                self.emit(OpCode::Nil);
+            }
+        }
+
+        self.define_variable(&var.name);
+    }
+
+    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) {
+        self.expression(&mut if_statement.condition);
+        self.span = if_statement.keyword.span;
+
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+        if_statement.then_branch.accept(self);
+
+        let else_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(then_jump);
+        self.emit(OpCode::Pop);
+        if let Some(ref mut else_branch) = *if_statement.else_branch {
+            else_branch.accept(self);
+        }
+        self.patch_jump(else_jump);
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &mut WhileStatement) {
+        let loop_start = self.chunk().code.len();
+        self.expression(&mut while_statement.condition);
+        self.span = while_statement.keyword.span;
+
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+
+        let locals = self.state().locals.len();
+        self.state().loops.push(Loop {
+            label: while_statement
+                .label
+                .as_ref()
+                .map(|label| label.lexeme.clone()),
+            locals,
+            breaks: vec![],
+            continues: vec![],
+        });
+        while_statement.body.accept(self);
+        let finished = self.state().loops.pop().expect("Loop was pushed above");
+
+        for jump in finished.continues {
+            self.patch_jump(jump);
+        }
+        if let Some(ref mut increment) = while_statement.increment {
+            self.expression(increment);
+            self.emit(OpCode::Pop);
+        }
+        self.span = while_statement.keyword.span;
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit(OpCode::Pop);
+        //Breaks skip the pop above, their condition was already popped entering the body
+        for jump in finished.breaks {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn visit_for_in_statement(&mut self, for_in: &mut ForInStatement) {
+        //The iterator sits in a hidden local, named with a keyword so no variable can see it
+        self.begin_scope();
+        match for_in.iterable {
+            Iterable::Value(ref mut iterable) => {
+                self.expression(iterable);
+                self.span = for_in.keyword.span;
+                self.emit(OpCode::Iterator);
+            }
+            Iterable::Range {
+                ref mut start,
+                ref operator,
+                ref mut end,
+            } => {
+                self.expression(start);
+                self.expression(end);
+                self.span = operator.span;
+                self.emit(OpCode::Range(operator.token_type == TokenType::DotDotEqual));
+            }
+        }
+        self.add_local("for");
+        self.mark_initialized();
+
+        let loop_start = self.chunk().code.len();
+        self.span = for_in.keyword.span;
+        let next_jump = self.emit_jump(OpCode::IterNext);
+        let exit_jump = self.emit_jump(OpCode::JumpIfNil);
+
+        //Jumps out of the body drop the loop variable but keep the iterator
+        let locals = self.state().locals.len();
+        self.state().loops.push(Loop {
+            label: for_in.label.as_ref().map(|label| label.lexeme.clone()),
+            locals,
+            breaks: vec![],
+            continues: vec![],
+        });
+        self.begin_scope();
+        self.add_local(&for_in.name.lexeme);
+        self.mark_initialized();
+        for_in.body.accept(self);
+        let finished = self.state().loops.pop().expect("Loop was pushed above");
+        self.end_scope();
+
+        for jump in finished.continues {
+            self.patch_jump(jump);
+        }
+        self.span = for_in.keyword.span;
+        self.emit_loop(loop_start);
+
+        self.patch_jump(next_jump);
+        self.patch_jump(exit_jump);
+        //Pops the nil that ended the loop, breaks already dropped the loop variable
+        self.emit(OpCode::Pop);
+        for jump in finished.breaks {
+            self.patch_jump(jump);
+        }
+        self.end_scope();
+    }
+
+    fn visit_try_statement(&mut self, try_statement: &mut TryStatement) {
+        //! Without a catch the handler goes straight to the rethrowing copy of `finally`
+        let finally = try_statement.finally.clone();
+        let loops = self.state().loops.len();
+        self.span = try_statement.keyword.span;
+
+        let handler = self.emit_jump(OpCode::Try);
+        self.state().tries.push(Protected {
+            finally: finally.clone(),
+            loops,
+        });
+        self.begin_scope();
+        for statement in &mut try_statement.body {
+            statement.accept(self);
+        }
+        self.end_scope();
+        self.state().tries.pop();
+        self.emit(OpCode::EndTry);
+        if let Some(ref finally) = finally {
+            self.finally_block(finally);
+        }
+        let mut exits = vec![self.emit_jump(OpCode::Jump)];
+        self.patch_jump(handler);
+
+        if let Some(ref mut catch) = try_statement.catch {
+            //Whatever the catch throws still runs `finally` before it leaves
+            let rethrow = finally.is_some().then(|| {
+                self.span = catch.name.span;
+                self.emit_jump(OpCode::Try)
+            });
+            if rethrow.is_some() {
+                self.state().tries.push(Protected {
+                    finally: finally.clone(),
+                    loops,
+                });
+            }
+
+            self.span = catch.name.span;
+            self.emit(OpCode::Caught);
+            self.begin_scope();
+            self.add_local(&catch.name.lexeme);
+            self.mark_initialized();
+            for statement in &mut catch.body {
+                statement.accept(self);
+            }
+            self.end_scope();
+
+            let Some(rethrow) = rethrow else {
+                for exit in exits {
+                    self.patch_jump(exit);
+                }
+                return;
+            };
+            self.state().tries.pop();
+            self.emit(OpCode::EndTry);
+            if let Some(ref finally) = finally {
+                self.finally_block(finally);
+            }
+            exits.push(self.emit_jump(OpCode::Jump));
+            self.patch_jump(rethrow);
+        }
+
+        //The fault sits on the stack while `finally` runs, then carries on to the next handler
+        let finally = finally.expect("The parser requires a catch or a finally");
+        self.hidden_local("try");
+        self.finally_block(&finally);
+        self.span = try_statement.keyword.span;
+        self.emit(OpCode::Throw);
+        self.forget_hidden_local();
+
+        for exit in exits {
+            self.patch_jump(exit);
+        }
+    }
+
+    fn visit_throw_statement(&mut self, throw: &mut ThrowStatement) {
+        self.expression(&mut throw.value);
+        self.span = throw.keyword.span;
+        self.emit(OpCode::Throw);
+    }
+
+    fn visit_break_statement(&mut self, break_statement: &mut BreakStatement) {
+        self.span = break_statement.keyword.span;
+        self.jump_out_of_loop(&break_statement.label, true);
+    }
+
+    fn visit_continue_statement(&mut self, continue_statement: &mut ContinueStatement) {
+        self.span = continue_statement.keyword.span;
+        self.jump_out_of_loop(&continue_statement.label, false);
+    }
+
+    fn visit_block_statement(&mut self, block_statement: &mut BlockStatement) {
+        self.begin_scope();
+        for statement in &mut block_statement.statements {
+            statement.accept(self);
+        }
+        self.end_scope();
+    }
+
+    fn visit_function_statement(&mut self, function_statement: &mut FunctionStatement) {
+        self.span = function_statement.name.span;
+
+        //Initialized up front so the function can call itself
+        self.declare_variable(&function_statement.name);
+        if self.state().scope_depth > 0 {
+            self.mark_initialized();
+        }
+
+        self.function(
+            FunctionKind::Function,
+            &function_statement.name.lexeme,
+            &function_statement.parameters,
+            &mut function_statement.body,
+        );
+        self.define_variable(&function_statement.name);
+    }
+
+    fn visit_return_statement(&mut self, return_statement: &mut ReturnStatement) {
+        self.span = return_statement.keyword.span;
+
+        match return_statement.value {
+            Some(ref mut value) => self.expression(value),
+            None => self.emit_implicit_result(),
+        }
+
+        //The result waits in a hidden local while every enclosing `finally` runs
+        if !self.state().tries.is_empty() {
+            self.hidden_local("return");
+            self.leave_tries(0);
+            self.forget_hidden_local();
+            self.span = return_statement.keyword.span;
+        }
+        self.emit(OpCode::Return);
+    }
+
+    fn visit_class_statement(&mut self, class_statement: &mut ClassStatement) {
+        let name = class_statement.name.clone();
+        self.span = name.span;
+
+        let name_constant = self.name_constant(&name);
+        self.declare_variable(&name);
+        self.emit(OpCode::Class(name_constant));
+        self.define_variable(&name);
+
+        //The superclass lives in a hidden `super` local that methods capture
+        if let Some(ref mut superclass) = class_statement.superclass {
+            self.expression(superclass);
+
+            self.begin_scope();
+            self.add_local("super");
+            self.mark_initialized();
+
+            self.named_variable(&name.lexeme, false);
+            self.emit(OpCode::Inherit);
+        }
+
+        self.named_variable(&name.lexeme, false);
+        for method in &mut class_statement.methods {
+            self.span = method.name.span;
+            let kind = if method.name.lexeme == "init" {
+                FunctionKind::Initializer
+            } else {
+                FunctionKind::Method
+            };
+
+            let method_constant = self.name_constant(&method.name);
+            self.function(
+                kind,
+                &method.name.lexeme,
+                &method.parameters,
+                &mut method.body,
+            );
+            self.emit(OpCode::Method(method_constant));
+        }
+        self.emit(OpCode::Pop);
+
+        if class_statement.superclass.is_some() {
+            self.end_scope();
+        }
+    }
+}
+
+impl InterpreterVisitor<()> for Compiler<'_> {
+    fn visit_grouping(&mut self, group: &mut Grouping) {
+        self.expression(&mut group.expression);
+    }
+
+    fn visit_binary(&mut self, bin: &mut Binary) {
+        self.expression(&mut bin.left);
+        self.expression(&mut bin.right);
+        self.span = bin.operator.span;
+        self.emit(Self::binary_op(&bin.operator));
+    }
+
+    fn visit_unary(&mut self, unary: &mut Unary) {
+        self.expression(&mut unary.operand);
+        self.span = unary.operator.span;
+
+        let op = match unary.operator.token_type {
+            TokenType::Minus => OpCode::Negate,
+            TokenType::Bang => OpCode::Not,
+            _ => unreachable!("Parser only builds unary expressions from unary operators"),
+        };
+        self.emit(op);
+    }
+
+    fn visit_literal(&mut self, lit: &mut Literal) {
+        match lit.value {
+            LiteralType::Nil => {
+                self.emit(OpCode::Nil);
+            }
+            LiteralType::Boolean(true) => {
+                self.emit(OpCode::True);
+            }
+            LiteralType::Boolean(false) => {
+                self.emit(OpCode::False);
+            }
+            LiteralType::Number(num) => self.emit_constant(Value::Number(num)),
+            LiteralType::String(ref string) => {
+                self.emit_constant(Value::String(Rc::from(string.as_str())))
+            }
+            LiteralType::Callable(_)
+            | LiteralType::Instance(_)
+            | LiteralType::List(_)
+            | LiteralType::Map(_) => {
+                unreachable!("Parser never produces runtime values as literals")
+            }
+        }
+    }
+
+    fn visit_ternary(&mut self, tern: &mut Ternary) {
+        self.expression(&mut tern.evaluator);
+        self.span = tern.question.span;
+
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+        self.expression(&mut tern.left);
+
+        let end_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(else_jump);
+        self.emit(OpCode::Pop);
+        self.expression(&mut tern.right);
+        self.patch_jump(end_jump);
+    }
+
+    fn visit_variable(&mut self, var: &mut Variable) {
+        self.span = var.name.span;
+        self.named_variable(&var.name.lexeme, false);
+    }
+
+    fn visit_assignment(&mut self, assign: &mut Assignment) {
+        self.expression(&mut assign.value);
+        self.span = assign.name.span;
+        self.named_variable(&assign.name.lexeme, true);
+    }
+
+    fn visit_logical(&mut self, logical: &mut Logical) {
+        self.expression(&mut logical.left);
+        self.span = logical.operator.span;
+        self.emit(OpCode::CheckLogical);
+
+        if logical.operator.token_type == TokenType::Or {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+            let end_jump = self.emit_jump(OpCode::Jump);
+            self.patch_jump(else_jump);
+            self.emit(OpCode::Pop);
+            self.expression(&mut logical.right);
+            self.patch_jump(end_jump);
+        } else {
+            let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+            self.emit(OpCode::Pop);
+            self.expression(&mut logical.right);
+            self.patch_jump(end_jump);
+        }
+    }
+
+    fn visit_call(&mut self, call: &mut Call) {
+        self.expression(&mut call.callee);
+        for argument in &mut call.arguments {
+            self.expression(argument);
+        }
+
+        self.span = call.paren.span;
+        let argument_count: u32 = call
+            .arguments
+            .len()
+            .try_into()
+            .expect("Argument count exceeded u32 range");
+        self.emit(OpCode::Call(argument_count));
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda) {
+        self.span = lambda.keyword.span;
+        self.function(
+            FunctionKind::Function,
+            "<lambda>",
+            &lambda.parameters,
+            &mut lambda.body,
+        );
+    }
+
+    fn visit_interpolation(&mut self, interpolation: &mut Interpolation) {
+        for part in &mut interpolation.parts {
+            self.expression(part);
+        }
+
+        self.span = interpolation.span;
+        let part_count: u32 = interpolation
+            .parts
+            .len()
+            .try_into()
+            .expect("Interpolation part count exceeded u32 range");
+        self.emit(OpCode::Interpolate(part_count));
+    }
+
+    fn visit_list(&mut self, list: &mut List) {
+        for element in &mut list.elements {
+            self.expression(element);
+        }
+
+        self.span = list.span;
+        let element_count: u32 = list
+            .elements
+            .len()
+            .try_into()
+            .expect("List element count exceeded u32 range");
+        self.emit(OpCode::BuildList(element_count));
+    }
+
+    fn visit_map(&mut self, map: &mut Map) {
+        for (key, value) in &mut map.entries {
+            self.expression(key);
+            self.expression(value);
+        }
+
+        //Bad keys are reported at the brace, like the tree-walker does
+        self.span = map.brace.span;
+        let entry_count: u32 = map
+            .entries
+            .len()
+            .try_into()
+            .expect("Map entry count exceeded u32 range");
+        self.emit(OpCode::BuildMap(entry_count));
+    }
+
+    fn visit_index(&mut self, index: &mut Index) {
+        self.expression(&mut index.object);
+        self.expression(&mut index.index);
+        self.span = index.bracket.span;
+        self.emit(OpCode::GetIndex);
+    }
+
+    fn visit_setindex(&mut self, set: &mut SetIndex) {
+        self.expression(&mut set.object);
+        self.expression(&mut set.index);
+        self.expression(&mut set.value);
+        self.span = set.bracket.span;
+        self.emit(OpCode::SetIndex);
+    }
+
+    fn visit_compound(&mut self, compound: &mut Compound) {
+        //!The target's object and index stay on the stack so they are evaluated once. A postfix
+        //!target first pushes a placeholder that the old value is stored into, left behind as the
+        //!result once the new value is popped
+        if compound.postfix {
+            self.span = compound.operator.span;
+            self.emit(OpCode::Nil);
+        }
+
+        let operands = match &mut compound.target {
+            Expression::Variable(var) => {
+                self.span = var.name.span;
+                self.named_variable(&var.name.lexeme, false);
+                0
+            }
+            Expression::Get(get) => {
+                self.expression(&mut get.object);
+                self.span = get.name.span;
+                let name = self.name_constant(&get.name);
+                self.emit(OpCode::Dup(0));
+                self.emit(OpCode::GetProperty(name));
+                1
+            }
+            Expression::Index(index) => {
+                self.expression(&mut index.object);
+                self.expression(&mut index.index);
+                self.span = index.bracket.span;
+                self.emit(OpCode::Dup(1));
+                self.emit(OpCode::Dup(1));
+                self.emit(OpCode::GetIndex);
+                2
+            }
+            _ => unreachable!("Parser only builds compound assignments to assignable targets"),
+        };
+        if compound.postfix {
+            self.emit(OpCode::Store(operands + 1));
+        }
+        if compound.step {
+            self.span = compound.operator.span;
+            self.emit(OpCode::CheckStep);
+        }
+
+        self.expression(&mut compound.value);
+        self.span = compound.operator.span;
+        self.emit(Self::binary_op(&compound.operator));
+
+        match &compound.target {
+            Expression::Variable(var) => {
+                self.span = var.name.span;
+                self.named_variable(&var.name.lexeme, true);
+            }
+            Expression::Get(get) => {
+                self.span = get.name.span;
+                let name = self.name_constant(&get.name);
+                self.emit(OpCode::SetProperty(name));
+            }
+            Expression::Index(index) => {
+                self.span = index.bracket.span;
+                self.emit(OpCode::SetIndex);
+            }
+            _ => unreachable!("Parser only builds compound assignments to assignable targets"),
+        }
+        if compound.postfix {
+            self.emit(OpCode::Pop);
+        }
+    }
+
+    fn visit_slice(&mut self, slice: &mut Slice) {
+        self.expression(&mut slice.object);
+        for bound in [&mut slice.start, &mut slice.end] {
+            match bound {
+                Some(bound) => self.expression(bound),
+                None => {
+                    self.emit(OpCode::Nil);
+                }
+            }
+        }
+        self.span = slice.bracket.span;
+        self.emit(OpCode::Slice);
+    }
+
+    fn visit_get(&mut self, get: &mut Get) {
+        self.expression(&mut get.object);
+        self.span = get.name.span;
+
+        let name = self.name_constant(&get.name);
+        self.emit(OpCode::GetProperty(name));
+    }
+
+    fn visit_set(&mut self, set: &mut Set) {
+        self.expression(&mut set.object);
+        self.expression(&mut set.value);
+        self.span = set.name.span;
+
+        let name = self.name_constant(&set.name);
+        self.emit(OpCode::SetProperty(name));
+    }
+
+    fn visit_this(&mut self, this: &mut This) {
+        self.span = this.keyword.span;
+        self.named_variable("this", false);
+    }
+
+    fn visit_super(&mut self, sup: &mut Super) {
+        self.span = sup.keyword.span;
+
+        let name = self.name_constant(&sup.method);
+        self.named_variable("this", false);
+        self.named_variable("super", false);
+        self.emit(OpCode::GetSuper(name));
+    }
+}
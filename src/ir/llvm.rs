@@ -0,0 +1,1046 @@
+use crate::{
+    diagnostics::codes::{
+        self,
+        ErrorCode,
+    },
+    interpreter::{
+        InterpreterVisitor,
+        Visitable,
+    },
+    ir::vm::FRAMES_MAX,
+    parser::statement::{
+        StatementVisitor,
+        Visitable as StatementVisitable,
+    },
+    types::{
+        Expression,
+        LiteralType,
+        ParserError,
+        TokenType,
+        expression::{
+            Call,
+            *,
+        },
+        statement::*,
+        token::Token,
+    },
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+};
+
+///Hand written support functions every module links against, see the file for the value layout
+const RUNTIME: &str = include_str!("llvm/runtime.ll");
+
+///Strings the runtime refers to through `i8**` globals, so it never needs to know their lengths
+const RUNTIME_STRINGS: [(&str, &str); 11] = [
+    ("error", "error[%s]: %s\n --> %s:%d\n"),
+    ("nil", "NIL"),
+    ("true", "true"),
+    ("false", "false"),
+    ("exponent", "%#.*e"),
+    ("nan", "NaN"),
+    ("inf", "inf"),
+    ("negative_inf", "-inf"),
+    ("fn", "<fn %s>"),
+    ("print", "%s"),
+    ("println", "%s\n"),
+];
+
+///Runtime errors as `%Error` globals pairing a code with its message, messages holding a `%`
+///are formats the runtime fills in first
+const RUNTIME_ERRORS: [(&str, &ErrorCode, &str); 12] = [
+    (
+        "condition",
+        &codes::BOOLEAN_REQUIRED,
+        "Condition must be a boolean.",
+    ),
+    (
+        "operand_boolean",
+        &codes::BOOLEAN_REQUIRED,
+        "Operand must be a boolean.",
+    ),
+    (
+        "operand_number",
+        &codes::OPERAND_MUST_BE_NUMBER,
+        codes::OPERAND_MUST_BE_NUMBER.title,
+    ),
+    (
+        "operands_numbers",
+        &codes::OPERANDS_MUST_BE_NUMBERS,
+        codes::OPERANDS_MUST_BE_NUMBERS.title,
+    ),
+    ("step", &codes::INVALID_STEP, codes::INVALID_STEP.title),
+    ("range", &codes::INVALID_RANGE, codes::INVALID_RANGE.title),
+    (
+        "add",
+        &codes::INVALID_ADDITION,
+        codes::INVALID_ADDITION.title,
+    ),
+    ("call", &codes::NOT_CALLABLE, codes::NOT_CALLABLE.title),
+    (
+        "arity",
+        &codes::WRONG_ARGUMENT_COUNT,
+        "Expected %d arguments but got %d.",
+    ),
+    (
+        "undefined",
+        &codes::UNDEFINED_VARIABLE,
+        "Variable not found: %s",
+    ),
+    (
+        "uncaught",
+        &codes::UNCAUGHT_EXCEPTION,
+        "Uncaught exception: ",
+    ),
+    (
+        "overflow",
+        &codes::STACK_OVERFLOW,
+        codes::STACK_OVERFLOW.title,
+    ),
+];
+
+///Built-ins, as (lux name, display name, runtime function, arity)
+const NATIVES: [(&str, &str, &str, usize); 3] = [
+    ("clock", "Clock", "lux_native_clock", 0),
+    ("print", "Print", "lux_native_print", 1),
+    ("println", "Println", "lux_native_println", 1),
+];
+
+///Operands are written without their `%Value` type, each use site supplies it
+const NIL: &str = "zeroinitializer";
+
+///One function being emitted, instructions are buffered until the whole body is known
+struct FunctionBuilder {
+    ///Stack slots for locals, hoisted into the entry block so loops don't grow the stack
+    allocas: String,
+    body: String,
+    next_register: usize,
+    ///Label of the block instructions are currently being appended to, needed for phis
+    label: String,
+    ///Set after a terminator, anything emitted afterwards starts a fresh unreachable block
+    terminated: bool,
+    ///Lux local name to the alloca holding it, innermost scope last
+    scopes: Vec<HashMap<String, String>>,
+    ///Enclosing loops, innermost last
+    loops: Vec<LoopLabels>,
+}
+
+///Where `continue` and `break` branch to for one loop
+struct LoopLabels {
+    name: Option<String>,
+    next: String,
+    end: String,
+}
+
+impl FunctionBuilder {
+    fn new() -> FunctionBuilder {
+        FunctionBuilder {
+            allocas: String::new(),
+            body: String::new(),
+            next_register: 0,
+            label: String::from("entry"),
+            terminated: false,
+            scopes: vec![],
+            loops: vec![],
+        }
+    }
+}
+
+///Lowers resolved statements to a textual LLVM IR module whose `main` runs the program. Values
+///stay dynamically typed, every operation goes through the runtime in `llvm/runtime.ll`
+pub(crate) struct LlvmEmitter {
+    builders: Vec<FunctionBuilder>,
+    ///Finished function definitions
+    functions: String,
+    ///`%FnDesc` constants, what a function value points at
+    descriptors: String,
+    ///Interned string constants, text to constant index
+    strings: HashMap<String, usize>,
+    string_definitions: String,
+    ///Globals in the order they were first seen
+    globals: Vec<String>,
+    next_label: usize,
+    next_function: usize,
+    ///Source file name and line of the last token seen, runtime errors report both
+    file: String,
+    line: u32,
+    errors: Vec<ParserError>,
+}
+
+///The type of a lowered function taking `arity` values
+fn function_type(arity: usize) -> String {
+    format!("%Value ({})", vec!["%Value"; arity].join(", "))
+}
+
+///Doubles are written as their bit pattern, LLVM rejects decimals it can't represent exactly
+fn double(number: f64) -> String {
+    format!("0x{:016X}", number.to_bits())
+}
+
+impl LlvmEmitter {
+    pub(crate) fn new(file: &str) -> LlvmEmitter {
+        LlvmEmitter {
+            builders: vec![FunctionBuilder::new()],
+            functions: String::new(),
+            descriptors: String::new(),
+            strings: HashMap::new(),
+            string_definitions: String::new(),
+            globals: vec![],
+            next_label: 0,
+            next_function: 0,
+            file: file.to_string(),
+            line: 1,
+            errors: vec![],
+        }
+    }
+
+    ///Emits a whole program, handing back every construct the backend couldn't lower
+    pub(crate) fn emit(mut self, statements: &mut [Statement]) -> Result<String, Vec<ParserError>> {
+        for statement in statements {
+            statement.accept(&mut self);
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        let main = self.builders.pop().expect("Main builder is always present");
+        let mut module = String::from("; ModuleID = 'rlux'\n\n");
+        module.push_str("%Value = type { i32, double, i8* }\n");
+        module.push_str("%FnDesc = type { i8*, i8*, i32 }\n");
+        module.push_str("%Error = type { i8*, i8* }\n\n");
+
+        for (name, text) in RUNTIME_STRINGS {
+            let pointer = self.string_pointer(text);
+            let _ = writeln!(
+                module,
+                "@rt.{name} = private unnamed_addr constant i8* {pointer}"
+            );
+        }
+        for (name, code, message) in RUNTIME_ERRORS {
+            let code = self.string_pointer(code.code);
+            let message = self.string_pointer(message);
+            let _ = writeln!(
+                module,
+                "@rt.{name} = private unnamed_addr constant %Error {{ i8* {code}, i8* {message} }}"
+            );
+        }
+        let file = self.string_pointer(&self.file.clone());
+        let _ = writeln!(
+            module,
+            "@rt.file = private unnamed_addr constant i8* {file}"
+        );
+        let _ = writeln!(
+            module,
+            "@rt.frames_max = private unnamed_addr constant i32 {FRAMES_MAX}"
+        );
+
+        let mut globals = String::new();
+        for (name, display, function, arity) in NATIVES {
+            let pointer = self.string_pointer(display);
+            let _ = writeln!(
+                self.descriptors,
+                "@\"desc.{name}\" = internal constant %FnDesc {{ i8* bitcast ({}* @{function} to i8*), i8* {pointer}, i32 {arity} }}",
+                function_type(arity)
+            );
+            let _ = writeln!(
+                globals,
+                "@\"g.{name}\" = internal global %Value {{ i32 4, double 0.0, i8* bitcast (%FnDesc* @\"desc.{name}\" to i8*) }}"
+            );
+        }
+        for name in &self.globals {
+            if !NATIVES.iter().any(|native| native.0 == name) {
+                let _ = writeln!(
+                    globals,
+                    "@\"g.{name}\" = internal global %Value {{ i32 5, double 0.0, i8* null }}"
+                );
+            }
+        }
+
+        module.push_str(&self.string_definitions);
+        module.push('\n');
+        module.push_str(&self.descriptors);
+        module.push('\n');
+        module.push_str(&globals);
+        module.push('\n');
+        module.push_str(RUNTIME);
+        module.push('\n');
+        module.push_str(&self.functions);
+
+        let _ = write!(
+            module,
+            "define i32 @main() {{\nentry:\n{}{}  ret i32 0\n}}\n",
+            main.allocas, main.body
+        );
+
+        Ok(module)
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ParserError {
+            source: token.clone(),
+            cause: message.to_string(),
+            code: Some(&codes::UNSUPPORTED_BY_LLVM),
+        });
+    }
+
+    fn builder(&mut self) -> &mut FunctionBuilder {
+        self.builders
+            .last_mut()
+            .expect("Emitter has no function builder")
+    }
+
+    fn instruction(&mut self, text: &str) {
+        if self.builder().terminated {
+            let label = self.label("dead");
+            self.start_block(&label);
+        }
+        let _ = writeln!(self.builder().body, "  {text}");
+    }
+
+    ///Emits an instruction producing a value, returning the register holding it
+    fn value(&mut self, text: &str) -> String {
+        let builder = self.builder();
+        let register = format!("%r{}", builder.next_register);
+        builder.next_register += 1;
+
+        self.instruction(&format!("{register} = {text}"));
+        register
+    }
+
+    fn terminate(&mut self, text: &str) {
+        self.instruction(text);
+        self.builder().terminated = true;
+    }
+
+    ///The block a jump to the innermost loop, or the one named `label`, branches to
+    fn loop_target(&mut self, label: &Option<Token>, block: fn(&LoopLabels) -> &String) -> String {
+        let labels = self
+            .builder()
+            .loops
+            .iter()
+            .rev()
+            .find(|labels| match label {
+                Some(label) => labels.name.as_ref() == Some(&label.lexeme),
+                None => true,
+            })
+            .expect("The parser rejects jumps without a matching loop");
+        block(labels).clone()
+    }
+
+    fn label(&mut self, name: &str) -> String {
+        self.next_label += 1;
+        format!("{name}.{}", self.next_label)
+    }
+
+    fn start_block(&mut self, label: &str) {
+        let builder = self.builder();
+        if !builder.terminated {
+            let _ = writeln!(builder.body, "  br label %{label}");
+        }
+        let _ = writeln!(builder.body, "{label}:");
+        builder.label = label.to_string();
+        builder.terminated = false;
+    }
+
+    ///Calls the runtime function behind a binary operator
+    fn binary(&mut self, operator: &Token, left: &str, right: &str) -> String {
+        self.line = operator.span.line;
+
+        let function = match operator.token_type {
+            TokenType::Plus => "lux_add",
+            TokenType::Minus => "lux_sub",
+            TokenType::Star => "lux_mul",
+            TokenType::Slash => "lux_div",
+            TokenType::Percent => "lux_mod",
+            TokenType::StarStar => "lux_pow",
+            TokenType::Greater => "lux_greater",
+            TokenType::GreaterEqual => "lux_greater_equal",
+            TokenType::Less => "lux_less",
+            TokenType::LessEqual => "lux_less_equal",
+            TokenType::EqualEqual => "lux_eq",
+            TokenType::BangEqual => "lux_ne",
+            _ => unreachable!("Parser only builds binary expressions from binary operators"),
+        };
+
+        let line = self.line;
+        self.value(&format!(
+            "call %Value @{function}(%Value {left}, %Value {right}, i32 {line})"
+        ))
+    }
+
+    ///Stores `value` into the variable `name`, a global must already be defined
+    fn assign(&mut self, name: &Token, value: &str) {
+        self.line = name.span.line;
+
+        match self.lookup(name) {
+            Some(Some(slot)) => {
+                self.instruction(&format!("store %Value {value}, %Value* {slot}"));
+            }
+            Some(None) => {
+                //Assigning doesn't declare, the global must already exist
+                let global = self.global(&name.lexeme);
+                let current = self.value(&format!("load %Value, %Value* {global}"));
+                let pointer = self.string_pointer(&name.lexeme);
+                let line = self.line;
+                self.value(&format!(
+                    "call %Value @lux_defined(%Value {current}, i8* {pointer}, i32 {line})"
+                ));
+                self.instruction(&format!("store %Value {value}, %Value* {global}"));
+            }
+            None => {}
+        }
+    }
+
+    ///Interns `text` as a global constant, returning a constant `i8*` expression pointing at it
+    fn string_pointer(&mut self, text: &str) -> String {
+        let length = text.len() + 1;
+        let index = match self.strings.get(text) {
+            Some(index) => *index,
+            None => {
+                let index = self.strings.len();
+                let mut escaped = String::new();
+                for byte in text.bytes() {
+                    if (byte.is_ascii_graphic() && byte != b'"' && byte != b'\\') || byte == b' ' {
+                        escaped.push(byte as char);
+                    } else {
+                        let _ = write!(escaped, "\\{byte:02X}");
+                    }
+                }
+                let _ = writeln!(
+                    self.string_definitions,
+                    "@.str.{index} = private unnamed_addr constant [{length} x i8] c\"{escaped}\\00\""
+                );
+                self.strings.insert(text.to_string(), index);
+                index
+            }
+        };
+
+        format!(
+            "getelementptr inbounds ([{length} x i8], [{length} x i8]* @.str.{index}, i64 0, i64 0)"
+        )
+    }
+
+    fn global(&mut self, name: &str) -> String {
+        if !self.globals.iter().any(|global| global == name) {
+            self.globals.push(name.to_string());
+        }
+        format!("@\"g.{name}\"")
+    }
+
+    ///Top-level declarations of the program become LLVM globals, everything else a stack slot
+    fn at_global_scope(&self) -> bool {
+        self.builders.len() == 1 && self.builders[0].scopes.is_empty()
+    }
+
+    ///Finds the alloca holding local `name`, `Some(None)` means it is a global and `None` that it
+    ///belongs to an enclosing function, which would need a closure
+    fn lookup(&mut self, name: &Token) -> Option<Option<String>> {
+        let found = self
+            .builder()
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).cloned());
+        if found.is_some() {
+            return Some(found);
+        }
+
+        let captured = self.builders[..self.builders.len() - 1]
+            .iter()
+            .any(|builder| {
+                builder
+                    .scopes
+                    .iter()
+                    .any(|scope| scope.contains_key(&name.lexeme))
+            });
+        if captured {
+            self.error(name, "Closures are not supported by the LLVM backend.");
+            return None;
+        }
+
+        Some(None)
+    }
+
+    ///Creates a stack slot for a new local in the innermost scope
+    fn declare_local(&mut self, name: &str) -> String {
+        let builder = self.builder();
+        let slot = format!("%l{}", builder.next_register);
+        builder.next_register += 1;
+
+        let _ = writeln!(builder.allocas, "  {slot} = alloca %Value");
+        builder
+            .scopes
+            .last_mut()
+            .expect("Locals are only declared inside a scope")
+            .insert(name.to_string(), slot.clone());
+        slot
+    }
+
+    ///Stores `value` into a newly declared variable
+    fn define(&mut self, name: &Token, value: &str) {
+        let slot = if self.at_global_scope() {
+            self.global(&name.lexeme)
+        } else {
+            self.declare_local(&name.lexeme)
+        };
+        self.instruction(&format!("store %Value {value}, %Value* {slot}"));
+    }
+
+    ///Emits a function body, returning a constant function value referring to it
+    fn function(&mut self, name: &str, parameters: &[Token], body: &mut [Statement]) -> String {
+        self.next_function += 1;
+        let symbol = format!("@\"fn.{}.{name}\"", self.next_function);
+        let descriptor = format!("@\"desc.{}.{name}\"", self.next_function);
+
+        let mut builder = FunctionBuilder::new();
+        builder.scopes.push(HashMap::new());
+        self.builders.push(builder);
+
+        //Parameters get stack slots so they can be assigned like any other local
+        let mut signature = vec![];
+        for (index, parameter) in parameters.iter().enumerate() {
+            signature.push(format!("%Value %p{index}"));
+            let slot = self.declare_local(&parameter.lexeme);
+            self.instruction(&format!("store %Value %p{index}, %Value* {slot}"));
+        }
+
+        for statement in body {
+            statement.accept(self);
+        }
+        if !self.builder().terminated {
+            self.terminate(&format!("ret %Value {NIL}"));
+        }
+
+        let builder = self
+            .builders
+            .pop()
+            .expect("Function builder was just pushed");
+        let _ = write!(
+            self.functions,
+            "define internal %Value {symbol}({}) {{\nentry:\n{}{}}}\n\n",
+            signature.join(", "),
+            builder.allocas,
+            builder.body
+        );
+
+        let name_pointer = self.string_pointer(name);
+        let _ = writeln!(
+            self.descriptors,
+            "{descriptor} = internal constant %FnDesc {{ i8* bitcast ({}* {symbol} to i8*), i8* {name_pointer}, i32 {} }}",
+            function_type(parameters.len()),
+            parameters.len()
+        );
+
+        format!("{{ i32 4, double 0.0, i8* bitcast (%FnDesc* {descriptor} to i8*) }}")
+    }
+
+    fn expression(&mut self, expression: &mut Expression) -> String {
+        expression.accept(self)
+    }
+
+    fn truthy(&mut self, value: &str) -> String {
+        let line = self.line;
+        self.value(&format!("call i1 @lux_truthy(%Value {value}, i32 {line})"))
+    }
+
+    ///Lowers `cond ? then : otherwise` style branches that produce a value
+    fn select(
+        &mut self,
+        condition: &str,
+        then: &mut Expression,
+        otherwise: &mut Expression,
+    ) -> String {
+        let then_label = self.label("then");
+        let else_label = self.label("else");
+        let end_label = self.label("end");
+
+        let truthy = self.truthy(condition);
+        self.terminate(&format!(
+            "br i1 {truthy}, label %{then_label}, label %{else_label}"
+        ));
+
+        self.start_block(&then_label);
+        let then_value = self.expression(then);
+        let then_end = self.builder().label.clone();
+        self.terminate(&format!("br label %{end_label}"));
+
+        self.start_block(&else_label);
+        let else_value = self.expression(otherwise);
+        let else_end = self.builder().label.clone();
+        self.terminate(&format!("br label %{end_label}"));
+
+        self.start_block(&end_label);
+        self.value(&format!(
+            "phi %Value [ {then_value}, %{then_end} ], [ {else_value}, %{else_end} ]"
+        ))
+    }
+}
+
+impl StatementVisitor<()> for LlvmEmitter {
+    fn visit_expression_statement(&mut self, expression: &mut ExpressionStatement) {
+        self.expression(&mut expression.expression);
+    }
+
+    fn visit_variable_statement(&mut self, var: &mut VariableStatement) {
+        self.line = var.name.span.line;
+        let value = match var.initalizer {
+            Some(ref mut initalizer) => self.expression(initalizer),
+            None => NIL.to_string(),
+        };
+        self.define(&var.name, &value);
+    }
+
+    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) {
+        let then_label = self.label("then");
+        let else_label = self.label("else");
+        let end_label = self.label("end");
+
+        let condition = self.expression(&mut if_statement.condition);
+        let truthy = self.truthy(&condition);
+        self.terminate(&format!(
+            "br i1 {truthy}, label %{then_label}, label %{else_label}"
+        ));
+
+        self.start_block(&then_label);
+        if_statement.then_branch.accept(self);
+        if !self.builder().terminated {
+            self.terminate(&format!("br label %{end_label}"));
+        }
+
+        self.start_block(&else_label);
+        if let Some(ref mut else_branch) = *if_statement.else_branch {
+            else_branch.accept(self);
+        }
+
+        self.start_block(&end_label);
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &mut WhileStatement) {
+        let condition_label = self.label("while");
+        let body_label = self.label("body");
+        let next_label = self.label("next");
+        let end_label = self.label("end");
+
+        self.start_block(&condition_label);
+        let condition = self.expression(&mut while_statement.condition);
+        let truthy = self.truthy(&condition);
+        self.terminate(&format!(
+            "br i1 {truthy}, label %{body_label}, label %{end_label}"
+        ));
+
+        self.start_block(&body_label);
+        self.builder().loops.push(LoopLabels {
+            name: while_statement
+                .label
+                .as_ref()
+                .map(|label| label.lexeme.clone()),
+            next: next_label.clone(),
+            end: end_label.clone(),
+        });
+        while_statement.body.accept(self);
+        self.builder().loops.pop();
+
+        //`continue` lands here so a `for` loop's increment still runs
+        self.start_block(&next_label);
+        if let Some(ref mut increment) = while_statement.increment {
+            self.expression(increment);
+        }
+        self.terminate(&format!("br label %{condition_label}"));
+
+        self.start_block(&end_label);
+    }
+
+    fn visit_for_in_statement(&mut self, for_in: &mut ForInStatement) {
+        let Iterable::Range {
+            ref mut start,
+            ref operator,
+            ref mut end,
+        } = for_in.iterable
+        else {
+            self.error(
+                &for_in.keyword,
+                "Only ranges can be iterated by the LLVM backend.",
+            );
+            return;
+        };
+
+        //Bounds are checked once up front, after that the counter is always a number
+        let start = self.expression(start);
+        let end = self.expression(end);
+        self.line = operator.span.line;
+        let line = self.line;
+        let first = self.value(&format!(
+            "call double @lux_number(%Value {start}, i32 {line}, %Error* @rt.range)"
+        ));
+        let last = self.value(&format!(
+            "call double @lux_number(%Value {end}, i32 {line}, %Error* @rt.range)"
+        ));
+        let comparison = match operator.token_type {
+            TokenType::DotDotEqual => "ole",
+            _ => "olt",
+        };
+
+        //The counter lives in a hidden local, named with a keyword so no variable can see it
+        self.builder().scopes.push(HashMap::new());
+        let counter = self.declare_local("for");
+        let initial = self.value(&format!("call %Value @lux_num(double {first})"));
+        self.instruction(&format!("store %Value {initial}, %Value* {counter}"));
+
+        let condition_label = self.label("for");
+        let body_label = self.label("body");
+        let next_label = self.label("next");
+        let end_label = self.label("end");
+
+        self.start_block(&condition_label);
+        let current = self.value(&format!("load %Value, %Value* {counter}"));
+        let number = self.value(&format!("extractvalue %Value {current}, 1"));
+        let in_range = self.value(&format!("fcmp {comparison} double {number}, {last}"));
+        self.terminate(&format!(
+            "br i1 {in_range}, label %{body_label}, label %{end_label}"
+        ));
+
+        self.start_block(&body_label);
+        self.builder().loops.push(LoopLabels {
+            name: for_in.label.as_ref().map(|label| label.lexeme.clone()),
+            next: next_label.clone(),
+            end: end_label.clone(),
+        });
+        self.builder().scopes.push(HashMap::new());
+        self.define(&for_in.name, &current);
+        for_in.body.accept(self);
+        self.builder().scopes.pop();
+        self.builder().loops.pop();
+
+        self.start_block(&next_label);
+        let current = self.value(&format!("load %Value, %Value* {counter}"));
+        let number = self.value(&format!("extractvalue %Value {current}, 1"));
+        let following = self.value(&format!("fadd double {number}, 1.0"));
+        let following = self.value(&format!("call %Value @lux_num(double {following})"));
+        self.instruction(&format!("store %Value {following}, %Value* {counter}"));
+        self.terminate(&format!("br label %{condition_label}"));
+
+        self.start_block(&end_label);
+        self.builder().scopes.pop();
+    }
+
+    fn visit_break_statement(&mut self, break_statement: &mut BreakStatement) {
+        let target = self.loop_target(&break_statement.label, |labels| &labels.end);
+        self.terminate(&format!("br label %{target}"));
+    }
+
+    fn visit_continue_statement(&mut self, continue_statement: &mut ContinueStatement) {
+        let target = self.loop_target(&continue_statement.label, |labels| &labels.next);
+        self.terminate(&format!("br label %{target}"));
+    }
+
+    fn visit_try_statement(&mut self, try_statement: &mut TryStatement) {
+        self.error(
+            &try_statement.keyword,
+            "Try statements are not supported by the LLVM backend.",
+        );
+    }
+
+    fn visit_throw_statement(&mut self, throw: &mut ThrowStatement) {
+        //Nothing can catch it, so a throw always ends the program like any other runtime error
+        let value = self.expression(&mut throw.value);
+        self.line = throw.keyword.span.line;
+        let line = self.line;
+
+        self.instruction(&format!(
+            "call void @lux_uncaught(%Value {value}, i32 {line})"
+        ));
+        self.terminate("unreachable");
+    }
+
+    fn visit_block_statement(&mut self, block_statement: &mut BlockStatement) {
+        self.builder().scopes.push(HashMap::new());
+        for statement in &mut block_statement.statements {
+            statement.accept(self);
+        }
+        self.builder().scopes.pop();
+    }
+
+    fn visit_function_statement(&mut self, function_statement: &mut FunctionStatement) {
+        self.line = function_statement.name.span.line;
+
+        //Declared before the body is emitted so the function can call itself
+        let slot = if self.at_global_scope() {
+            self.global(&function_statement.name.lexeme)
+        } else {
+            self.declare_local(&function_statement.name.lexeme)
+        };
+
+        let function = self.function(
+            &function_statement.name.lexeme,
+            &function_statement.parameters,
+            &mut function_statement.body,
+        );
+        self.instruction(&format!("store %Value {function}, %Value* {slot}"));
+    }
+
+    fn visit_return_statement(&mut self, return_statement: &mut ReturnStatement) {
+        self.line = return_statement.keyword.span.line;
+        let value = match return_statement.value {
+            Some(ref mut value) => self.expression(value),
+            None => NIL.to_string(),
+        };
+        self.terminate(&format!("ret %Value {value}"));
+    }
+
+    fn visit_class_statement(&mut self, class_statement: &mut ClassStatement) {
+        self.error(
+            &class_statement.name,
+            "Classes are not supported by the LLVM backend.",
+        );
+    }
+}
+
+impl InterpreterVisitor<String> for LlvmEmitter {
+    fn visit_grouping(&mut self, group: &mut Grouping) -> String {
+        self.expression(&mut group.expression)
+    }
+
+    fn visit_binary(&mut self, bin: &mut Binary) -> String {
+        let left = self.expression(&mut bin.left);
+        let right = self.expression(&mut bin.right);
+        self.binary(&bin.operator, &left, &right)
+    }
+
+    fn visit_unary(&mut self, unary: &mut Unary) -> String {
+        let operand = self.expression(&mut unary.operand);
+        self.line = unary.operator.span.line;
+
+        let function = match unary.operator.token_type {
+            TokenType::Minus => "lux_negate",
+            TokenType::Bang => "lux_not",
+            _ => unreachable!("Parser only builds unary expressions from unary operators"),
+        };
+
+        let line = self.line;
+        self.value(&format!(
+            "call %Value @{function}(%Value {operand}, i32 {line})"
+        ))
+    }
+
+    fn visit_literal(&mut self, lit: &mut Literal) -> String {
+        match lit.value {
+            LiteralType::Nil => NIL.to_string(),
+            LiteralType::Boolean(boolean) => format!(
+                "{{ i32 1, double {}, i8* null }}",
+                double(if boolean { 1.0 } else { 0.0 })
+            ),
+            LiteralType::Number(num) => {
+                format!("{{ i32 2, double {}, i8* null }}", double(num))
+            }
+            LiteralType::String(ref string) => {
+                let pointer = self.string_pointer(string);
+                format!("{{ i32 3, double 0.0, i8* {pointer} }}")
+            }
+            LiteralType::Callable(_)
+            | LiteralType::Instance(_)
+            | LiteralType::List(_)
+            | LiteralType::Map(_) => {
+                unreachable!("Parser never produces runtime values as literals")
+            }
+        }
+    }
+
+    fn visit_ternary(&mut self, tern: &mut Ternary) -> String {
+        let condition = self.expression(&mut tern.evaluator);
+        self.select(&condition, &mut tern.left, &mut tern.right)
+    }
+
+    fn visit_variable(&mut self, var: &mut Variable) -> String {
+        self.line = var.name.span.line;
+
+        match self.lookup(&var.name) {
+            Some(Some(slot)) => self.value(&format!("load %Value, %Value* {slot}")),
+            Some(None) => {
+                let global = self.global(&var.name.lexeme);
+                let value = self.value(&format!("load %Value, %Value* {global}"));
+                let name = self.string_pointer(&var.name.lexeme);
+                let line = self.line;
+                self.value(&format!(
+                    "call %Value @lux_defined(%Value {value}, i8* {name}, i32 {line})"
+                ))
+            }
+            None => NIL.to_string(),
+        }
+    }
+
+    fn visit_assignment(&mut self, assign: &mut Assignment) -> String {
+        let value = self.expression(&mut assign.value);
+        self.assign(&assign.name, &value);
+        value
+    }
+
+    fn visit_compound(&mut self, compound: &mut Compound) -> String {
+        //Property and index targets report themselves as unsupported
+        let errors = self.errors.len();
+        let old = self.expression(&mut compound.target);
+        //Writing back to a target that couldn't be read would report it twice
+        let readable = self.errors.len() == errors;
+        if compound.step {
+            self.line = compound.operator.span.line;
+            let line = self.line;
+            self.value(&format!(
+                "call %Value @lux_check_step(%Value {old}, i32 {line})"
+            ));
+        }
+        let right = self.expression(&mut compound.value);
+        let new = self.binary(&compound.operator, &old, &right);
+
+        if let Expression::Variable(var) = &compound.target
+            && readable
+        {
+            self.assign(&var.name, &new);
+        }
+
+        if compound.postfix { old } else { new }
+    }
+
+    fn visit_logical(&mut self, logical: &mut Logical) -> String {
+        let left = self.expression(&mut logical.left);
+        self.line = logical.operator.span.line;
+
+        let left_end = self.builder().label.clone();
+        let right_label = self.label("rhs");
+        let end_label = self.label("end");
+
+        //`or` skips the right side when the left is true, `and` when it is false
+        let truthy = self.truthy(&left);
+        let (on_true, on_false) = if logical.operator.token_type == TokenType::Or {
+            (&end_label, &right_label)
+        } else {
+            (&right_label, &end_label)
+        };
+        self.terminate(&format!(
+            "br i1 {truthy}, label %{on_true}, label %{on_false}"
+        ));
+
+        self.start_block(&right_label);
+        let right = self.expression(&mut logical.right);
+        let right_end = self.builder().label.clone();
+        self.terminate(&format!("br label %{end_label}"));
+
+        self.start_block(&end_label);
+        self.value(&format!(
+            "phi %Value [ {left}, %{left_end} ], [ {right}, %{right_end} ]"
+        ))
+    }
+
+    fn visit_call(&mut self, call: &mut Call) -> String {
+        let callee = self.expression(&mut call.callee);
+        let arguments: Vec<String> = call
+            .arguments
+            .iter_mut()
+            .map(|argument| format!("%Value {}", self.expression(argument)))
+            .collect();
+
+        self.line = call.paren.span.line;
+        let line = self.line;
+        let pointer = self.value(&format!(
+            "call i8* @lux_callee(%Value {callee}, i32 {}, i32 {line})",
+            arguments.len()
+        ));
+        let function = self.value(&format!(
+            "bitcast i8* {pointer} to {}*",
+            function_type(arguments.len())
+        ));
+        //Counted like the interpreters' frames, so runaway recursion is an error, not a crash
+        self.instruction(&format!("call void @lux_enter(i32 {line})"));
+        let result = self.value(&format!("call %Value {function}({})", arguments.join(", ")));
+        self.instruction("call void @lux_leave()");
+        result
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda) -> String {
+        self.line = lambda.keyword.span.line;
+        self.function("<lambda>", &lambda.parameters, &mut lambda.body)
+    }
+
+    fn visit_interpolation(&mut self, interpolation: &mut Interpolation) -> String {
+        //Folded into one string a part at a time, starting from ""
+        let empty = self.string_pointer("");
+        let mut joined = format!("{{ i32 3, double 0.0, i8* {empty} }}");
+        for part in &mut interpolation.parts {
+            let part = self.expression(part);
+            joined = self.value(&format!(
+                "call %Value @lux_concat(%Value {joined}, %Value {part})"
+            ));
+        }
+        joined
+    }
+
+    fn visit_list(&mut self, list: &mut List) -> String {
+        self.error(
+            &list.bracket,
+            "Lists are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_map(&mut self, map: &mut Map) -> String {
+        self.error(&map.brace, "Maps are not supported by the LLVM backend.");
+        NIL.to_string()
+    }
+
+    fn visit_index(&mut self, index: &mut Index) -> String {
+        self.error(
+            &index.bracket,
+            "Lists and maps are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_setindex(&mut self, set: &mut SetIndex) -> String {
+        self.error(
+            &set.bracket,
+            "Lists and maps are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_slice(&mut self, slice: &mut Slice) -> String {
+        self.error(
+            &slice.bracket,
+            "Lists are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_get(&mut self, get: &mut Get) -> String {
+        self.error(
+            &get.name,
+            "Properties are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_set(&mut self, set: &mut Set) -> String {
+        self.error(
+            &set.name,
+            "Properties are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_this(&mut self, this: &mut This) -> String {
+        self.error(
+            &this.keyword,
+            "Classes are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+
+    fn visit_super(&mut self, sup: &mut Super) -> String {
+        self.error(
+            &sup.keyword,
+            "Classes are not supported by the LLVM backend.",
+        );
+        NIL.to_string()
+    }
+}
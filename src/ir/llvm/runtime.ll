@@ -0,0 +1,564 @@
+; Runtime support for dynamically typed Lux values, linked into every emitted module.
+; Values are { tag, number payload, pointer payload }, tags: 0 nil, 1 bool (payload 0 or 1),
+; 2 number, 3 string (char*), 4 function (%FnDesc*), 5 a global that was never defined.
+; Strings are never freed, programs are expected to be short lived.
+; Errors are { code, message } pairs, reported on stderr like the interpreters do.
+
+declare i32 @printf(i8*, ...)
+declare i32 @dprintf(i32, i8*, ...)
+declare i32 @snprintf(i8*, i64, i8*, ...)
+declare i8* @malloc(i64)
+declare i64 @strlen(i8*)
+declare i32 @strcmp(i8*, i8*)
+declare i8* @memcpy(i8*, i8*, i64)
+declare i8* @memset(i8*, i32, i64)
+declare i8* @strchr(i8*, i32)
+declare i64 @strtol(i8*, i8**, i32)
+declare double @strtod(i8*, i8**)
+declare i64 @time(i64*)
+declare void @exit(i32)
+declare double @llvm.pow.f64(double, double)
+
+@lux_depth = internal global i32 0
+
+define internal void @lux_fail(i32 %line, i8* %code, i8* %message) {
+entry:
+  %format = load i8*, i8** @rt.error
+  %file = load i8*, i8** @rt.file
+  call i32 (i32, i8*, ...) @dprintf(i32 2, i8* %format, i8* %code, i8* %message, i8* %file, i32 %line)
+  call void @exit(i32 70)
+  unreachable
+}
+
+define internal i8* @lux_error_code(%Error* %error) {
+entry:
+  %code.ptr = getelementptr inbounds %Error, %Error* %error, i32 0, i32 0
+  %code = load i8*, i8** %code.ptr
+  ret i8* %code
+}
+
+define internal i8* @lux_error_message(%Error* %error) {
+entry:
+  %message.ptr = getelementptr inbounds %Error, %Error* %error, i32 0, i32 1
+  %message = load i8*, i8** %message.ptr
+  ret i8* %message
+}
+
+define internal void @lux_error(i32 %line, %Error* %error) {
+entry:
+  %code = call i8* @lux_error_code(%Error* %error)
+  %message = call i8* @lux_error_message(%Error* %error)
+  call void @lux_fail(i32 %line, i8* %code, i8* %message)
+  unreachable
+}
+
+; Calls nest at most as deep as the VM's frames, so runaway recursion fails like it does there
+define internal void @lux_enter(i32 %line) {
+entry:
+  %depth = load i32, i32* @lux_depth
+  %deeper = add i32 %depth, 1
+  %max = load i32, i32* @rt.frames_max
+  %overflow = icmp sge i32 %deeper, %max
+  br i1 %overflow, label %bad, label %ok
+ok:
+  store i32 %deeper, i32* @lux_depth
+  ret void
+bad:
+  call void @lux_error(i32 %line, %Error* @rt.overflow)
+  unreachable
+}
+
+define internal void @lux_leave() {
+entry:
+  %depth = load i32, i32* @lux_depth
+  %shallower = sub i32 %depth, 1
+  store i32 %shallower, i32* @lux_depth
+  ret void
+}
+
+define internal void @lux_uncaught(%Value %v, i32 %line) {
+entry:
+  %prefix = call i8* @lux_error_message(%Error* @rt.uncaught)
+  %head = insertvalue %Value { i32 3, double 0.0, i8* null }, i8* %prefix, 2
+  %joined = call %Value @lux_concat(%Value %head, %Value %v)
+  %message = extractvalue %Value %joined, 2
+  %code = call i8* @lux_error_code(%Error* @rt.uncaught)
+  call void @lux_fail(i32 %line, i8* %code, i8* %message)
+  unreachable
+}
+
+define internal %Value @lux_bool(i1 %b) {
+entry:
+  %d = uitofp i1 %b to double
+  %v = insertvalue %Value { i32 1, double 0.0, i8* null }, double %d, 1
+  ret %Value %v
+}
+
+define internal %Value @lux_num(double %n) {
+entry:
+  %v = insertvalue %Value { i32 2, double 0.0, i8* null }, double %n, 1
+  ret %Value %v
+}
+
+define internal double @lux_number(%Value %v, i32 %line, %Error* %error) {
+entry:
+  %tag = extractvalue %Value %v, 0
+  %is = icmp eq i32 %tag, 2
+  br i1 %is, label %ok, label %bad
+ok:
+  %n = extractvalue %Value %v, 1
+  ret double %n
+bad:
+  call void @lux_error(i32 %line, %Error* %error)
+  unreachable
+}
+
+define internal i1 @lux_boolean(%Value %v, i32 %line, %Error* %error) {
+entry:
+  %tag = extractvalue %Value %v, 0
+  %is = icmp eq i32 %tag, 1
+  br i1 %is, label %ok, label %bad
+ok:
+  %n = extractvalue %Value %v, 1
+  %b = fcmp one double %n, 0.0
+  ret i1 %b
+bad:
+  call void @lux_error(i32 %line, %Error* %error)
+  unreachable
+}
+
+define internal i1 @lux_truthy(%Value %v, i32 %line) {
+entry:
+  %b = call i1 @lux_boolean(%Value %v, i32 %line, %Error* @rt.condition)
+  ret i1 %b
+}
+
+define internal %Value @lux_not(%Value %v, i32 %line) {
+entry:
+  %b = call i1 @lux_boolean(%Value %v, i32 %line, %Error* @rt.operand_boolean)
+  %n = xor i1 %b, true
+  %r = call %Value @lux_bool(i1 %n)
+  ret %Value %r
+}
+
+define internal %Value @lux_negate(%Value %v, i32 %line) {
+entry:
+  %n = call double @lux_number(%Value %v, i32 %line, %Error* @rt.operand_number)
+  %m = fneg double %n
+  %r = call %Value @lux_num(double %m)
+  ret %Value %r
+}
+
+define internal %Value @lux_check_step(%Value %v, i32 %line) {
+entry:
+  %n = call double @lux_number(%Value %v, i32 %line, %Error* @rt.step)
+  ret %Value %v
+}
+
+define internal %Value @lux_sub(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %n = fsub double %x, %y
+  %r = call %Value @lux_num(double %n)
+  ret %Value %r
+}
+
+define internal %Value @lux_mul(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %n = fmul double %x, %y
+  %r = call %Value @lux_num(double %n)
+  ret %Value %r
+}
+
+define internal %Value @lux_div(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %n = fdiv double %x, %y
+  %r = call %Value @lux_num(double %n)
+  ret %Value %r
+}
+
+define internal %Value @lux_mod(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %n = frem double %x, %y
+  %r = call %Value @lux_num(double %n)
+  ret %Value %r
+}
+
+define internal %Value @lux_pow(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %n = call double @llvm.pow.f64(double %x, double %y)
+  %r = call %Value @lux_num(double %n)
+  ret %Value %r
+}
+
+define internal %Value @lux_less(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %c = fcmp olt double %x, %y
+  %r = call %Value @lux_bool(i1 %c)
+  ret %Value %r
+}
+
+define internal %Value @lux_less_equal(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %c = fcmp ole double %x, %y
+  %r = call %Value @lux_bool(i1 %c)
+  ret %Value %r
+}
+
+define internal %Value @lux_greater(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %c = fcmp ogt double %x, %y
+  %r = call %Value @lux_bool(i1 %c)
+  ret %Value %r
+}
+
+define internal %Value @lux_greater_equal(%Value %a, %Value %b, i32 %line) {
+entry:
+  %x = call double @lux_number(%Value %a, i32 %line, %Error* @rt.operands_numbers)
+  %y = call double @lux_number(%Value %b, i32 %line, %Error* @rt.operands_numbers)
+  %c = fcmp oge double %x, %y
+  %r = call %Value @lux_bool(i1 %c)
+  ret %Value %r
+}
+
+; Tries increasingly precise `%#.*e` formats until one reads back exactly, leaving the shortest
+; digits Rust would print followed by their exponent. A number exactly halfway between two
+; shortest forms rounds to even here where Rust rounds away from zero
+define internal i8* @lux_scientific(double %n) {
+entry:
+  %buffer = call i8* @malloc(i64 32)
+  %format = load i8*, i8** @rt.exponent
+  br label %try
+try:
+  %precision = phi i32 [ 0, %entry ], [ %more, %retry ]
+  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buffer, i64 32, i8* %format, i32 %precision, double %n)
+  %back = call double @strtod(i8* %buffer, i8** null)
+  %exact = fcmp oeq double %back, %n
+  ; 17 significant digits always read back exactly
+  %longest = icmp eq i32 %precision, 16
+  %found = or i1 %exact, %longest
+  br i1 %found, label %done, label %retry
+retry:
+  %more = add i32 %precision, 1
+  br label %try
+done:
+  ret i8* %buffer
+}
+
+; Writes numbers the way Rust's `{}` does, every digit in full with no exponent, so `1e21` is
+; `1000000000000000000000`, and `NaN`, `inf` and `-inf` are spelled the same
+define internal i8* @lux_number_to_string(double %n) {
+entry:
+  %nan = fcmp uno double %n, %n
+  br i1 %nan, label %not.number, label %infinite.check
+not.number:
+  %nan.text = load i8*, i8** @rt.nan
+  ret i8* %nan.text
+infinite.check:
+  %infinite = fcmp oeq double %n, 0x7FF0000000000000
+  br i1 %infinite, label %infinity, label %negative.infinite.check
+infinity:
+  %inf.text = load i8*, i8** @rt.inf
+  ret i8* %inf.text
+negative.infinite.check:
+  %negative.infinite = fcmp oeq double %n, 0xFFF0000000000000
+  br i1 %negative.infinite, label %negative.infinity, label %finite
+negative.infinity:
+  %negative.inf.text = load i8*, i8** @rt.negative_inf
+  ret i8* %negative.inf.text
+finite:
+  ; `[-]d.ddde[+-]x`, the leading digit is moved over the `.` so the digits sit together
+  %scientific = call i8* @lux_scientific(double %n)
+  %e = call i8* @strchr(i8* %scientific, i32 101)
+  %exponent.text = getelementptr inbounds i8, i8* %e, i64 1
+  %exponent = call i64 @strtol(i8* %exponent.text, i8** null, i32 10)
+  %out = call i8* @malloc(i64 400)
+  %first = load i8, i8* %scientific
+  %negative = icmp eq i8 %first, 45
+  %sign = zext i1 %negative to i64
+  ; Overwritten by the first digit when the number is positive
+  store i8 45, i8* %out
+  %lead.at = getelementptr inbounds i8, i8* %scientific, i64 %sign
+  %lead = load i8, i8* %lead.at
+  %digits = getelementptr inbounds i8, i8* %lead.at, i64 1
+  store i8 %lead, i8* %digits
+  %digits.start = ptrtoint i8* %digits to i64
+  %digits.end = ptrtoint i8* %e to i64
+  %count = sub i64 %digits.end, %digits.start
+  %body = getelementptr inbounds i8, i8* %out, i64 %sign
+  br label %trim
+trim:
+  %length = phi i64 [ %count, %finite ], [ %shorter, %trim.step ]
+  %several = icmp ugt i64 %length, 1
+  br i1 %several, label %trim.check, label %place
+trim.check:
+  %last.index = sub i64 %length, 1
+  %last.at = getelementptr inbounds i8, i8* %digits, i64 %last.index
+  %last = load i8, i8* %last.at
+  %zero = icmp eq i8 %last, 48
+  br i1 %zero, label %trim.step, label %place
+trim.step:
+  %shorter = sub i64 %length, 1
+  br label %trim
+place:
+  %small = icmp slt i64 %exponent, 0
+  br i1 %small, label %fraction, label %whole.check
+fraction:
+  ; 0.000ddd
+  store i8 48, i8* %body
+  %point.at = getelementptr inbounds i8, i8* %body, i64 1
+  store i8 46, i8* %point.at
+  %zeros.at = getelementptr inbounds i8, i8* %body, i64 2
+  %zeros = sub i64 -1, %exponent
+  call i8* @memset(i8* %zeros.at, i32 48, i64 %zeros)
+  %fraction.at = getelementptr inbounds i8, i8* %zeros.at, i64 %zeros
+  call i8* @memcpy(i8* %fraction.at, i8* %digits, i64 %length)
+  %fraction.end = getelementptr inbounds i8, i8* %fraction.at, i64 %length
+  store i8 0, i8* %fraction.end
+  ret i8* %out
+whole.check:
+  %last.place = sub i64 %length, 1
+  %integral = icmp sge i64 %exponent, %last.place
+  br i1 %integral, label %integer, label %decimal
+integer:
+  ; ddd000
+  call i8* @memcpy(i8* %body, i8* %digits, i64 %length)
+  %padding.at = getelementptr inbounds i8, i8* %body, i64 %length
+  %padding = sub i64 %exponent, %last.place
+  call i8* @memset(i8* %padding.at, i32 48, i64 %padding)
+  %integer.end = getelementptr inbounds i8, i8* %padding.at, i64 %padding
+  store i8 0, i8* %integer.end
+  ret i8* %out
+decimal:
+  ; dd.ddd
+  %whole = add i64 %exponent, 1
+  call i8* @memcpy(i8* %body, i8* %digits, i64 %whole)
+  %decimal.point = getelementptr inbounds i8, i8* %body, i64 %whole
+  store i8 46, i8* %decimal.point
+  %rest.at = getelementptr inbounds i8, i8* %decimal.point, i64 1
+  %rest.from = getelementptr inbounds i8, i8* %digits, i64 %whole
+  %rest = sub i64 %length, %whole
+  call i8* @memcpy(i8* %rest.at, i8* %rest.from, i64 %rest)
+  %decimal.end = getelementptr inbounds i8, i8* %rest.at, i64 %rest
+  store i8 0, i8* %decimal.end
+  ret i8* %out
+}
+
+define internal i8* @lux_to_string(%Value %v) {
+entry:
+  %tag = extractvalue %Value %v, 0
+  switch i32 %tag, label %nil [ i32 1, label %bool
+                                i32 2, label %number
+                                i32 3, label %string
+                                i32 4, label %function ]
+nil:
+  %nil.text = load i8*, i8** @rt.nil
+  ret i8* %nil.text
+bool:
+  %payload = extractvalue %Value %v, 1
+  %b = fcmp one double %payload, 0.0
+  %true.text = load i8*, i8** @rt.true
+  %false.text = load i8*, i8** @rt.false
+  %bool.text = select i1 %b, i8* %true.text, i8* %false.text
+  ret i8* %bool.text
+number:
+  %n = extractvalue %Value %v, 1
+  %number.text = call i8* @lux_number_to_string(double %n)
+  ret i8* %number.text
+string:
+  %string.text = extractvalue %Value %v, 2
+  ret i8* %string.text
+function:
+  %raw = extractvalue %Value %v, 2
+  %desc = bitcast i8* %raw to %FnDesc*
+  %name.ptr = getelementptr inbounds %FnDesc, %FnDesc* %desc, i32 0, i32 1
+  %name = load i8*, i8** %name.ptr
+  %length = call i64 @strlen(i8* %name)
+  %size = add i64 %length, 6
+  %buffer = call i8* @malloc(i64 %size)
+  %format = load i8*, i8** @rt.fn
+  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buffer, i64 %size, i8* %format, i8* %name)
+  ret i8* %buffer
+}
+
+; Joins the text of any two values, used by `+` on strings and by interpolation
+define internal %Value @lux_concat(%Value %a, %Value %b) {
+entry:
+  %left = call i8* @lux_to_string(%Value %a)
+  %right = call i8* @lux_to_string(%Value %b)
+  %left.length = call i64 @strlen(i8* %left)
+  %right.length = call i64 @strlen(i8* %right)
+  %total = add i64 %left.length, %right.length
+  %size = add i64 %total, 1
+  %buffer = call i8* @malloc(i64 %size)
+  call i8* @memcpy(i8* %buffer, i8* %left, i64 %left.length)
+  %tail = getelementptr inbounds i8, i8* %buffer, i64 %left.length
+  call i8* @memcpy(i8* %tail, i8* %right, i64 %right.length)
+  %end = getelementptr inbounds i8, i8* %buffer, i64 %total
+  store i8 0, i8* %end
+  %string = insertvalue %Value { i32 3, double 0.0, i8* null }, i8* %buffer, 2
+  ret %Value %string
+}
+
+define internal %Value @lux_add(%Value %a, %Value %b, i32 %line) {
+entry:
+  %ta = extractvalue %Value %a, 0
+  %tb = extractvalue %Value %b, 0
+  %a.number = icmp eq i32 %ta, 2
+  %b.number = icmp eq i32 %tb, 2
+  %both = and i1 %a.number, %b.number
+  br i1 %both, label %numbers, label %check.string
+numbers:
+  %x = extractvalue %Value %a, 1
+  %y = extractvalue %Value %b, 1
+  %sum = fadd double %x, %y
+  %number = call %Value @lux_num(double %sum)
+  ret %Value %number
+check.string:
+  ; Strings swallow anything printable on their right, like the tree-walker
+  %a.string = icmp eq i32 %ta, 3
+  %b.printable = icmp ult i32 %tb, 4
+  %concat = and i1 %a.string, %b.printable
+  br i1 %concat, label %strings, label %bad
+strings:
+  %string = call %Value @lux_concat(%Value %a, %Value %b)
+  ret %Value %string
+bad:
+  call void @lux_error(i32 %line, %Error* @rt.add)
+  unreachable
+}
+
+define internal i1 @lux_equal(%Value %a, %Value %b) {
+entry:
+  %ta = extractvalue %Value %a, 0
+  %tb = extractvalue %Value %b, 0
+  %same = icmp eq i32 %ta, %tb
+  br i1 %same, label %compare, label %different
+different:
+  ret i1 false
+compare:
+  switch i32 %ta, label %nil [ i32 1, label %number
+                               i32 2, label %number
+                               i32 3, label %string
+                               i32 4, label %pointer ]
+nil:
+  ret i1 true
+number:
+  %x = extractvalue %Value %a, 1
+  %y = extractvalue %Value %b, 1
+  %numbers = fcmp oeq double %x, %y
+  ret i1 %numbers
+string:
+  %left = extractvalue %Value %a, 2
+  %right = extractvalue %Value %b, 2
+  %order = call i32 @strcmp(i8* %left, i8* %right)
+  %strings = icmp eq i32 %order, 0
+  ret i1 %strings
+pointer:
+  %left.pointer = extractvalue %Value %a, 2
+  %right.pointer = extractvalue %Value %b, 2
+  %pointers = icmp eq i8* %left.pointer, %right.pointer
+  ret i1 %pointers
+}
+
+define internal %Value @lux_eq(%Value %a, %Value %b, i32 %line) {
+entry:
+  %e = call i1 @lux_equal(%Value %a, %Value %b)
+  %r = call %Value @lux_bool(i1 %e)
+  ret %Value %r
+}
+
+define internal %Value @lux_ne(%Value %a, %Value %b, i32 %line) {
+entry:
+  %e = call i1 @lux_equal(%Value %a, %Value %b)
+  %n = xor i1 %e, true
+  %r = call %Value @lux_bool(i1 %n)
+  ret %Value %r
+}
+
+; Checks a value can be called with `argc` arguments, handing back the function to call
+define internal i8* @lux_callee(%Value %callee, i32 %argc, i32 %line) {
+entry:
+  %tag = extractvalue %Value %callee, 0
+  %is = icmp eq i32 %tag, 4
+  br i1 %is, label %function, label %bad
+function:
+  %raw = extractvalue %Value %callee, 2
+  %desc = bitcast i8* %raw to %FnDesc*
+  %arity.ptr = getelementptr inbounds %FnDesc, %FnDesc* %desc, i32 0, i32 2
+  %arity = load i32, i32* %arity.ptr
+  %matches = icmp eq i32 %arity, %argc
+  br i1 %matches, label %ok, label %mismatch
+ok:
+  %function.ptr = getelementptr inbounds %FnDesc, %FnDesc* %desc, i32 0, i32 0
+  %pointer = load i8*, i8** %function.ptr
+  ret i8* %pointer
+mismatch:
+  %buffer = call i8* @malloc(i64 64)
+  %format = call i8* @lux_error_message(%Error* @rt.arity)
+  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buffer, i64 64, i8* %format, i32 %arity, i32 %argc)
+  %arity.code = call i8* @lux_error_code(%Error* @rt.arity)
+  call void @lux_fail(i32 %line, i8* %arity.code, i8* %buffer)
+  unreachable
+bad:
+  call void @lux_error(i32 %line, %Error* @rt.call)
+  unreachable
+}
+
+; Globals start out undefined, reading one before its declaration ran is an error
+define internal %Value @lux_defined(%Value %v, i8* %name, i32 %line) {
+entry:
+  %tag = extractvalue %Value %v, 0
+  %undefined = icmp eq i32 %tag, 5
+  br i1 %undefined, label %bad, label %ok
+ok:
+  ret %Value %v
+bad:
+  %length = call i64 @strlen(i8* %name)
+  %size = add i64 %length, 21
+  %buffer = call i8* @malloc(i64 %size)
+  %format = call i8* @lux_error_message(%Error* @rt.undefined)
+  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buffer, i64 %size, i8* %format, i8* %name)
+  %code = call i8* @lux_error_code(%Error* @rt.undefined)
+  call void @lux_fail(i32 %line, i8* %code, i8* %buffer)
+  unreachable
+}
+
+define internal %Value @lux_native_print(%Value %v) {
+entry:
+  %text = call i8* @lux_to_string(%Value %v)
+  %format = load i8*, i8** @rt.print
+  call i32 (i8*, ...) @printf(i8* %format, i8* %text)
+  ret %Value zeroinitializer
+}
+
+define internal %Value @lux_native_println(%Value %v) {
+entry:
+  %text = call i8* @lux_to_string(%Value %v)
+  %format = load i8*, i8** @rt.println
+  call i32 (i8*, ...) @printf(i8* %format, i8* %text)
+  ret %Value zeroinitializer
+}
+
+define internal %Value @lux_native_clock() {
+entry:
+  %seconds = call i64 @time(i64* null)
+  %n = sitofp i64 %seconds to double
+  %r = call %Value @lux_num(double %n)
+  ret %Value %r
+}
@@ -0,0 +1,102 @@
+///A single VM instruction, operands are indices into the owning chunk's constant pool, the
+///current frame's stack slots, the global table or jump distances
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum OpCode {
+    //Literals
+    Constant(u32),
+    Nil,
+    True,
+    False,
+
+    //Stack and variables
+    Pop,
+    ///Pushes a copy of the value this many slots below the top
+    Dup(u32),
+    ///Overwrites the value this many slots below the top with the top, which stays in place
+    Store(u32),
+    GetLocal(u32),
+    SetLocal(u32),
+    GetGlobal(u32),
+    DefineGlobal(u32),
+    SetGlobal(u32),
+    GetUpvalue(u32),
+    SetUpvalue(u32),
+    CloseUpvalue,
+
+    //Objects, operands are the constant holding the property name
+    GetProperty(u32),
+    SetProperty(u32),
+    GetSuper(u32),
+
+    //Lists and maps
+    ///Collects the top N values into a new list, the deepest becomes the first element
+    BuildList(u32),
+    ///Collects the top N key and value pairs into a new map, keys sit below their values
+    BuildMap(u32),
+    GetIndex,
+    ///Stack holds list or map, index and value, leaves the value behind
+    SetIndex,
+    ///Stack holds list, start and end, a nil bound means the matching end of the list
+    Slice,
+
+    //Operators
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    Not,
+    Negate,
+    ///Fails unless the top is a number, `++` and `--` only step numbers
+    CheckStep,
+    ///Fails unless the top is a boolean, the left side of `and` and `or` must be one
+    CheckLogical,
+    ///Formats the top N values like `print` and joins them into one string
+    Interpolate(u32),
+
+    //Control flow, jumps are relative to the instruction after the jump
+    Jump(u32),
+    JumpIfFalse(u32),
+    Loop(u32),
+    ///Jumps when the top of the stack is nil, leaving it there
+    JumpIfNil(u32),
+
+    //For-in loops
+    ///Replaces the iterable on top of the stack with something `IterNext` can step
+    Iterator,
+    ///Replaces the two range bounds with an iterator over them, true for `..=`
+    Range(bool),
+    ///Pushes the next value of the iterator on top of the stack. Built-in iterators skip the
+    ///`JumpIfNil` that follows and jump by the operand once they run out, user iterators are
+    ///called and leave the `JumpIfNil` to spot the nil that ends them
+    IterNext(u32),
+
+    //Exceptions
+    ///Starts a `try`, a runtime error or throw before the matching `EndTry` unwinds to here and
+    ///jumps by the operand with the fault on top of the stack
+    Try(u32),
+    EndTry,
+    ///Raises the top of the stack, a fault caught by `Try` is raised again unchanged
+    Throw,
+    ///Replaces the fault on top of the stack with what `catch` receives, the thrown value or a
+    ///map with the error's message and line
+    Caught,
+
+    //Functions and classes
+    Call(u32),
+    Closure(u32),
+    Return,
+    Class(u32),
+    Inherit,
+    Method(u32),
+
+    ///Prints the top of the stack unless it is nil, used for REPL expression statements
+    Echo,
+}
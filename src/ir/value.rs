@@ -0,0 +1,437 @@
+use super::{
+    chunk::Chunk,
+    vm::Fault,
+};
+use crate::{
+    diagnostics::codes::{
+        self,
+        Failure,
+    },
+    types::lux_functions::map::{
+        self,
+        MapKey,
+    },
+};
+use std::{
+    cell::RefCell,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    fmt::{
+        Display,
+        Formatter,
+    },
+    io::Write,
+    rc::Rc,
+    time::SystemTime,
+};
+
+///Runtime value of the VM, unlike `LiteralType` functions carry compiled chunks rather than syntax
+///trees and enviroments
+#[derive(Clone)]
+pub(crate) enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(Rc<str>),
+    ///Only ever lives in a constant pool, `OpCode::Closure` wraps it before it reaches the stack
+    Function(Rc<Function>),
+    Closure(Rc<Closure>),
+    Native(Native),
+    Class(Rc<RefCell<Class>>),
+    Instance(Rc<RefCell<Instance>>),
+    BoundMethod(Rc<BoundMethod>),
+    List(ListRef),
+    Map(Rc<RefCell<BTreeMap<MapKey, Value>>>),
+    ///Only ever lives in a `for-in` loop's hidden local, `OpCode::IterNext` steps it
+    Iterator(Rc<RefCell<Iteration>>),
+    ///Only ever lives in a `try`'s hidden local, `OpCode::Throw` raises it again unchanged
+    Fault(Rc<Fault>),
+}
+
+///Lists are shared, every copy of the value sees changes made through any other
+pub(crate) type ListRef = Rc<RefCell<Vec<Value>>>;
+
+///The state of a `for-in` loop over a built-in value, user iterators are called directly instead
+pub(crate) enum Iteration {
+    ///Lists are read by position each step, so pushing inside the loop extends it
+    List(ListRef, usize),
+    ///Map keys and string characters are taken when the loop starts
+    Values(std::vec::IntoIter<Value>),
+    Range {
+        next: f64,
+        end: f64,
+        inclusive: bool,
+    },
+}
+
+impl Iterator for Iteration {
+    type Item = Value;
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            Iteration::List(list, index) => {
+                let element = list.borrow().get(*index).cloned();
+                *index += 1;
+                element
+            }
+            Iteration::Values(values) => values.next(),
+            Iteration::Range {
+                next,
+                end,
+                inclusive,
+            } => {
+                let in_range = if *inclusive {
+                    *next <= *end
+                } else {
+                    *next < *end
+                };
+                let value = in_range.then_some(Value::Number(*next));
+                *next += 1.0;
+                value
+            }
+        }
+    }
+}
+
+///How a closure finds one of its captured variables when it is created
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct UpvalueDescriptor {
+    ///True when the variable is a local of the directly enclosing function, otherwise `index`
+    ///refers to one of the enclosing function's own upvalues
+    pub(crate) is_local: bool,
+    pub(crate) index: u32,
+}
+
+///A compiled function body
+pub(crate) struct Function {
+    pub(crate) name: String,
+    pub(crate) arity: usize,
+    pub(crate) chunk: Chunk,
+    pub(crate) upvalues: Vec<UpvalueDescriptor>,
+}
+
+///A captured variable, it points into the stack while the variable's frame is alive and takes
+///ownership of the value once that frame returns
+pub(crate) enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+pub(crate) struct Closure {
+    pub(crate) function: Rc<Function>,
+    pub(crate) upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+///Built-in functions, the VM's counterpart to the tree-walker's native `Functions`
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Native {
+    Clock,
+    Print,
+    Println,
+    Len,
+    Push,
+    Pop,
+    Has,
+    Remove,
+    Keys,
+}
+
+impl Native {
+    pub(crate) fn arity(&self) -> usize {
+        match self {
+            Native::Clock => 0,
+            Native::Print | Native::Println | Native::Len | Native::Pop | Native::Keys => 1,
+            Native::Push | Native::Has | Native::Remove => 2,
+        }
+    }
+
+    ///Errors carry the same messages as the tree-walker's natives, `print` and `println` write
+    ///to `out`
+    pub(crate) fn call(&self, arguments: &[Value], out: &mut impl Write) -> Result<Value, Failure> {
+        match self {
+            Native::Clock => Ok(Value::Number(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .expect("Died getting Unix Time")
+                    .as_secs() as f64,
+            )),
+            Native::Print => {
+                write!(out, "{}", arguments[0]).expect("Failed writing to stdout");
+                Ok(Value::Nil)
+            }
+            Native::Println => {
+                writeln!(out, "{}", arguments[0]).expect("Failed writing to stdout");
+                Ok(Value::Nil)
+            }
+            Native::Len => match &arguments[0] {
+                Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+                Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+                Value::String(string) => Ok(Value::Number(string.chars().count() as f64)),
+                _ => Err(Failure::new(
+                    &codes::INVALID_ARGUMENT,
+                    "len expects a list, map or string.",
+                )),
+            },
+            Native::Push => match &arguments[0] {
+                Value::List(list) => {
+                    list.borrow_mut().push(arguments[1].clone());
+                    Ok(Value::Nil)
+                }
+                _ => Err(Failure::new(
+                    &codes::INVALID_ARGUMENT,
+                    "push expects a list.",
+                )),
+            },
+            Native::Pop => match &arguments[0] {
+                Value::List(list) => list.borrow_mut().pop().ok_or_else(|| {
+                    Failure::new(&codes::INVALID_ARGUMENT, "Can't pop from an empty list.")
+                }),
+                _ => Err(Failure::new(
+                    &codes::INVALID_ARGUMENT,
+                    "pop expects a list.",
+                )),
+            },
+            Native::Has => match &arguments[0] {
+                Value::Map(map) => Ok(Value::Boolean(
+                    map.borrow().contains_key(&MapKey::try_from(&arguments[1])?),
+                )),
+                _ => Err(Failure::new(&codes::INVALID_ARGUMENT, "has expects a map.")),
+            },
+            Native::Remove => match &arguments[0] {
+                Value::Map(map) => {
+                    let key = MapKey::try_from(&arguments[1])?;
+                    let removed = map.borrow_mut().remove(&key);
+                    removed.ok_or_else(|| map::missing(&key))
+                }
+                _ => Err(Failure::new(
+                    &codes::INVALID_ARGUMENT,
+                    "remove expects a map.",
+                )),
+            },
+            Native::Keys => match &arguments[0] {
+                Value::Map(map) => {
+                    let keys = map.borrow().keys().map(Value::from).collect();
+                    Ok(Value::List(Rc::new(RefCell::new(keys))))
+                }
+                _ => Err(Failure::new(
+                    &codes::INVALID_ARGUMENT,
+                    "keys expects a map.",
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<&Value> for MapKey {
+    type Error = Failure;
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(boolean) => Ok(MapKey::Boolean(*boolean)),
+            Value::Number(number) => Ok(MapKey::number(*number)),
+            Value::String(string) => Ok(MapKey::String(string.to_string())),
+            _ => Err(Failure::from(&codes::INVALID_KEY)),
+        }
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Boolean(boolean) => Value::Boolean(*boolean),
+            MapKey::Number(number) => Value::Number(*number),
+            MapKey::String(string) => Value::String(Rc::from(string.as_str())),
+        }
+    }
+}
+
+pub(crate) struct Class {
+    pub(crate) name: String,
+    ///Inherited methods are copied in when the class is declared, so lookups never walk a chain
+    pub(crate) methods: HashMap<String, Rc<Closure>>,
+}
+
+pub(crate) struct Instance {
+    pub(crate) class: Rc<RefCell<Class>>,
+    pub(crate) fields: HashMap<String, Value>,
+}
+
+///A method pulled off an instance, remembers the instance to use as `this`
+pub(crate) struct BoundMethod {
+    pub(crate) receiver: Value,
+    pub(crate) method: Rc<Closure>,
+}
+
+impl PartialEq for Value {
+    ///Primitives, lists and maps compare by value, everything else only equals itself
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Boolean(left), Value::Boolean(right)) => left == right,
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::String(left), Value::String(right)) => left == right,
+            (Value::Function(left), Value::Function(right)) => Rc::ptr_eq(left, right),
+            (Value::Closure(left), Value::Closure(right)) => Rc::ptr_eq(left, right),
+            (Value::Native(left), Value::Native(right)) => left == right,
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => Rc::ptr_eq(left, right),
+            (Value::BoundMethod(left), Value::BoundMethod(right)) => Rc::ptr_eq(left, right),
+            (Value::List(_), Value::List(_)) | (Value::Map(_), Value::Map(_)) => {
+                collections_equal(self, other, &mut Vec::new())
+            }
+            (Value::Iterator(left), Value::Iterator(right)) => Rc::ptr_eq(left, right),
+            (Value::Fault(left), Value::Fault(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+///Compares lists element by element and maps entry by entry, a pair already in `comparing` is
+///taken as equal so collections that contain themselves finish, any difference is still found
+///where the pair was first reached
+fn collections_equal(
+    left: &Value,
+    right: &Value,
+    comparing: &mut Vec<(*const (), *const ())>,
+) -> bool {
+    match (left, right) {
+        (Value::List(left_list), Value::List(right_list)) => compare_once(
+            Rc::as_ptr(left_list),
+            Rc::as_ptr(right_list),
+            comparing,
+            |comparing| {
+                let (left_list, right_list) = (left_list.borrow(), right_list.borrow());
+                left_list.len() == right_list.len()
+                    && left_list
+                        .iter()
+                        .zip(right_list.iter())
+                        .all(|(left, right)| collections_equal(left, right, comparing))
+            },
+        ),
+        (Value::Map(left_map), Value::Map(right_map)) => compare_once(
+            Rc::as_ptr(left_map),
+            Rc::as_ptr(right_map),
+            comparing,
+            |comparing| {
+                let (left_map, right_map) = (left_map.borrow(), right_map.borrow());
+                left_map.len() == right_map.len()
+                    && left_map.iter().zip(right_map.iter()).all(
+                        |((left_key, left), (right_key, right))| {
+                            left_key == right_key && collections_equal(left, right, comparing)
+                        },
+                    )
+            },
+        ),
+        _ => left == right,
+    }
+}
+
+///Runs `compare` unless the two collections are the same one or already being compared
+fn compare_once<T>(
+    left: *const T,
+    right: *const T,
+    comparing: &mut Vec<(*const (), *const ())>,
+    compare: impl FnOnce(&mut Vec<(*const (), *const ())>) -> bool,
+) -> bool {
+    let pair = (left as *const (), right as *const ());
+    if pair.0 == pair.1 || comparing.contains(&pair) {
+        return true;
+    }
+    comparing.push(pair);
+    let equal = compare(comparing);
+    comparing.pop();
+    equal
+}
+
+///Matches how the tree-walker displays the equivalent `LiteralType`
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Nil => write!(f, "NIL"),
+            Value::Boolean(val) => write!(f, "{val}"),
+            Value::Number(num) => write!(f, "{num}"),
+            Value::String(string) => write!(f, "{string}"),
+            Value::Function(function) => write!(f, "<fn {}>", function.name),
+            Value::Closure(closure) => write!(f, "<fn {}>", closure.function.name),
+            Value::Native(Native::Clock) => write!(f, "<fn Clock>"),
+            Value::Native(Native::Print) => write!(f, "<fn Print>"),
+            Value::Native(Native::Println) => write!(f, "<fn Println>"),
+            Value::Native(Native::Len) => write!(f, "<fn Len>"),
+            Value::Native(Native::Push) => write!(f, "<fn Push>"),
+            Value::Native(Native::Pop) => write!(f, "<fn Pop>"),
+            Value::Native(Native::Has) => write!(f, "<fn Has>"),
+            Value::Native(Native::Remove) => write!(f, "<fn Remove>"),
+            Value::Native(Native::Keys) => write!(f, "<fn Keys>"),
+            Value::Class(class) => write!(f, "<class {}>", class.borrow().name),
+            Value::Instance(instance) => {
+                write!(f, "<{} instance>", instance.borrow().class.borrow().name)
+            }
+            Value::BoundMethod(bound) => write!(f, "<fn {}>", bound.method.function.name),
+            Value::List(_) | Value::Map(_) => write_collection(f, self, &mut Vec::new()),
+            Value::Iterator(_) => write!(f, "<iterator>"),
+            Value::Fault(fault) => write!(f, "<error {}>", fault.message),
+        }
+    }
+}
+
+///Writes a list or map, printing `[...]` or `{...}` for one `seen` says is already being written
+fn write_collection(
+    f: &mut Formatter<'_>,
+    value: &Value,
+    seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+    match value {
+        Value::List(list) => {
+            let pointer = Rc::as_ptr(list) as *const ();
+            if seen.contains(&pointer) {
+                return write!(f, "[...]");
+            }
+            seen.push(pointer);
+            write!(f, "[")?;
+            for (position, element) in list.borrow().iter().enumerate() {
+                if position > 0 {
+                    write!(f, ", ")?;
+                }
+                write_nested(f, element, seen)?;
+            }
+            seen.pop();
+            write!(f, "]")
+        }
+        Value::Map(map) => {
+            let pointer = Rc::as_ptr(map) as *const ();
+            if seen.contains(&pointer) {
+                return write!(f, "{{...}}");
+            }
+            seen.push(pointer);
+            write!(f, "{{")?;
+            for (position, (key, value)) in map.borrow().iter().enumerate() {
+                if position > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{key}: ")?;
+                write_nested(f, value, seen)?;
+            }
+            seen.pop();
+            write!(f, "}}")
+        }
+        _ => write!(f, "{value}"),
+    }
+}
+
+///Writes a value held by a list or map, with strings quoted
+fn write_nested(
+    f: &mut Formatter<'_>,
+    value: &Value,
+    seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+    match value {
+        Value::String(string) => write!(f, "\"{string}\""),
+        _ => write_collection(f, value, seen),
+    }
+}
+
+impl std::fmt::Debug for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
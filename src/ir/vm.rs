@@ -0,0 +1,980 @@
+use super::{
+    OpCode,
+    compiler::Compiler,
+    value::{
+        BoundMethod,
+        Class,
+        Closure,
+        Instance,
+        Iteration,
+        Native,
+        Upvalue,
+        Value,
+    },
+};
+use crate::{
+    Backend,
+    ExitCode,
+    diagnostics::{
+        Diagnostic,
+        codes::{
+            self,
+            ErrorCode,
+            Failure,
+        },
+    },
+    types::{
+        lux_functions::{
+            list,
+            map::{
+                self,
+                MapKey,
+            },
+        },
+        span::Span,
+        statement::Statement,
+    },
+};
+use std::{
+    cell::RefCell,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    rc::Rc,
+};
+
+///Deepest call stack allowed before the VM reports a stack overflow, the tree-walker shares it
+pub(crate) const FRAMES_MAX: usize = 4096;
+
+///Locked once for the VM's whole life, so printing doesn't pay for the lock on every call
+#[cfg(not(test))]
+type Stdout = io::StdoutLock<'static>;
+#[cfg(test)]
+type Stdout = Captured;
+
+///Writes through `print!`, which the test harness captures unlike a locked stdout
+#[cfg(test)]
+struct Captured;
+
+#[cfg(test)]
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        print!("{}", String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+///One active function call, `base` is the stack index of the callee, its locals follow it
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+}
+
+///An active `try`, a fault inside it unwinds the VM back to how things stood when it started
+struct Handler {
+    frames: usize,
+    stack: usize,
+    ///Where the handling code starts in the chunk of the frame that ran the `try`
+    target: usize,
+}
+
+///Why the VM stopped, where is read off the failing instruction afterwards
+#[derive(Clone)]
+pub(crate) struct Fault {
+    pub(crate) message: String,
+    pub(crate) code: &'static ErrorCode,
+    pub(crate) notes: Vec<String>,
+    ///What a `throw` threw, `None` for errors raised by the VM itself
+    thrown: Option<Value>,
+    ///Filled in once a handler catches the fault, so raising it again still points at where it
+    ///started
+    span: Option<Span>,
+}
+
+impl From<Failure> for Fault {
+    fn from(failure: Failure) -> Self {
+        Fault {
+            message: failure.message,
+            code: failure.code,
+            notes: vec![],
+            thrown: None,
+            span: None,
+        }
+    }
+}
+
+impl Fault {
+    fn with_note(mut self, note: impl Into<String>) -> Fault {
+        self.notes.push(note.into());
+        self
+    }
+
+    ///Raised by `throw`, it only reaches the user when no `catch` handles it
+    fn thrown(value: Value) -> Fault {
+        Fault {
+            thrown: Some(value.clone()),
+            ..Fault::from(Failure::new(
+                &codes::UNCAUGHT_EXCEPTION,
+                format!("Uncaught exception: {value}"),
+            ))
+        }
+    }
+
+    ///What a `catch` clause receives, matches `RuntimeError::caught` in the tree-walker
+    fn caught(&self) -> Value {
+        if let Some(ref value) = self.thrown {
+            return value.clone();
+        }
+
+        let line = self.span.map_or(0, |span| span.line);
+        let error = BTreeMap::from([
+            (
+                MapKey::String(String::from("message")),
+                Value::String(Rc::from(self.message.as_str())),
+            ),
+            (
+                MapKey::String(String::from("line")),
+                Value::Number(line as f64),
+            ),
+        ]);
+        Value::Map(Rc::new(RefCell::new(error)))
+    }
+}
+
+impl From<&'static ErrorCode> for Fault {
+    fn from(code: &'static ErrorCode) -> Self {
+        Fault::from(Failure::from(code))
+    }
+}
+
+///Stack based virtual machine executing the compiler's bytecode
+pub(crate) struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    ///Globals are addressed by slot, `None` until the slot's variable is defined
+    globals: Vec<Option<Value>>,
+    ///Name to slot table handed to the compiler, kept here so REPL lines share slots
+    global_slots: HashMap<String, u32>,
+    ///Captured variables still living on the stack, ordered by stack index
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    ///Active `try` statements across every frame, innermost last
+    handlers: Vec<Handler>,
+    ///What `print`, `println` and echoed values write to, flushed whenever a run ends
+    out: BufWriter<Stdout>,
+}
+
+impl Vm {
+    pub(crate) fn new() -> Vm {
+        let mut vm = Vm {
+            stack: Vec::with_capacity(256),
+            frames: Vec::with_capacity(64),
+            globals: vec![],
+            global_slots: HashMap::new(),
+            open_upvalues: vec![],
+            handlers: vec![],
+            #[cfg(not(test))]
+            out: BufWriter::new(io::stdout().lock()),
+            #[cfg(test)]
+            out: BufWriter::new(Captured),
+        };
+
+        //Inject built-ins (native functions) into the globals
+        vm.define_global("clock", Value::Native(Native::Clock));
+        vm.define_global("print", Value::Native(Native::Print));
+        vm.define_global("println", Value::Native(Native::Println));
+        vm.define_global("len", Value::Native(Native::Len));
+        vm.define_global("push", Value::Native(Native::Push));
+        vm.define_global("pop", Value::Native(Native::Pop));
+        vm.define_global("has", Value::Native(Native::Has));
+        vm.define_global("remove", Value::Native(Native::Remove));
+        vm.define_global("keys", Value::Native(Native::Keys));
+
+        vm
+    }
+
+    fn define_global(&mut self, name: &str, value: Value) {
+        let slot = self.global_slots.len();
+        self.global_slots.insert(name.to_string(), slot as u32);
+        self.globals.push(Some(value));
+    }
+
+    ///Looks a global up by name, used to inspect the VM after a run
+    #[cfg(test)]
+    pub(crate) fn global(&self, name: &str) -> Option<Value> {
+        let slot = *self.global_slots.get(name)?;
+        self.globals.get(slot as usize).cloned().flatten()
+    }
+
+    ///Compiles and runs a resolved program, returning the exit code
+    pub(crate) fn interpret(&mut self, statements: &mut [Statement], echo: bool) -> i32 {
+        let result = self.execute(statements, echo);
+        //Before any diagnostic, so the output it interrupted comes first
+        let _ = self.out.flush();
+
+        match result {
+            Ok(()) => ExitCode::Okay as i32,
+            Err(fault) => {
+                self.diagnostic(fault).emit();
+
+                //Unwind everything so the VM is usable again, like after a REPL line
+                self.stack.clear();
+                self.frames.clear();
+                self.open_upvalues.clear();
+                self.handlers.clear();
+                ExitCode::RuntimeErr as i32
+            }
+        }
+    }
+
+    ///Describes `fault` at the instruction that raised it, call before the VM is unwound
+    pub(crate) fn diagnostic(&self, fault: Fault) -> Diagnostic {
+        let span = fault.span.unwrap_or_else(|| self.fault_span());
+        let mut diagnostic = Diagnostic::error(fault.message, span).with_code(Some(fault.code));
+        for note in fault.notes {
+            diagnostic = diagnostic.with_note(note);
+        }
+        diagnostic
+    }
+
+    ///Compiles and runs a resolved program, handing back the fault that stopped it
+    pub(crate) fn execute(
+        &mut self,
+        statements: &mut [Statement],
+        echo: bool,
+    ) -> Result<(), Fault> {
+        let function = Compiler::new(&mut self.global_slots, echo).compile(statements);
+        self.globals.resize(self.global_slots.len(), None);
+
+        let closure = Rc::new(Closure {
+            function: Rc::new(function),
+            upvalues: vec![],
+        });
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: 0,
+        });
+
+        self.run()
+    }
+
+    ///Where the instruction that just failed came from
+    fn fault_span(&self) -> Span {
+        self.frames
+            .last()
+            .map(|frame| frame.closure.function.chunk.spans[frame.ip - 1])
+            .unwrap_or_default()
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("VM stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn global_name(&self, slot: u32) -> &str {
+        self.global_slots
+            .iter()
+            .find_map(|(name, index)| (*index == slot).then_some(name.as_str()))
+            .unwrap_or("<unknown>")
+    }
+
+    fn frame(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("VM has no call frame")
+    }
+
+    fn constant_name(closure: &Closure, index: u32) -> Rc<str> {
+        match closure.function.chunk.constants[index as usize] {
+            Value::String(ref name) => Rc::clone(name),
+            _ => unreachable!("Compiler only emits names as string constants"),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), Fault> {
+        //! Hands faults to the innermost `try`, only stopping when nothing is left to catch them
+        loop {
+            let Err(mut fault) = self.dispatch() else {
+                return Ok(());
+            };
+            let Some(handler) = self.handlers.pop() else {
+                return Err(fault);
+            };
+
+            fault.span.get_or_insert_with(|| self.fault_span());
+            self.frames.truncate(handler.frames);
+            self.close_upvalues(handler.stack);
+            self.stack.truncate(handler.stack);
+            self.stack.push(Value::Fault(Rc::new(fault)));
+            self.frame().ip = handler.target;
+        }
+    }
+
+    fn dispatch(&mut self) -> Result<(), Fault> {
+        //The executing frame is cached in locals and only written back when control leaves it
+        let frame = self.frames.last().expect("VM has no call frame");
+        let mut closure = Rc::clone(&frame.closure);
+        let mut ip = frame.ip;
+        let mut base = frame.base;
+
+        ///Bails out of the dispatch loop with a runtime error
+        macro_rules! fail {
+            ($message:expr) => {{
+                self.frame().ip = ip;
+                return Err(Fault::from($message));
+            }};
+        }
+
+        ///Unwraps the result of a helper, bailing out on a runtime error
+        macro_rules! attempt {
+            ($result:expr) => {
+                if let Err(message) = $result {
+                    fail!(message);
+                }
+            };
+        }
+
+        ///Applies a numeric operator to the top two values, replacing them with the result
+        macro_rules! number_op {
+            ($wrap:path, $op:tt) => {{
+                let len = self.stack.len();
+                let result = match (&self.stack[len - 2], &self.stack[len - 1]) {
+                    (Value::Number(left), Value::Number(right)) => $wrap(*left $op *right),
+                    _ => fail!(&codes::OPERANDS_MUST_BE_NUMBERS),
+                };
+                self.stack.pop();
+                self.stack[len - 2] = result;
+            }};
+        }
+
+        ///Saves the current frame's position and switches to whatever frame is now on top
+        macro_rules! switch_frame {
+            () => {{
+                let frame = self.frames.last().expect("VM has no call frame");
+                closure = Rc::clone(&frame.closure);
+                ip = frame.ip;
+                base = frame.base;
+            }};
+        }
+
+        loop {
+            let op = closure.function.chunk.code[ip];
+            ip += 1;
+
+            match op {
+                OpCode::Constant(index) => {
+                    let value = closure.function.chunk.constants[index as usize].clone();
+                    self.stack.push(value);
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(Value::Boolean(true)),
+                OpCode::False => self.stack.push(Value::Boolean(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::Dup(distance) => {
+                    self.stack.push(self.peek(distance as usize).clone());
+                }
+                OpCode::Store(distance) => {
+                    let len = self.stack.len();
+                    self.stack[len - 1 - distance as usize] = self.peek(0).clone();
+                }
+
+                OpCode::GetLocal(slot) => {
+                    self.stack.push(self.stack[base + slot as usize].clone());
+                }
+                OpCode::SetLocal(slot) => {
+                    self.stack[base + slot as usize] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal(slot) => match self.globals[slot as usize] {
+                    Some(ref value) => self.stack.push(value.clone()),
+                    None => fail!(Failure::new(
+                        &codes::UNDEFINED_VARIABLE,
+                        format!("Variable not found: {}", self.global_name(slot))
+                    )),
+                },
+                OpCode::DefineGlobal(slot) => {
+                    self.globals[slot as usize] = Some(self.pop());
+                }
+                OpCode::SetGlobal(slot) => {
+                    let value = self.peek(0).clone();
+                    match self.globals[slot as usize] {
+                        Some(ref mut global) => *global = value,
+                        None => fail!(Failure::new(
+                            &codes::UNDEFINED_VARIABLE,
+                            format!("Undefined variable '{}'.", self.global_name(slot))
+                        )),
+                    }
+                }
+                OpCode::GetUpvalue(index) => {
+                    let value = match *closure.upvalues[index as usize].borrow() {
+                        Upvalue::Open(slot) => self.stack[slot].clone(),
+                        Upvalue::Closed(ref value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::SetUpvalue(index) => {
+                    let value = self.peek(0).clone();
+                    match *closure.upvalues[index as usize].borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[slot] = value,
+                        Upvalue::Closed(ref mut closed) => *closed = value,
+                    }
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+
+                OpCode::GetProperty(name) => {
+                    let name = Self::constant_name(&closure, name);
+                    let Value::Instance(instance) = self.peek(0).clone() else {
+                        fail!(&codes::NOT_AN_INSTANCE);
+                    };
+
+                    if let Some(value) = instance.borrow().fields.get(&*name) {
+                        self.pop();
+                        self.stack.push(value.clone());
+                        continue;
+                    }
+
+                    let class = Rc::clone(&instance.borrow().class);
+                    attempt!(self.bind_method(&class, &name));
+                }
+                OpCode::SetProperty(name) => {
+                    let name = Self::constant_name(&closure, name);
+                    let Value::Instance(instance) = self.peek(1).clone() else {
+                        fail!(Failure::new(
+                            &codes::NOT_AN_INSTANCE,
+                            "Only instances have fields."
+                        ));
+                    };
+
+                    let value = self.pop();
+                    instance
+                        .borrow_mut()
+                        .fields
+                        .insert(name.to_string(), value.clone());
+                    self.pop();
+                    self.stack.push(value);
+                }
+                OpCode::GetSuper(name) => {
+                    let name = Self::constant_name(&closure, name);
+                    let Value::Class(superclass) = self.pop() else {
+                        unreachable!("Resolver only allows super inside subclasses");
+                    };
+                    attempt!(self.bind_method(&superclass, &name));
+                }
+
+                OpCode::Equal => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(Value::Boolean(left == right));
+                }
+                OpCode::NotEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(Value::Boolean(left != right));
+                }
+                OpCode::Greater => number_op!(Value::Boolean, >),
+                OpCode::GreaterEqual => number_op!(Value::Boolean, >=),
+                OpCode::Less => number_op!(Value::Boolean, <),
+                OpCode::LessEqual => number_op!(Value::Boolean, <=),
+                OpCode::Add => {
+                    //Numbers are added in place, the common case shouldn't pay for moving values
+                    let len = self.stack.len();
+                    if let (Value::Number(left), Value::Number(right)) =
+                        (&self.stack[len - 2], &self.stack[len - 1])
+                    {
+                        self.stack[len - 2] = Value::Number(left + right);
+                        self.stack.pop();
+                        continue;
+                    }
+
+                    let right = self.pop();
+                    let left = self.pop();
+                    let result = match (left, right) {
+                        (Value::Number(left), Value::Number(right)) => Value::Number(left + right),
+                        //Strings swallow anything printable on their right, like the tree-walker
+                        (
+                            Value::String(left),
+                            right @ (Value::String(_)
+                            | Value::Number(_)
+                            | Value::Boolean(_)
+                            | Value::Nil),
+                        ) => Value::String(Rc::from(format!("{left}{right}"))),
+                        (Value::String(_), _) => fail!(&codes::INVALID_CONCATENATION),
+                        _ => fail!(&codes::INVALID_ADDITION),
+                    };
+                    self.stack.push(result);
+                }
+                OpCode::Subtract => number_op!(Value::Number, -),
+                OpCode::Multiply => number_op!(Value::Number, *),
+                OpCode::Divide => number_op!(Value::Number, /),
+                OpCode::Modulo => number_op!(Value::Number, %),
+                OpCode::Power => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    match (left, right) {
+                        (Value::Number(base), Value::Number(exponent)) => {
+                            self.stack.push(Value::Number(base.powf(exponent)))
+                        }
+                        _ => fail!(&codes::OPERANDS_MUST_BE_NUMBERS),
+                    }
+                }
+                OpCode::Not => match self.pop() {
+                    Value::Boolean(boolean) => self.stack.push(Value::Boolean(!boolean)),
+                    _ => fail!(Failure::new(
+                        &codes::BOOLEAN_REQUIRED,
+                        "Operand must be a boolean."
+                    )),
+                },
+                OpCode::Negate => match self.pop() {
+                    Value::Number(num) => self.stack.push(Value::Number(-num)),
+                    _ => fail!(&codes::OPERAND_MUST_BE_NUMBER),
+                },
+                OpCode::CheckStep => {
+                    if !matches!(self.peek(0), Value::Number(_)) {
+                        fail!(&codes::INVALID_STEP);
+                    }
+                }
+                OpCode::CheckLogical => {
+                    if !matches!(self.peek(0), Value::Boolean(_)) {
+                        fail!(
+                            Fault::from(Failure::new(
+                                &codes::BOOLEAN_REQUIRED,
+                                "Operands must be booleans."
+                            ))
+                            .with_note(
+                                "rlux has no truthiness, `and` and `or` only work on booleans"
+                            )
+                        );
+                    }
+                }
+                OpCode::BuildList(count) => {
+                    let elements = self.stack.split_off(self.stack.len() - count as usize);
+                    self.stack
+                        .push(Value::List(Rc::new(RefCell::new(elements))));
+                }
+                OpCode::BuildMap(count) => {
+                    let entries = self.stack.split_off(self.stack.len() - 2 * count as usize);
+                    let mut map = BTreeMap::new();
+                    for entry in entries.chunks(2) {
+                        let key = match MapKey::try_from(&entry[0]) {
+                            Ok(key) => key,
+                            Err(message) => fail!(message),
+                        };
+                        map.insert(key, entry[1].clone());
+                    }
+                    self.stack.push(Value::Map(Rc::new(RefCell::new(map))));
+                }
+                OpCode::GetIndex => {
+                    let value = match Self::get_index(self.peek(1), self.peek(0)) {
+                        Ok(value) => value,
+                        Err(message) => fail!(message),
+                    };
+                    self.stack.truncate(self.stack.len() - 2);
+                    self.stack.push(value);
+                }
+                OpCode::SetIndex => {
+                    let value = self.peek(0).clone();
+                    attempt!(Self::set_index(self.peek(2), self.peek(1), value.clone()));
+                    self.stack.truncate(self.stack.len() - 3);
+                    self.stack.push(value);
+                }
+                OpCode::Slice => {
+                    let Value::List(elements) = self.peek(2).clone() else {
+                        fail!(Failure::new(&codes::NOT_INDEXABLE, "Can only slice lists."));
+                    };
+                    let mut bounds = [None, None];
+                    for (bound, value) in bounds
+                        .iter_mut()
+                        .zip([self.peek(1).clone(), self.peek(0).clone()])
+                    {
+                        match value {
+                            Value::Number(number) => *bound = Some(number),
+                            Value::Nil => {}
+                            _ => fail!(Failure::new(
+                                &codes::INVALID_INDEX,
+                                "Slice bounds must be numbers."
+                            )),
+                        }
+                    }
+
+                    let length = elements.borrow().len();
+                    let range = match list::slice_bounds(bounds[0], bounds[1], length) {
+                        Ok(range) => range,
+                        Err(message) => fail!(message),
+                    };
+                    let copy = elements.borrow()[range].to_vec();
+                    self.stack.truncate(self.stack.len() - 3);
+                    self.stack.push(Value::List(Rc::new(RefCell::new(copy))));
+                }
+
+                OpCode::Interpolate(count) => {
+                    let parts = self.stack.split_off(self.stack.len() - count as usize);
+                    let joined: String = parts.iter().map(ToString::to_string).collect();
+                    self.stack.push(Value::String(Rc::from(joined)));
+                }
+
+                OpCode::Jump(distance) => ip += distance as usize,
+                OpCode::JumpIfFalse(distance) => match self.peek(0) {
+                    Value::Boolean(false) => ip += distance as usize,
+                    Value::Boolean(true) => {}
+                    _ => fail!(
+                        Fault::from(Failure::new(
+                            &codes::BOOLEAN_REQUIRED,
+                            "Condition must be a boolean."
+                        ))
+                        .with_note("rlux has no truthiness, conditions must be `true` or `false`")
+                    ),
+                },
+                OpCode::Loop(distance) => ip -= distance as usize,
+                OpCode::JumpIfNil(distance) => {
+                    if let Value::Nil = self.peek(0) {
+                        ip += distance as usize;
+                    }
+                }
+
+                OpCode::Try(distance) => self.handlers.push(Handler {
+                    frames: self.frames.len(),
+                    stack: self.stack.len(),
+                    target: ip + distance as usize,
+                }),
+                OpCode::EndTry => {
+                    self.handlers.pop();
+                }
+                OpCode::Throw => match self.pop() {
+                    Value::Fault(fault) => fail!(Rc::unwrap_or_clone(fault)),
+                    value => fail!(Fault::thrown(value)),
+                },
+                OpCode::Caught => {
+                    let Value::Fault(fault) = self.pop() else {
+                        unreachable!("Handlers always leave the fault on top of the stack");
+                    };
+                    self.stack.push(fault.caught());
+                }
+
+                OpCode::Iterator => {
+                    let iteration = match self.peek(0).clone() {
+                        Value::List(list) => Iteration::List(list, 0),
+                        Value::Map(map) => {
+                            let keys: Vec<Value> = map.borrow().keys().map(Value::from).collect();
+                            Iteration::Values(keys.into_iter())
+                        }
+                        Value::String(string) => {
+                            let characters: Vec<Value> = string
+                                .chars()
+                                .map(|character| Value::String(Rc::from(character.to_string())))
+                                .collect();
+                            Iteration::Values(characters.into_iter())
+                        }
+                        //User iterators stay as they are and get called by `IterNext`
+                        Value::Closure(_) | Value::Native(_) | Value::BoundMethod(_) => continue,
+                        Value::Instance(instance) => {
+                            if let Some(next) = instance.borrow().fields.get("next") {
+                                self.pop();
+                                self.stack.push(next.clone());
+                                continue;
+                            }
+                            let class = Rc::clone(&instance.borrow().class);
+                            if self.bind_method(&class, "next").is_err() {
+                                fail!(&codes::NOT_ITERABLE);
+                            }
+                            continue;
+                        }
+                        _ => fail!(&codes::NOT_ITERABLE),
+                    };
+                    self.pop();
+                    self.stack
+                        .push(Value::Iterator(Rc::new(RefCell::new(iteration))));
+                }
+                OpCode::Range(inclusive) => {
+                    let (Value::Number(start), Value::Number(end)) = (self.peek(1), self.peek(0))
+                    else {
+                        fail!(&codes::INVALID_RANGE);
+                    };
+                    let iteration = Iteration::Range {
+                        next: *start,
+                        end: *end,
+                        inclusive,
+                    };
+                    self.stack.truncate(self.stack.len() - 2);
+                    self.stack
+                        .push(Value::Iterator(Rc::new(RefCell::new(iteration))));
+                }
+                OpCode::IterNext(distance) => match self.peek(0).clone() {
+                    Value::Iterator(iteration) => {
+                        let next = iteration.borrow_mut().next();
+                        match next {
+                            Some(value) => {
+                                self.stack.push(value);
+                                ip += 1;
+                            }
+                            None => {
+                                self.stack.push(Value::Nil);
+                                ip += distance as usize;
+                            }
+                        }
+                    }
+                    callee => {
+                        self.frame().ip = ip;
+                        self.stack.push(callee.clone());
+                        attempt!(self.call_value(callee, 0));
+                        switch_frame!();
+                    }
+                },
+
+                OpCode::Call(argument_count) => {
+                    self.frame().ip = ip;
+                    let callee = self.peek(argument_count as usize).clone();
+                    attempt!(self.call_value(callee, argument_count as usize));
+                    switch_frame!();
+                }
+                OpCode::Closure(index) => {
+                    let Value::Function(function) =
+                        closure.function.chunk.constants[index as usize].clone()
+                    else {
+                        unreachable!("Compiler only emits closures over function constants");
+                    };
+
+                    let upvalues = function
+                        .upvalues
+                        .iter()
+                        .map(|upvalue| {
+                            if upvalue.is_local {
+                                self.capture_upvalue(base + upvalue.index as usize)
+                            } else {
+                                Rc::clone(&closure.upvalues[upvalue.index as usize])
+                            }
+                        })
+                        .collect();
+
+                    self.stack
+                        .push(Value::Closure(Rc::new(Closure { function, upvalues })));
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.frames.pop();
+                    self.close_upvalues(base);
+                    self.stack.truncate(base);
+
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+                    self.stack.push(result);
+                    switch_frame!();
+                }
+                OpCode::Class(name) => {
+                    let name = Self::constant_name(&closure, name);
+                    self.stack.push(Value::Class(Rc::new(RefCell::new(Class {
+                        name: name.to_string(),
+                        methods: HashMap::new(),
+                    }))));
+                }
+                OpCode::Inherit => {
+                    let Value::Class(superclass) = self.peek(1).clone() else {
+                        fail!(&codes::INVALID_SUPERCLASS);
+                    };
+                    let Value::Class(subclass) = self.pop() else {
+                        unreachable!("Compiler loads the subclass right before inheriting");
+                    };
+
+                    //Copy down now, methods declared afterwards override these
+                    subclass.borrow_mut().methods = superclass.borrow().methods.clone();
+                }
+                OpCode::Method(name) => {
+                    let name = Self::constant_name(&closure, name);
+                    let Value::Closure(method) = self.pop() else {
+                        unreachable!("Compiler only emits methods after their closure");
+                    };
+                    let Value::Class(class) = self.peek(0) else {
+                        unreachable!("Compiler keeps the class under its methods");
+                    };
+                    class.borrow_mut().methods.insert(name.to_string(), method);
+                }
+
+                OpCode::Echo => {
+                    let value = self.pop();
+                    if value != Value::Nil {
+                        writeln!(self.out, "{value}").expect("Failed writing to stdout");
+                    }
+                }
+            }
+        }
+    }
+
+    ///Reads `object[index]` from a list or map
+    fn get_index(object: &Value, index: &Value) -> Result<Value, Failure> {
+        match object {
+            Value::List(elements) => {
+                let position = Self::position(index, elements.borrow().len())?;
+                Ok(elements.borrow()[position].clone())
+            }
+            Value::Map(map) => {
+                let key = MapKey::try_from(index)?;
+                let value = map.borrow().get(&key).cloned();
+                value.ok_or_else(|| map::missing(&key))
+            }
+            _ => Err(Failure::from(&codes::NOT_INDEXABLE)),
+        }
+    }
+
+    ///Writes `object[index] = value`, assigning to a missing map key adds it
+    fn set_index(object: &Value, index: &Value, value: Value) -> Result<(), Failure> {
+        match object {
+            Value::List(elements) => {
+                let position = Self::position(index, elements.borrow().len())?;
+                elements.borrow_mut()[position] = value;
+            }
+            Value::Map(map) => {
+                map.borrow_mut().insert(MapKey::try_from(index)?, value);
+            }
+            _ => return Err(Failure::from(&codes::NOT_INDEXABLE)),
+        }
+        Ok(())
+    }
+
+    fn position(index: &Value, length: usize) -> Result<usize, Failure> {
+        let Value::Number(index) = index else {
+            return Err(Failure::new(
+                &codes::INVALID_INDEX,
+                "List index must be a number.",
+            ));
+        };
+        list::position(*index, length)
+    }
+
+    ///Replaces the instance on top of the stack with its method `name` bound to it
+    fn bind_method(&mut self, class: &Rc<RefCell<Class>>, name: &str) -> Result<(), Failure> {
+        let Some(method) = class.borrow().methods.get(name).cloned() else {
+            return Err(Failure::new(
+                &codes::UNDEFINED_PROPERTY,
+                format!("Undefined property '{name}'."),
+            ));
+        };
+
+        let receiver = self.pop();
+        self.stack.push(Value::BoundMethod(Rc::new(BoundMethod {
+            receiver,
+            method,
+        })));
+        Ok(())
+    }
+
+    fn call_value(&mut self, callee: Value, argument_count: usize) -> Result<(), Failure> {
+        match callee {
+            Value::Closure(closure) => self.call(closure, argument_count),
+            Value::BoundMethod(bound) => {
+                let receiver_slot = self.stack.len() - argument_count - 1;
+                self.stack[receiver_slot] = bound.receiver.clone();
+                self.call(Rc::clone(&bound.method), argument_count)
+            }
+            Value::Class(class) => {
+                let instance_slot = self.stack.len() - argument_count - 1;
+                self.stack[instance_slot] = Value::Instance(Rc::new(RefCell::new(Instance {
+                    class: Rc::clone(&class),
+                    fields: HashMap::new(),
+                })));
+
+                let initializer = class.borrow().methods.get("init").cloned();
+                match initializer {
+                    Some(initializer) => self.call(initializer, argument_count),
+                    None if argument_count != 0 => Err(Failure::new(
+                        &codes::WRONG_ARGUMENT_COUNT,
+                        format!("Expected 0 arguments but got {argument_count}."),
+                    )),
+                    None => Ok(()),
+                }
+            }
+            Value::Native(native) => {
+                if native.arity() != argument_count {
+                    return Err(Failure::new(
+                        &codes::WRONG_ARGUMENT_COUNT,
+                        format!(
+                            "Expected {} arguments but got {argument_count}.",
+                            native.arity()
+                        ),
+                    ));
+                }
+
+                let arguments_start = self.stack.len() - argument_count;
+                let result = native.call(&self.stack[arguments_start..], &mut self.out)?;
+                self.stack.truncate(arguments_start - 1);
+                self.stack.push(result);
+                Ok(())
+            }
+            _ => Err(Failure::from(&codes::NOT_CALLABLE)),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, argument_count: usize) -> Result<(), Failure> {
+        if closure.function.arity != argument_count {
+            return Err(Failure::new(
+                &codes::WRONG_ARGUMENT_COUNT,
+                format!(
+                    "Expected {} arguments but got {argument_count}.",
+                    closure.function.arity
+                ),
+            ));
+        }
+
+        if self.frames.len() == FRAMES_MAX {
+            return Err(Failure::from(&codes::STACK_OVERFLOW));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: self.stack.len() - argument_count - 1,
+        });
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        if let Some(existing) = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot))
+        {
+            return Rc::clone(existing);
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(Rc::clone(&upvalue));
+        upvalue
+    }
+
+    ///Moves every captured variable at or above `from` off the stack and into its upvalue
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let mut upvalue = upvalue.borrow_mut();
+            match *upvalue {
+                Upvalue::Open(slot) if slot >= from => {
+                    *upvalue = Upvalue::Closed(stack[slot].clone());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+}
+
+impl Backend for Vm {
+    fn execute_program(&mut self, statements: &mut [Statement], echo: bool) -> i32 {
+        self.interpret(statements, echo)
+    }
+}
@@ -9,45 +9,38 @@ macro_rules! debug {
 
 ///Removes some of Syntactical ugliness in the Scanner
 macro_rules! new_character {
-    ($token_type:expr, $string:expr, $line:expr) => {
-        Some(Token::new($token_type, &$string, None, $line))
+    ($token_type:expr, $string:expr, $span:expr) => {
+        Some(Token::new($token_type, &$string, None, $span))
     };
 }
 
 ///Removes Syntactical Ugliness in the Parser, and in the scanner
 macro_rules! new_literal {
     //Scanner
-    ($token_type:expr, $string:expr, $literal_type:expr, $line:expr) => {
+    ($token_type:expr, $string:expr, $literal_type:expr, $span:expr) => {
         Some(Token::new(
             $token_type,
             &$string,
             Some($literal_type),
-            $line,
+            $span,
         ))
     };
     //Expressions
-    ($value:expr) => {
-        Expression::Literal(Box::new(Literal { value: $value }))
-    };
-}
-
-///Let's me push errors into corrections for the user at runtime, good examples are syntax, etc
-macro_rules! error_check {
-    ($variable:ident ) => {
-        if let Err(ref error) = $variable {
-            crate::error(error.source.line, error.cause.clone());
-            // return Err(error.clone());
-        }
+    ($value:expr, $span:expr) => {
+        Expression::Literal(Box::new(Literal {
+            value: $value,
+            span: $span,
+        }))
     };
 }
 
 ///Internal shorthand to generate Visitor expressions for generating statements
 macro_rules! visitable_trait_two_elements {
-    ($trait_type1:ty,  $enum_variant:ty, $enum_parent:ty) => {
-        impl Visitable<$trait_type1, $enum_parent> for $enum_variant {
+    ($enum_variant:ty) => {
+        impl<T, V: StatementVisitor<T>> Visitable<T, V> for $enum_variant {
             paste::paste! {
                 #[doc = "Redirect Visitors to `" $enum_variant "` version."]
-                fn accept(&mut self, visitor: &mut $enum_parent) -> $trait_type1 {
+                fn accept(&mut self, visitor: &mut V) -> T {
                     paste::item! {visitor.[<visit_ $enum_variant:snake:lower>](self)}
                 }
             }
@@ -60,23 +53,16 @@ macro_rules! boolean_op {
     ($self:ident, $other:ident, $op:tt) => {
         match $self {
             Self::Number(left_num) => match $other {
-                Self::Number(right_num) => *left_num $op *right_num,
-                _ =>  {
-                    eprintln!("Error: Type Mismatch! \n\tReturned false from a Number while trying to perform: {}" , stringify!($op));
-                    false
-                },
+                Self::Number(right_num) => Ok(LiteralType::Boolean(*left_num $op *right_num)),
+                _ => Err(&codes::OPERANDS_MUST_BE_NUMBERS),
             },
-            _ =>  {
-                    eprintln!("Error: Type Mismatch! \n\tReturned false while trying to perform: {}, between: {:?} and {:?}", stringify!($op), $self, $other);
-                    false
-                },
+            _ => Err(&codes::OPERANDS_MUST_BE_NUMBERS),
         }
     };
 }
 
 pub(crate) use boolean_op;
 pub(crate) use debug;
-pub(crate) use error_check;
 pub(crate) use new_character;
 pub(crate) use new_literal;
 pub(crate) use visitable_trait_two_elements;
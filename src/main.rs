@@ -1,15 +1,24 @@
 use std::{
     fs,
-    io::{Error, stdin},
+    io::{
+        Error,
+        Write,
+        stdin,
+        stdout,
+    },
     process::exit,
+    thread,
 };
 
 //Execution Path Modules
 mod interpreter;
+mod ir;
 mod parser;
+mod resolver;
 mod scanner;
 
 //Misc Utility Modules
+mod diagnostics;
 mod enviroment;
 mod types;
 
@@ -24,83 +33,264 @@ enum ExitCode {
     GenerallyBad = 1,
     CommandLineErr = 64,
     DataErr = 65,
+    RuntimeErr = 70,
     OSErr = 72,
 }
 
+///The tree-walker recurses natively for every lux call, this leaves room for `FRAMES_MAX` of them
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
 fn main() {
+    let cli = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(cli)
+        .expect("Failed to start the interpreter thread");
+
+    match cli.join() {
+        Ok(code) => exit(code),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+///Everything `main` does, run on a thread with a deep enough stack
+fn cli() -> i32 {
     //Collect arguments then run based on number of arguments
     // let args: Vec<String> = env::args().collect();
     let args = Args::parse();
+    diagnostics::set_format(args.error_format);
 
-    //Runs file then an interactive prompt depending on command-line flags
-    //TODO: Intoduce a feature where the file can pass it's enviroment to the interactive shell,
-    //like Python
+    if let Some(code) = args.explain {
+        return explain(&code);
+    }
+
+    //Runs file then an interactive prompt depending on command-line flags, both share one
+    //backend so the prompt starts with everything the file defined, like `python -i`
+    let mut backend: Box<dyn Backend> = match args.backend {
+        BackendKind::Tree => Box::new(interpreter::Interpreter::new()),
+        BackendKind::Vm => Box::new(ir::vm::Vm::new()),
+    };
+
+    if let Some(output) = args.emit_llvm {
+        let Some(filepath) = args.filepath else {
+            eprintln!("--emit-llvm needs a source file, pass one with -f");
+            return ExitCode::CommandLineErr as i32;
+        };
+        return emit_llvm_file(filepath, output);
+    }
 
+    let mut code = ExitCode::Okay as i32;
     if let Some(filepath) = args.filepath {
-        run_file(filepath);
+        code = run_file(backend.as_mut(), filepath);
     }
 
+    //A failed file still hands over whatever it defined before stopping
     if args.interpret {
-        run_prompt();
+        run_prompt(backend.as_mut());
+        code = ExitCode::Okay as i32;
     }
 
-    exit(0);
+    code
 }
 
-#[allow(unused)]
-///Sends runtime error report to user with specific additonal details
-fn report(line: u32, place: String, message: String) {
-    if place != *"" {
-        let place: String = "in ".to_owned() + &place;
+///Runs source string provided, may be multi-line string
+#[cfg(test)]
+fn run(source: &str) -> Result<i32, Error> {
+    let mut interpreter = interpreter::Interpreter::new();
+    run_with(&mut interpreter, source, false)
+}
+
+///Something that can execute a resolved program, lets the CLI pick between the tree-walking
+///interpreter and the bytecode VM
+trait Backend {
+    ///Runs `statements`, returning the exit code, when `echo` is set the value of every bare
+    ///expression statement is printed back like Python's REPL
+    fn execute_program(
+        &mut self,
+        statements: &mut [types::statement::Statement],
+        echo: bool,
+    ) -> i32;
+}
+
+impl Backend for interpreter::Interpreter {
+    fn execute_program(
+        &mut self,
+        statements: &mut [types::statement::Statement],
+        echo: bool,
+    ) -> i32 {
+        match self.execute_statements(statements, echo) {
+            Ok(()) => ExitCode::Okay as i32,
+            Err(err) => {
+                diagnostics::Diagnostic::from(&err).emit();
+                ExitCode::RuntimeErr as i32
+            }
+        }
     }
+}
 
-    println!(" [Line {line}] Error: {message} {place}");
+impl interpreter::Interpreter {
+    ///Runs every top level statement, stopping at the first runtime error
+    fn execute_statements(
+        &mut self,
+        statements: &mut [types::statement::Statement],
+        echo: bool,
+    ) -> interpreter::RuntimeResult<()> {
+        for statement in statements {
+            match statement {
+                //Assignments are expressions too, but echoing them back is just noise
+                types::statement::Statement::Expression(expression)
+                    if echo
+                        && !matches!(
+                            expression.expression,
+                            types::Expression::Assignment(_)
+                                | types::Expression::Set(_)
+                                | types::Expression::SetIndex(_)
+                                | types::Expression::Compound(_)
+                        ) =>
+                {
+                    let value = self.evaluate(&mut expression.expression)?;
+                    if !matches!(value, types::LiteralType::Nil) {
+                        self.output(&format!("{value}\n"));
+                    }
+                }
+                _ => {
+                    self.execute(statement)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
-///Sends an error report to user - semantic sugar
-fn error(line: u32, message: String) {
-    report(line, String::from(""), message)
+///Runs source against an existing backend so state carries over between calls
+fn run_with(backend: &mut dyn Backend, source: &str, echo: bool) -> Result<i32, Error> {
+    run_named(backend, "<repl>", source, echo)
 }
 
-///Runs source string provided, may be multi-line string
-fn run(source: &str) -> Result<i32, Error> {
-    let mut scanner = scanner::Scanner::new(source, None, Some(1));
+///Runs source against an existing backend, `name` is what diagnostics call the source
+fn run_named(
+    backend: &mut dyn Backend,
+    name: &str,
+    source: &str,
+    echo: bool,
+) -> Result<i32, Error> {
+    match frontend(name, source) {
+        Some(mut statements) => Result::Ok(backend.execute_program(&mut statements, echo)),
+        None => Result::Ok(ExitCode::DataErr as i32),
+    }
+}
 
-    //Scan in & Store token string
-    let mut tokens: Vec<types::token::Token> = scanner.scan_tokens();
+///Scans, parses and resolves source, reporting any errors and handing back nothing if there were
+fn frontend(name: &str, source: &str) -> Option<Vec<types::statement::Statement>> {
+    let file = diagnostics::add_source(name, source);
+    let mut scanner = scanner::Scanner::new(source, file, None, Some(1));
 
-    //Push Final EOF token
-    tokens.push(types::token::Token::new(
-        types::TokenType::Eof,
-        "",
-        None,
-        scanner.line,
-    ));
+    //Scan in & Store token string, ending with EOF
+    let tokens: Vec<types::token::Token> = scanner.scan_tokens();
 
     // debug!("Tokenizing Done");
 
     let mut parser = parser::Parser::new(tokens, 0);
-    let statements: Vec<types::statement::Statement> = parser.parse();
+    let parsed = parser.parse();
 
     // debug!("Parsing Done");
 
-    let mut interpreter = interpreter::Interpreter::new();
-    for statement in statements {
-        interpreter.execute(statement);
+    //Refuse to run a program with any syntax errors, reporting them all at once
+    let mut errors = std::mem::take(&mut scanner.errors);
+    if let Err(ref parser_errors) = parsed {
+        errors.extend(parser_errors.iter().map(diagnostics::Diagnostic::from));
+    }
+    let mut statements = match parsed {
+        Ok(statements) if errors.is_empty() => statements,
+        _ => {
+            diagnostics::emit_all(errors);
+            return None;
+        }
+    };
+
+    //Refuse to run anything if scoping is broken
+    if let Err(errors) = resolver::Resolver::new().resolve(&mut statements) {
+        diagnostics::emit_all(errors.iter().map(diagnostics::Diagnostic::from).collect());
+        return None;
     }
 
-    Result::Ok(ExitCode::Okay as i32)
+    Some(statements)
+}
+
+///Lowers source to a textual LLVM IR module, reporting anything the backend can't handle
+fn emit_llvm(name: &str, source: &str) -> Result<String, i32> {
+    let mut statements = frontend(name, source).ok_or(ExitCode::DataErr as i32)?;
+
+    ir::llvm::LlvmEmitter::new(name)
+        .emit(&mut statements)
+        .map_err(|errors| {
+            for error in errors {
+                diagnostics::Diagnostic::from(&error).emit();
+            }
+            ExitCode::DataErr as i32
+        })
 }
 
 use std::path::Path;
 
-///On Startup - Runs source from provided filepath
-pub fn run_file(filepath: String) {
-    let file_path = Path::new(&filepath);
+///On Startup - Runs source from provided filepath, returning the exit code for the shell
+fn run_file(backend: &mut dyn Backend, filepath: String) -> i32 {
+    let Some(valid_source) = read_source(&filepath) else {
+        return ExitCode::GenerallyBad as i32;
+    };
+
+    //Run the code
+    match run_named(backend, &filepath, &valid_source, false) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::GenerallyBad as i32
+        }
+    }
+}
+
+///On Startup - Writes the LLVM IR for the provided filepath to `output` instead of running it
+fn emit_llvm_file(filepath: String, output: String) -> i32 {
+    let Some(valid_source) = read_source(&filepath) else {
+        return ExitCode::GenerallyBad as i32;
+    };
+
+    match emit_llvm(&filepath, &valid_source) {
+        Ok(module) => {
+            if let Err(error) = fs::write(&output, module) {
+                eprintln!("File Error: {error}");
+                return ExitCode::OSErr as i32;
+            }
+            ExitCode::Okay as i32
+        }
+        Err(code) => code,
+    }
+}
+
+///Prints the long description of an error code
+fn explain(code: &str) -> i32 {
+    match diagnostics::codes::lookup(code) {
+        Some(error_code) => {
+            println!(
+                "{}: {}\n\n{}",
+                error_code.code, error_code.title, error_code.explanation
+            );
+            ExitCode::Okay as i32
+        }
+        None => {
+            eprintln!("{code} is not an rlux error code.");
+            ExitCode::CommandLineErr as i32
+        }
+    }
+}
+
+///Reads a lux source file, `None` when there is nothing to run
+fn read_source(filepath: &str) -> Option<String> {
+    let file_path = Path::new(filepath);
     //println!("File Path: {}", filepath);
     if !file_path.exists() {
-        println!("Please provide a valid file.");
-        return;
+        eprintln!("Please provide a valid file.");
+        return None;
     }
 
     let unvalidated_extension = file_path.extension();
@@ -116,50 +306,44 @@ pub fn run_file(filepath: String) {
 
     //Check source for OS Errors
     if let Err(error) = source {
-        println!("File Error: {error}");
+        eprintln!("File Error: {error}");
         exit(ExitCode::GenerallyBad as i32);
     }
 
-    let valid_source = source.unwrap();
-
-    //Run the code
-    match run(&valid_source) {
-        Ok(_) => {
-            // exit(ExitCode::Okay as i32);
-        }
-        Err(err) => {
-            // println!("{err}");
-            // exit(ExitCode::GenerallyBad as i32);
-        }
-    };
+    source.ok()
 }
 
 ///On startup - Enters Interactive Mode
-pub fn run_prompt() {
+fn run_prompt(backend: &mut dyn Backend) {
+    //One backend lives for the whole session so definitions persist between lines
     loop {
+        print!("> ");
+        let _ = stdout().flush();
+
         let input: &mut String = &mut String::new();
         let matcher = stdin().read_line(input);
 
         //Bad Path 1 - Readline err
         if let Err(err) = matcher {
-            println!("{err}");
+            eprintln!("{err}");
             exit(ExitCode::DataErr as i32);
         }
 
+        //End of input (Ctrl-D) leaves the prompt
+        if let Ok(0) = matcher {
+            println!();
+            return;
+        }
+
         //Core function of REPL
-        let result = run(input);
+        let result = run_with(backend, input, true);
 
-        // Bad Path 2 - REPL Returns an err
+        // Bad Path 2 - REPL Returns an err, a line that merely fails to compile is already
+        // reported and the session carries on
         if let Err(err) = result {
-            println!("{err}");
+            eprintln!("{err}");
             exit(ExitCode::DataErr as i32);
         }
-
-        //Check if we exit Normally
-        let number = result.unwrap();
-        if number == ExitCode::DataErr as i32 {
-            exit(0);
-        }
     }
 }
 
@@ -168,15 +352,35 @@ use clap::Parser;
 #[derive(Parser, Debug)]
 #[command(version, about, author)]
 struct Args {
-    ///If this flag is set, instead of being interpreted input will be compiled into llvm
-    #[arg(short, long, default_value_t = true)]
-    ir: bool,
+    ///Instead of running the file, compile it into textual LLVM IR written to this path
+    #[arg(short = 'i', long, visible_alias = "ir", value_name = "OUT")]
+    emit_llvm: Option<String>,
 
     ///If this flag set, rlux will enter an interactive REPL
     #[arg(short = 'n', long, default_value_t = false)]
     interpret: bool,
 
+    ///Which engine runs the program, the tree-walking interpreter or the bytecode VM
+    #[arg(short, long, value_enum, default_value_t = BackendKind::Tree)]
+    backend: BackendKind,
+
     ///Filepath for .lux source file
     #[arg(short, long)]
     filepath: Option<String>,
+
+    ///Explain an error code, like E0012, then exit
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
+
+    ///How errors are printed, `json` writes one object per line for tools to read
+    #[arg(long, value_enum, default_value_t = diagnostics::ErrorFormat::Human)]
+    error_format: diagnostics::ErrorFormat,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum BackendKind {
+    ///Walks the syntax tree directly
+    Tree,
+    ///Compiles to bytecode and runs it on a stack VM
+    Vm,
 }
@@ -1,8 +1,11 @@
 //use crate::token::Token;
-use crate::types::{
-    statement::*,
-    token::Token,
-    *,
+use crate::{
+    diagnostics::codes,
+    types::{
+        statement::*,
+        token::Token,
+        *,
+    },
 };
 
 pub(crate) mod expression;
@@ -11,11 +14,21 @@ pub(crate) mod statement;
 pub struct Parser {
     tokens: Vec<Token>,
     current: i32,
+    ///Every syntax error so far, parsing carries on past them so all get reported at once
+    errors: Vec<ParserError>,
+    ///Loops enclosing the current statement, innermost last, with their labels if they have one.
+    ///Function bodies start with none, a `break` can't leave the function it is in
+    loops: Vec<Option<String>>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>, current: i32) -> Parser {
-        Parser { tokens, current }
+        Parser {
+            tokens,
+            current,
+            errors: vec![],
+            loops: vec![],
+        }
     }
 
     fn match_token_type(&mut self, types: Vec<TokenType>) -> bool {
@@ -38,6 +51,22 @@ impl Parser {
         }
     }
 
+    fn check_next(&self, token_type: TokenType) -> bool {
+        //!Checks the Token after the next one is of a certain Type
+        match self.tokens.get((self.current + 1) as usize) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
+    fn check_third(&self, token_type: TokenType) -> bool {
+        //!Checks the Token two after the next one is of a certain Type
+        match self.tokens.get((self.current + 2) as usize) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         //!Returns the current token while advancing the counter
         if !self.is_at_end() {
@@ -68,56 +97,93 @@ impl Parser {
             Ok(self.advance())
         } else {
             let token = self.peek();
-            Err(Self::error(token, message))
+            Err(ParserError {
+                code: codes::expected(type_),
+                ..Self::error(token, message)
+            })
         }
     }
 
     fn error(token: Token, message: &str) -> ParserError {
-        // let _ = crate::error(token.line, message.to_string());
         ParserError {
             source: token,
             cause: message.to_string(),
+            code: None,
         }
     }
 
-    fn synchronize(&mut self) {
+    ///Skips ahead to what is probably the start of the next statement so one mistake doesn't
+    ///cascade into a wall of errors. `start` is where the failed declaration began
+    fn synchronize(&mut self, start: i32) {
+        //A missing `;` or `}` is reported on whatever comes next, which shouldn't be thrown away.
+        //The failing token is only kept if something was consumed, otherwise it would fail forever
+        if self.current > start && self.at_boundary() {
+            return;
+        }
+
         self.advance();
         while !self.is_at_end() {
-            if self.previous().token_type == TokenType::Semicolon {
+            match self.previous().token_type {
+                TokenType::Semicolon => return,
+                //A block skipped into most likely belongs to the broken statement, like the body
+                //of a function with a bad signature, so it goes too
+                TokenType::LeftBrace => {
+                    self.skip_block();
+                    return;
+                }
+                _ => {}
+            }
+            if self.at_boundary() {
                 return;
-            };
+            }
+
+            self.advance();
+        }
+    }
 
-            match self.peek().token_type {
-                TokenType::Class
+    fn skip_block(&mut self) {
+        //!Advances past the `}` matching an already consumed `{`
+        let mut depth = 1;
+        while depth > 0 && !self.is_at_end() {
+            match self.advance().token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn at_boundary(&self) -> bool {
+        //!Checks next Token starts a statement or closes the enclosing block
+        matches!(
+            self.peek().token_type,
+            TokenType::Class
                 | TokenType::Fun
                 | TokenType::Var
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
                 // | TokenType::Print
-                | TokenType::Return => {
-                    return;
-                }
-
-                _ => {}
-            }
-
-            self.advance();
-        }
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::RightBrace
+        )
     }
 
-    pub(crate) fn parse(&mut self) -> Vec<Statement> {
+    ///Parses the whole token stream, handing back every syntax error in order if there were any
+    pub(crate) fn parse(&mut self) -> Result<Vec<Statement>, Vec<ParserError>> {
         let mut statements: Vec<Statement> = vec![];
         while !self.is_at_end() {
-            let state = self.declaration();
-            match state {
-                Ok(statement) => {
-                    statements.push(statement);
-                }
-                Err(_err) => {}
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
             }
         }
 
-        statements
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    macros::error_check,
+    diagnostics::codes,
     parser::Parser,
     types::{
         Expression,
@@ -13,9 +13,10 @@ use crate::{
 
 //These macros create new types of expressions, this is so the code is understandable
 macro_rules! new_ternary {
-    ($eval:expr, $lhs:expr,  $rhs:expr) => {
+    ($eval:expr, $question:expr, $lhs:expr,  $rhs:expr) => {
         Expression::Ternary(Box::new(Ternary {
             evaluator: $eval,
+            question: $question,
             left: $lhs,
             right: $rhs,
         }))
@@ -47,6 +48,17 @@ macro_rules! new_assignment {
         Expression::Assignment(Box::new(Assignment {
             name: $name,
             value: $value,
+            binding: None,
+        }))
+    };
+}
+
+macro_rules! new_set {
+    ($object:expr, $name:expr, $value:expr) => {
+        Expression::Set(Box::new(Set {
+            object: $object,
+            name: $name,
+            value: $value,
         }))
     };
 }
@@ -71,16 +83,20 @@ macro_rules! new_unary {
 }
 
 macro_rules! new_grouping {
-    ($expression:expr) => {
+    ($expression:expr, $span:expr) => {
         Expression::Grouping(Box::new(Grouping {
             expression: $expression,
+            span: $span,
         }))
     };
 }
 
 macro_rules! new_literal {
-    ($value:expr) => {
-        Expression::Literal(Box::new(Literal { value: $value }))
+    ($value:expr, $span:expr) => {
+        Expression::Literal(Box::new(Literal {
+            value: $value,
+            span: $span,
+        }))
     };
 }
 
@@ -117,17 +133,17 @@ impl Parser {
         let mut ternary = self.assignment()?;
 
         while self.match_token_type(vec![TokenType::Question]) {
+            let question = self.previous();
             let lhs = self.assignment()?;
 
             /* Consume ":", Enforces Grammar */
-            let consumed = self.consume(
+            self.consume(
                 TokenType::Colon,
                 &(format!("Expected \":\" instead of {}", self.peek())),
-            );
-            error_check!(consumed);
+            )?;
 
             let rhs = self.assignment()?;
-            ternary = new_ternary!(ternary, lhs, rhs);
+            ternary = new_ternary!(ternary, question, lhs, rhs);
         }
 
         Ok(ternary)
@@ -136,23 +152,48 @@ impl Parser {
     fn assignment(&mut self) -> Result<Expression, ParserError> {
         let expression = self.equality()?;
 
+        if self.match_token_type(vec![
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let mut operator = self.previous();
+            operator.token_type = match operator.token_type {
+                TokenType::PlusEqual => TokenType::Plus,
+                TokenType::MinusEqual => TokenType::Minus,
+                TokenType::StarEqual => TokenType::Star,
+                _ => TokenType::Slash,
+            };
+            let value = self.assignment()?;
+            return self.compound(expression, operator, value, false, false);
+        }
+
         if self.match_token_type(vec![TokenType::Equal]) {
             let equals = self.previous();
             let value: Expression = self.assignment()?;
-            if self.peek().token_type != TokenType::RightParen {
-                let consumed = self.consume(TokenType::Semicolon, "Expected ';' after assignement");
-                // println!("{:?}", consumed);
-                error_check!(consumed);
-            }
-            match expression.clone() {
+            match expression {
                 Expression::Variable(var) => {
                     let name = var.name;
                     return Ok(new_assignment!(name, value));
                 }
+                //Assigning to a property turns the Get into a Set
+                Expression::Get(get) => {
+                    return Ok(new_set!(get.object, get.name, value));
+                }
+                Expression::Index(index) => {
+                    return Ok(Expression::SetIndex(Box::new(SetIndex {
+                        object: index.object,
+                        index: index.index,
+                        bracket: index.bracket,
+                        value,
+                    })));
+                }
                 _ => {
                     return Err(ParserError {
                         source: equals,
                         cause: String::from("Invalid Assignment Target"),
+                        code: Some(&codes::INVALID_ASSIGNMENT_TARGET),
                     });
                 }
             }
@@ -205,7 +246,7 @@ impl Parser {
     fn factor(&mut self) -> Result<Expression, ParserError> {
         let mut expression = self.unary()?;
 
-        while self.match_token_type(vec![TokenType::Slash, TokenType::Star]) {
+        while self.match_token_type(vec![TokenType::Slash, TokenType::Star, TokenType::Percent]) {
             let operator = self.previous();
             let right = self.unary()?;
             expression = new_binary!(expression, operator, right);
@@ -218,23 +259,107 @@ impl Parser {
         if self.match_token_type(vec![TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
             let right = self.unary()?;
-            let expression = new_unary!(operator, right);
-            println!("{}", expression.clone());
-            return Ok(expression);
+            return Ok(new_unary!(operator, right));
         }
 
-        self.call()
+        self.power()
     }
 
-    fn call(&mut self) -> Result<Expression, ParserError> {
-        let mut expression = self.primary();
+    fn power(&mut self) -> Result<Expression, ParserError> {
+        //!`**` binds tighter than a unary minus on its left and is right associative,
+        //!so `-2 ** 2` is `-4` and `2 ** 3 ** 2` is `2 ** 9`
+        let expression = self.increment()?;
 
-        while self.match_token_type(vec![TokenType::LeftParen]) {
-            expression =
-                self.finish_call(expression.expect("Expression Expected, ParserError Found"));
+        if self.match_token_type(vec![TokenType::StarStar]) {
+            let operator = self.previous();
+            let right = self.unary()?;
+            return Ok(new_binary!(expression, operator, right));
         }
 
-        expression
+        Ok(expression)
+    }
+
+    fn increment(&mut self) -> Result<Expression, ParserError> {
+        //!Prefix `++x` evaluates to the new value, postfix `x++` to the old one
+        if self.match_token_type(vec![TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            let target = self.call()?;
+            return self.step(target, operator, false);
+        }
+
+        let expression = self.call()?;
+
+        if self.match_token_type(vec![TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            return self.step(expression, operator, true);
+        }
+
+        Ok(expression)
+    }
+
+    fn step(
+        &self,
+        target: Expression,
+        mut operator: Token,
+        postfix: bool,
+    ) -> Result<Expression, ParserError> {
+        //!`x++` is `x += 1` and `x--` is `x -= 1`, marked as a step so only numbers are accepted
+        operator.token_type = match operator.token_type {
+            TokenType::PlusPlus => TokenType::Plus,
+            _ => TokenType::Minus,
+        };
+        let value = new_literal!(LiteralType::Number(1.0), operator.span);
+
+        self.compound(target, operator, value, postfix, true)
+    }
+
+    fn compound(
+        &self,
+        target: Expression,
+        operator: Token,
+        value: Expression,
+        postfix: bool,
+        step: bool,
+    ) -> Result<Expression, ParserError> {
+        match target {
+            Expression::Variable(_) | Expression::Get(_) | Expression::Index(_) => {
+                Ok(Expression::Compound(Box::new(Compound {
+                    target,
+                    operator,
+                    value,
+                    postfix,
+                    step,
+                })))
+            }
+            _ => Err(ParserError {
+                source: operator,
+                cause: String::from("Invalid Assignment Target"),
+                code: Some(&codes::INVALID_ASSIGNMENT_TARGET),
+            }),
+        }
+    }
+
+    fn call(&mut self) -> Result<Expression, ParserError> {
+        let mut expression = self.primary()?;
+
+        loop {
+            if self.match_token_type(vec![TokenType::LeftParen]) {
+                expression = self.finish_call(expression)?;
+            } else if self.match_token_type(vec![TokenType::LeftBracket]) {
+                expression = self.finish_index(expression)?;
+            } else if self.match_token_type(vec![TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expression = Expression::Get(Box::new(Get {
+                    object: expression,
+                    name,
+                }));
+            } else {
+                break;
+            }
+        }
+
+        Ok(expression)
     }
 
     fn finish_call(&mut self, callee: Expression) -> Result<Expression, ParserError> {
@@ -252,13 +377,116 @@ impl Parser {
         }
 
         let paren: Token = self.consume(TokenType::RightParen, "Expect ')' after arguments ")?;
-        let consumed = self.consume(TokenType::Semicolon, "Expected ';' after function call");
-        // println!("{:?}",consumed);
-        error_check!(consumed);
 
         Ok(new_call!(callee, paren, arguments))
     }
 
+    fn finish_index(&mut self, object: Expression) -> Result<Expression, ParserError> {
+        //!Parses what follows `[`, either an index or a slice like `1:3`, `:3` or `1:`
+        let start = if self.check(TokenType::Colon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        if self.match_token_type(vec![TokenType::Colon]) {
+            let end = if self.check(TokenType::RightBracket) {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            let bracket = self.consume(TokenType::RightBracket, "Expect ']' after slice.")?;
+            return Ok(Expression::Slice(Box::new(Slice {
+                object,
+                start,
+                end,
+                bracket,
+            })));
+        }
+
+        let bracket = self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+        Ok(Expression::Index(Box::new(Index {
+            object,
+            index: start.expect("Only a slice can start with ':'"),
+            bracket,
+        })))
+    }
+
+    fn list(&mut self) -> Result<Expression, ParserError> {
+        //!Parses the elements after `[`, a trailing comma is allowed
+        let bracket = self.previous();
+        let mut elements: Vec<Expression> = vec![];
+        while !self.check(TokenType::RightBracket) {
+            elements.push(self.expression()?);
+            if !self.match_token_type(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+
+        let closing = self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+        Ok(Expression::List(Box::new(List {
+            elements,
+            span: bracket.span.to(closing.span),
+            bracket,
+        })))
+    }
+
+    fn map(&mut self) -> Result<Expression, ParserError> {
+        //!Parses the `key: value` entries after `{`, a trailing comma is allowed
+        let brace = self.previous();
+        let mut entries: Vec<(Expression, Expression)> = vec![];
+        while !self.check(TokenType::RightBrace) {
+            let key = self.expression()?;
+            self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+            entries.push((key, self.expression()?));
+            if !self.match_token_type(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+
+        let closing = self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expression::Map(Box::new(Map {
+            entries,
+            span: brace.span.to(closing.span),
+            brace,
+        })))
+    }
+
+    fn interpolation(&mut self) -> Result<Expression, ParserError> {
+        //!The scanner splits `"a ${b} c"` into an Interpolation token for `a `, the tokens of `b`,
+        //!then a String token for ` c`, with one Interpolation token per `${`
+        let opening = self.previous();
+        let mut parts: Vec<Expression> = vec![];
+        let mut piece = opening.clone();
+
+        loop {
+            //Empty pieces, like the one before `${` in `"${a}"`, add nothing
+            if let Some(LiteralType::String(ref text)) = piece.literal
+                && !text.is_empty()
+            {
+                parts.push(new_literal!(LiteralType::String(text.clone()), piece.span));
+            }
+            if piece.token_type == TokenType::String {
+                break;
+            }
+
+            parts.push(self.expression()?);
+            piece = if self.match_token_type(vec![TokenType::Interpolation, TokenType::String]) {
+                self.previous()
+            } else {
+                return Err(ParserError {
+                    code: Some(&codes::UNBALANCED_BRACES),
+                    ..Self::error(self.peek(), "Expected '}' to close '${'")
+                });
+            };
+        }
+
+        Ok(Expression::Interpolation(Box::new(Interpolation {
+            parts,
+            span: opening.span.to(piece.span),
+        })))
+    }
+
     fn primary(&mut self) -> Result<Expression, ParserError> {
         if self.match_token_type(vec![
             TokenType::False,
@@ -271,14 +499,21 @@ impl Parser {
             This will always work bc I am garunteeing that literal types
             will always have a literal value attatched
             */
-            let underlying_value = self.previous().literal.unwrap();
+            let token = self.previous();
+            let span = token.span;
+            let underlying_value = token.literal.unwrap();
 
             let return_val = match underlying_value {
-                LiteralType::Number(num) => new_literal!(LiteralType::Number(num)),
-                LiteralType::String(string) => new_literal!(LiteralType::String(string)),
-                LiteralType::Boolean(boolean) => new_literal!(LiteralType::Boolean(boolean)),
-                LiteralType::Nil => new_literal!(LiteralType::Nil),
-                LiteralType::Callable(_) => {
+                LiteralType::Number(num) => new_literal!(LiteralType::Number(num), span),
+                LiteralType::String(string) => new_literal!(LiteralType::String(string), span),
+                LiteralType::Boolean(boolean) => {
+                    new_literal!(LiteralType::Boolean(boolean), span)
+                }
+                LiteralType::Nil => new_literal!(LiteralType::Nil, span),
+                LiteralType::Callable(_)
+                | LiteralType::Instance(_)
+                | LiteralType::List(_)
+                | LiteralType::Map(_) => {
                     // This specific literal will always get caught higher up on the tree
                     unreachable!()
                 }
@@ -286,39 +521,59 @@ impl Parser {
 
             Ok(return_val)
         } else if self.match_token_type(vec![TokenType::LeftParen]) {
+            let left_paren = self.previous();
             let expression = self.expression()?;
-            let consumed = self.consume(
+            self.consume(
                 TokenType::RightParen,
                 "Expect \')\' after grouping expression.",
-            );
-            error_check!(consumed);
-
-            Ok(new_grouping!(expression))
+            )?;
+
+            let span = left_paren.span.to(self.previous().span);
+            Ok(new_grouping!(expression, span))
+        } else if self.match_token_type(vec![TokenType::This]) {
+            Ok(Expression::This(Box::new(This {
+                keyword: self.previous(),
+                binding: None,
+            })))
+        } else if self.match_token_type(vec![TokenType::Super]) {
+            let keyword = self.previous();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            Ok(Expression::Super(Box::new(Super {
+                keyword,
+                method,
+                binding: None,
+            })))
         } else if self.match_token_type(vec![TokenType::Identifier]) {
             Ok(Expression::Variable(Box::new(Variable {
                 name: self.previous(),
+                binding: None,
             })))
+        } else if self.match_token_type(vec![TokenType::LeftBracket]) {
+            self.list()
+        } else if self.match_token_type(vec![TokenType::LeftBrace]) {
+            //A statement starting with `{` is always a block, so this only sees maps
+            self.map()
+        } else if self.match_token_type(vec![TokenType::Interpolation]) {
+            self.interpolation()
         } else if self.match_token_type(vec![TokenType::Fun]) {
-            let mut arguments: Vec<Expression> = vec![];
-
-            if !self.check(TokenType::RightParen) {
-                //Secretly a do while
-                while {
-                    //Body
-                    arguments.push(self.expression()?);
-
-                    //Then Eval Condition
-                    self.match_token_type(vec![TokenType::Comma])
-                } {}
-            }
-
-            let paren: Token =
-                self.consume(TokenType::RightParen, "Expect ')' after arguments ")?;
-            Ok(Expression::Lambda(Box::new(Lambda { paren, arguments })))
+            let keyword = self.previous();
+            let (parameters, body) = self.function_signature_and_body("lambda")?;
+            Ok(Expression::Lambda(Box::new(Lambda {
+                span: keyword.span.to(self.previous().span),
+                keyword,
+                parameters,
+                body,
+            })))
         } else {
+            let found = match self.peek().token_type {
+                TokenType::Eof => String::from("the end of the file"),
+                _ => format!("'{}'", self.peek().lexeme),
+            };
             Err(ParserError {
                 source: self.peek(),
-                cause: "Not in Parser AST: \'".to_owned() + &self.peek().lexeme + "\'",
+                cause: format!("Expected expression, found {found}."),
+                code: Some(&codes::EXPECTED_EXPRESSION),
             })
         }
     }
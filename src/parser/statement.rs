@@ -1,5 +1,4 @@
 use crate::{
-    interpreter::Interpreter,
     macros::visitable_trait_two_elements as visitable_trait,
     parser::{
         LiteralType,
@@ -18,39 +17,56 @@ pub(crate) trait Visitable<T, U> {
     fn accept(&mut self, visitor: &mut U) -> T;
 }
 
-pub(crate) trait StatementVisitor {
+pub(crate) trait StatementVisitor<T> {
     //Turning print into a native function
-    //fn visit_print_statement(&mut self, print: &mut PrintStatement) -> Statement;
-    fn visit_expression_statement(&mut self, expression: &mut ExpressionStatement) -> Statement;
-    fn visit_variable_statement(&mut self, var: &mut VariableStatement) -> Statement;
-    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) -> Statement;
-    fn visit_while_statement(&mut self, while_statement: &mut WhileStatement) -> Statement;
-    fn visit_block_statement(&mut self, block_statement: &mut BlockStatement) -> Statement;
-    fn visit_function_statement(&mut self, function_statement: &mut FunctionStatement)
-    -> Statement;
-    fn visit_return_statement(&mut self, return_statement: &mut ReturnStatement) -> Statement;
+    //fn visit_print_statement(&mut self, print: &mut PrintStatement) -> T;
+    fn visit_expression_statement(&mut self, expression: &mut ExpressionStatement) -> T;
+    fn visit_variable_statement(&mut self, var: &mut VariableStatement) -> T;
+    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) -> T;
+    fn visit_while_statement(&mut self, while_statement: &mut WhileStatement) -> T;
+    fn visit_for_in_statement(&mut self, for_in: &mut ForInStatement) -> T;
+    fn visit_block_statement(&mut self, block_statement: &mut BlockStatement) -> T;
+    fn visit_function_statement(&mut self, function_statement: &mut FunctionStatement) -> T;
+    fn visit_return_statement(&mut self, return_statement: &mut ReturnStatement) -> T;
+    fn visit_class_statement(&mut self, class_statement: &mut ClassStatement) -> T;
+    fn visit_break_statement(&mut self, break_statement: &mut BreakStatement) -> T;
+    fn visit_continue_statement(&mut self, continue_statement: &mut ContinueStatement) -> T;
+    fn visit_try_statement(&mut self, try_statement: &mut TryStatement) -> T;
+    fn visit_throw_statement(&mut self, throw: &mut ThrowStatement) -> T;
 }
 
-impl Visitable<Statement, Interpreter> for Statement {
-    fn accept(&mut self, visitor: &mut Interpreter) -> Statement {
+impl<T, V: StatementVisitor<T>> Visitable<T, V> for Statement {
+    fn accept(&mut self, visitor: &mut V) -> T {
         match self {
             // Statement::Print(statement) => statement.accept(visitor),
             Statement::Expression(statement) => statement.accept(visitor),
             Statement::Variable(statement) => statement.accept(visitor),
             Statement::If(statement) => statement.accept(visitor),
             Statement::While(statement) => statement.accept(visitor),
+            Statement::ForIn(statement) => statement.accept(visitor),
             Statement::Block(statement) => statement.accept(visitor),
             Statement::Function(statement) => statement.accept(visitor),
             Statement::Return(statement) => statement.accept(visitor),
+            Statement::Class(statement) => statement.accept(visitor),
+            Statement::Break(statement) => statement.accept(visitor),
+            Statement::Continue(statement) => statement.accept(visitor),
+            Statement::Try(statement) => statement.accept(visitor),
+            Statement::Throw(statement) => statement.accept(visitor),
         }
     }
 }
 
-visitable_trait! {Statement, IfStatement, Interpreter}
-// visitable_trait! {Statement, PrintStatement, Interpreter}
-visitable_trait! {Statement, ReturnStatement, Interpreter}
-visitable_trait! {Statement, VariableStatement, Interpreter}
-visitable_trait! {Statement, ExpressionStatement, Interpreter}
-visitable_trait! {Statement, WhileStatement, Interpreter}
-visitable_trait! {Statement, BlockStatement, Interpreter}
-visitable_trait! {Statement, FunctionStatement, Interpreter}
+visitable_trait! {IfStatement}
+// visitable_trait! {PrintStatement}
+visitable_trait! {ReturnStatement}
+visitable_trait! {VariableStatement}
+visitable_trait! {ExpressionStatement}
+visitable_trait! {WhileStatement}
+visitable_trait! {ForInStatement}
+visitable_trait! {BlockStatement}
+visitable_trait! {FunctionStatement}
+visitable_trait! {ClassStatement}
+visitable_trait! {BreakStatement}
+visitable_trait! {ContinueStatement}
+visitable_trait! {TryStatement}
+visitable_trait! {ThrowStatement}
@@ -1,42 +1,138 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+};
+
 use crate::{
-    interpreter::Interpreter,
+    diagnostics::codes,
+    enviroment::Enviroment,
+    interpreter::{
+        Completion,
+        Interpreter,
+        RuntimeResult,
+    },
     parser::{
         LiteralType,
         statement::*,
     },
+    types::{
+        RuntimeError,
+        TokenType,
+        lux_functions::class::LuxInstance,
+        token::Token,
+    },
 };
 
-macro_rules! new_literal {
-    ($value:expr) => {
-        Expression::Literal(Box::new(crate::types::expression::Literal {
-            value: $value,
-        }))
-    };
+///Where a `for-in` loop gets its next value from
+enum Iteration {
+    ///Lists are read by position each step, so pushing inside the loop extends it
+    List(Rc<RefCell<Vec<LiteralType>>>, usize),
+    ///Map keys and string characters are taken when the loop starts
+    Values(std::vec::IntoIter<LiteralType>),
+    Range {
+        next: f64,
+        end: f64,
+        inclusive: bool,
+    },
+    ///Called with no arguments until it returns nil
+    Function(LiteralType),
 }
 
-impl StatementVisitor for Interpreter {
-    fn visit_return_statement(&mut self, ret: &mut ReturnStatement) -> Statement {
-        use std::panic;
-        // let original_hook =
-        //Prevent Display of this particluar panic
-        panic::set_hook(Box::new(|_info| {
-            // do nothing
-        }));
-        let ret_value: Statement = if ret.value.is_some() {
-            ret.value = Some(new_literal!(self.evaluate(&mut ret.value.clone().unwrap())));
-            Statement::Return(ret.clone())
-        } else {
-            Statement::Return(ret.clone())
+impl Interpreter {
+    fn iteration(&mut self, iterable: &mut Iterable, keyword: &Token) -> RuntimeResult<Iteration> {
+        let value = match iterable {
+            Iterable::Value(iterable) => self.evaluate(iterable)?,
+            Iterable::Range {
+                start,
+                operator,
+                end,
+            } => {
+                let (start, end) = (self.evaluate(start)?, self.evaluate(end)?);
+                let (LiteralType::Number(start), LiteralType::Number(end)) = (start, end) else {
+                    return Err(RuntimeError::coded(operator, &codes::INVALID_RANGE));
+                };
+                let inclusive = operator.token_type == TokenType::DotDotEqual;
+                return Ok(Iteration::Range {
+                    next: start,
+                    end,
+                    inclusive,
+                });
+            }
         };
 
-        panic::panic_any(ret_value);
+        let next = match value {
+            LiteralType::List(list) => return Ok(Iteration::List(list, 0)),
+            LiteralType::Map(map) => {
+                let keys: Vec<LiteralType> = map.borrow().keys().map(LiteralType::from).collect();
+                return Ok(Iteration::Values(keys.into_iter()));
+            }
+            LiteralType::String(string) => {
+                let characters: Vec<LiteralType> = string
+                    .chars()
+                    .map(|character| LiteralType::String(character.into()))
+                    .collect();
+                return Ok(Iteration::Values(characters.into_iter()));
+            }
+            LiteralType::Callable(_) => Some(value),
+            LiteralType::Instance(instance) => LuxInstance::get(&instance, "next"),
+            _ => None,
+        };
+        next.map(Iteration::Function)
+            .ok_or_else(|| RuntimeError::coded(keyword, &codes::NOT_ITERABLE))
     }
-    fn visit_expression_statement(&mut self, expression: &mut ExpressionStatement) -> Statement {
-        let result = self.evaluate(&mut expression.expression);
-        Statement::Expression(ExpressionStatement {
-            expression: new_literal!(result),
+
+    fn next_value(
+        &mut self,
+        iteration: &mut Iteration,
+        keyword: &Token,
+    ) -> RuntimeResult<Option<LiteralType>> {
+        Ok(match iteration {
+            Iteration::List(list, index) => {
+                let element = list.borrow().get(*index).cloned();
+                *index += 1;
+                element
+            }
+            Iteration::Values(values) => values.next(),
+            Iteration::Range {
+                next,
+                end,
+                inclusive,
+            } => {
+                let in_range = if *inclusive {
+                    *next <= *end
+                } else {
+                    *next < *end
+                };
+                let value = in_range.then_some(LiteralType::Number(*next));
+                *next += 1.0;
+                value
+            }
+            Iteration::Function(function) => {
+                match self.call_value(function.clone(), keyword, vec![])? {
+                    LiteralType::Nil => None,
+                    value => Some(value),
+                }
+            }
         })
     }
+}
+
+impl StatementVisitor<RuntimeResult<Completion>> for Interpreter {
+    fn visit_return_statement(&mut self, ret: &mut ReturnStatement) -> RuntimeResult<Completion> {
+        let ret_value: LiteralType = match ret.value {
+            Some(ref mut value) => self.evaluate(value)?,
+            None => LiteralType::Nil,
+        };
+
+        Ok(Completion::Return(ret_value))
+    }
+    fn visit_expression_statement(
+        &mut self,
+        expression: &mut ExpressionStatement,
+    ) -> RuntimeResult<Completion> {
+        self.evaluate(&mut expression.expression)?;
+        Ok(Completion::Normal)
+    }
     /*fn visit_print_statement(&mut self, print: &mut PrintStatement) -> Statement {
         let expression = self.evaluate(&mut print.expression);
 
@@ -46,69 +142,138 @@ impl StatementVisitor for Interpreter {
         })
     }*/
 
-    fn visit_variable_statement(&mut self, var: &mut VariableStatement) -> Statement {
-        let init: LiteralType = if var.initalizer.is_some() {
-            //Should be fine because we are directly checking there is something before unwrapping
-            self.evaluate(&mut var.initalizer.clone().unwrap())
-        } else {
-            LiteralType::Nil
+    fn visit_variable_statement(
+        &mut self,
+        var: &mut VariableStatement,
+    ) -> RuntimeResult<Completion> {
+        let init: LiteralType = match var.initalizer {
+            Some(ref mut initalizer) => self.evaluate(initalizer)?,
+            None => LiteralType::Nil,
         };
 
-        self.enviroment.define(&var.name.lexeme.clone(), init);
+        self.enviroment.borrow_mut().define(&var.name.lexeme, init);
 
-        let var = var.clone();
-        Statement::Variable(VariableStatement {
-            name: var.name,
-            initalizer: var.initalizer,
-        })
+        Ok(Completion::Normal)
     }
-    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) -> Statement {
-        let unboxed = if_statement.clone();
-        let return_thing = unboxed.clone();
-        let mut condition = unboxed.condition;
-        let then_branch = *(unboxed.then_branch);
-        let else_branch = unboxed.else_branch;
-
-        if self.evaluate(&mut condition).into() {
-            self.execute(then_branch.clone());
-
-            then_branch
-        } else if else_branch.is_some() {
-            let else_branch = else_branch.unwrap();
-            self.execute(else_branch.clone());
-
-            else_branch
+    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) -> RuntimeResult<Completion> {
+        if self.evaluate_condition(&mut if_statement.condition, &if_statement.keyword)? {
+            self.execute(&mut if_statement.then_branch)
+        } else if let Some(ref mut else_branch) = *if_statement.else_branch {
+            self.execute(else_branch)
         } else {
-            Statement::If(return_thing)
+            Ok(Completion::Normal)
         }
     }
 
-    fn visit_while_statement(&mut self, while_statement: &mut WhileStatement) -> Statement {
-        let return_thing = while_statement.clone();
-        let unboxed = while_statement.clone();
-        let (body, mut condition) = (*(unboxed.body), unboxed.condition);
+    fn visit_while_statement(
+        &mut self,
+        while_statement: &mut WhileStatement,
+    ) -> RuntimeResult<Completion> {
+        //A jump without a label always belongs to the innermost loop
+        let label = while_statement.label.as_ref().map(|label| &label.lexeme);
+        let targets_this = |target: &Option<String>| target.is_none() || target.as_ref() == label;
+
+        while self.evaluate_condition(&mut while_statement.condition, &while_statement.keyword)? {
+            match self.execute(&mut while_statement.body)? {
+                Completion::Normal => {}
+                Completion::Break(target) if targets_this(&target) => break,
+                Completion::Continue(target) if targets_this(&target) => {}
+                completion => return Ok(completion),
+            }
 
-        while Into::<bool>::into(self.evaluate(&mut condition)) {
-            self.execute(body.clone());
+            if let Some(ref mut increment) = while_statement.increment {
+                self.evaluate(increment)?;
+            }
         }
 
-        Statement::While(return_thing)
+        Ok(Completion::Normal)
     }
 
-    fn visit_block_statement(&mut self, block_statement: &mut BlockStatement) -> Statement {
-        // println!("Before Block Execution");
-        if let Some(return_val) = self.execute_block(block_statement.statements.clone()) {
-            Statement::Return(return_val)
-        } else {
-            // println!("After Block Execution");
-            Statement::Block(block_statement.clone())
+    fn visit_for_in_statement(&mut self, for_in: &mut ForInStatement) -> RuntimeResult<Completion> {
+        let label = for_in.label.as_ref().map(|label| &label.lexeme);
+        let targets_this = |target: &Option<String>| target.is_none() || target.as_ref() == label;
+
+        let mut iteration = self.iteration(&mut for_in.iterable, &for_in.keyword)?;
+        while let Some(value) = self.next_value(&mut iteration, &for_in.keyword)? {
+            //Every pass gets its own variable, so closures made in the body keep their value
+            let enviroment = Enviroment::new(Some(Rc::clone(&self.enviroment)));
+            enviroment.borrow_mut().define(&for_in.name.lexeme, value);
+
+            match self.execute_block_in_env(std::slice::from_mut(&mut *for_in.body), enviroment)? {
+                Completion::Normal => {}
+                Completion::Break(target) if targets_this(&target) => break,
+                Completion::Continue(target) if targets_this(&target) => {}
+                completion => return Ok(completion),
+            }
         }
+
+        Ok(Completion::Normal)
+    }
+
+    fn visit_break_statement(
+        &mut self,
+        break_statement: &mut BreakStatement,
+    ) -> RuntimeResult<Completion> {
+        let label = break_statement
+            .label
+            .as_ref()
+            .map(|label| label.lexeme.clone());
+        Ok(Completion::Break(label))
+    }
+
+    fn visit_continue_statement(
+        &mut self,
+        continue_statement: &mut ContinueStatement,
+    ) -> RuntimeResult<Completion> {
+        let label = continue_statement
+            .label
+            .as_ref()
+            .map(|label| label.lexeme.clone());
+        Ok(Completion::Continue(label))
+    }
+
+    fn visit_try_statement(
+        &mut self,
+        try_statement: &mut TryStatement,
+    ) -> RuntimeResult<Completion> {
+        let mut outcome = self.execute_block(&mut try_statement.body);
+
+        if let Some(ref mut catch) = try_statement.catch
+            && let Err(error) = outcome
+        {
+            let enviroment = Enviroment::new(Some(Rc::clone(&self.enviroment)));
+            enviroment
+                .borrow_mut()
+                .define(&catch.name.lexeme, error.caught());
+            outcome = self.execute_block_in_env(&mut catch.body, enviroment);
+        }
+
+        //A `finally` that returns, jumps or throws replaces whatever the rest of the try did
+        if let Some(ref mut finally) = try_statement.finally {
+            match self.execute_block(finally)? {
+                Completion::Normal => {}
+                completion => return Ok(completion),
+            }
+        }
+        outcome
+    }
+
+    fn visit_throw_statement(&mut self, throw: &mut ThrowStatement) -> RuntimeResult<Completion> {
+        let value = self.evaluate(&mut throw.value)?;
+        Err(RuntimeError::thrown(&throw.keyword, value))
+    }
+
+    fn visit_block_statement(
+        &mut self,
+        block_statement: &mut BlockStatement,
+    ) -> RuntimeResult<Completion> {
+        self.execute_block(&mut block_statement.statements)
     }
 
     fn visit_function_statement(
         &mut self,
         function_statement: &mut FunctionStatement,
-    ) -> Statement {
+    ) -> RuntimeResult<Completion> {
         //! Define user function declarations
         use crate::types::lux_functions::{
             Functions,
@@ -117,13 +282,77 @@ impl StatementVisitor for Interpreter {
 
         let function_name = &function_statement.name.lexeme;
         let function = Functions::User(UserFunction {
-            closure: *self.enviroment.clone(),
+            closure: Rc::clone(&self.enviroment),
             declaration: Box::new(function_statement.clone()),
+            is_initializer: false,
         });
         self.enviroment
+            .borrow_mut()
             .define(function_name, LiteralType::Callable(function));
 
-        Statement::Function(function_statement.clone())
+        Ok(Completion::Normal)
     }
 
+    fn visit_class_statement(
+        &mut self,
+        class_statement: &mut ClassStatement,
+    ) -> RuntimeResult<Completion> {
+        //! Define a class, binding its methods to the enviroment it was declared in
+        use crate::types::lux_functions::{
+            Functions,
+            class::LuxClass,
+            user::UserFunction,
+        };
+        use std::collections::HashMap;
+
+        let superclass: Option<Rc<LuxClass>> = match class_statement.superclass {
+            Some(ref mut superclass) => match self.evaluate(superclass)? {
+                LiteralType::Callable(Functions::Class(class)) => Some(class),
+                _ => {
+                    return Err(RuntimeError::coded(
+                        &class_statement.name,
+                        &codes::INVALID_SUPERCLASS,
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        //Methods of a subclass see `super` one scope above their own closure
+        let mut method_enviroment = Rc::clone(&self.enviroment);
+        if let Some(ref superclass) = superclass {
+            method_enviroment = Enviroment::new(Some(method_enviroment));
+            method_enviroment.borrow_mut().define(
+                "super",
+                LiteralType::Callable(Functions::Class(Rc::clone(superclass))),
+            );
+        }
+
+        let methods: HashMap<String, UserFunction> = class_statement
+            .methods
+            .iter()
+            .map(|method| {
+                let function = UserFunction {
+                    closure: Rc::clone(&method_enviroment),
+                    declaration: Box::new(method.clone()),
+                    is_initializer: method.name.lexeme == "init",
+                };
+                (method.name.lexeme.clone(), function)
+            })
+            .collect();
+
+        let class = LuxClass {
+            name: class_statement.name.lexeme.clone(),
+            superclass,
+            methods,
+        };
+
+        //Methods can still refer to the class by name since they close over this same enviroment
+        self.enviroment.borrow_mut().define(
+            &class_statement.name.lexeme,
+            LiteralType::Callable(Functions::Class(Rc::new(class))),
+        );
+
+        Ok(Completion::Normal)
+    }
 }
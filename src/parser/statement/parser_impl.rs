@@ -1,16 +1,6 @@
-use std::time::{
-        SystemTime,
-        UNIX_EPOCH,
-    };
-
-use rand_chacha::rand_core::{
-    RngCore,
-    SeedableRng,
-};
-
 use super::*;
 use crate::{
-    macros::error_check,
+    diagnostics::codes,
     parser::Parser,
     types::token::Token,
 };
@@ -18,13 +8,10 @@ use crate::{
 impl Parser {
     /*Statement Grammar is Here Down */
     fn if_statement(&mut self) -> Result<Statement, ParserError> {
-        let consumed = self.consume(TokenType::LeftParen, "Expected \"(\" after if statement");
-        error_check!(consumed);
-
+        let keyword: Token = self.previous();
+        self.consume(TokenType::LeftParen, "Expected \"(\" after if statement")?;
         let condition = self.expression()?;
-
-        let consumed = self.consume(TokenType::RightParen, "Expected \")\" after if statement");
-        error_check!(consumed);
+        self.consume(TokenType::RightParen, "Expected \")\" after if statement")?;
 
         let then_branch = Box::new(self.statement()?);
         let else_branch: Option<Statement> = if self.match_token_type(vec![TokenType::Else]) {
@@ -36,6 +23,7 @@ impl Parser {
         let else_branch = Box::new(else_branch);
 
         Ok(Statement::If(IfStatement {
+            keyword,
             condition,
             then_branch,
             else_branch,
@@ -43,14 +31,13 @@ impl Parser {
     }
     fn return_statement(&mut self) -> Result<Statement, ParserError> {
         let keyword: Token = self.previous();
-        let value: Option<Expression> = if self.match_token_type(vec![TokenType::Semicolon]) {
+        let value: Option<Expression> = if self.check(TokenType::Semicolon) {
             None
         } else {
             Some(self.expression()?)
         };
 
-        let consumed = self.consume(TokenType::Semicolon, "Expected ';' after return");
-        error_check!(consumed);
+        self.consume(TokenType::Semicolon, "Expected ';' after return")?;
 
         Ok(Statement::Return(ReturnStatement { keyword, value }))
     }
@@ -63,18 +50,97 @@ impl Parser {
             Ok(Statement::Print(PrintStatement { expression }))
         }
     */
-    fn while_statement(&mut self) -> Result<Statement, ParserError> {
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Statement, ParserError> {
+        let keyword: Token = self.previous();
         let _ = self.consume(TokenType::LeftParen, "Expect '(' after while.")?;
         let condition = self.expression()?;
         let _ = self.consume(TokenType::RightParen, "Expect ')' after while condition.")?;
-        let body = Box::new(self.statement()?);
+        let body = Box::new(self.loop_body(&label)?);
 
-        Ok(Statement::While(WhileStatement { condition, body }))
+        Ok(Statement::While(WhileStatement {
+            keyword,
+            label,
+            condition,
+            body,
+            increment: None,
+        }))
+    }
+
+    fn loop_body(&mut self, label: &Option<Token>) -> Result<Statement, ParserError> {
+        //!Parses a loop's body with the loop in scope for `break` and `continue`
+        self.loops
+            .push(label.as_ref().map(|label| label.lexeme.clone()));
+        let body = self.statement();
+        self.loops.pop();
+        body
+    }
+
+    fn labeled_statement(&mut self) -> Result<Statement, ParserError> {
+        //!Parses `name: while ...` or `name: for ...`, only loops can be labeled
+        let label = self.advance();
+        self.advance();
+
+        if self.match_token_type(vec![TokenType::While]) {
+            self.while_statement(Some(label))
+        } else if self.match_token_type(vec![TokenType::For]) {
+            self.for_statement(Some(label))
+        } else {
+            Err(ParserError {
+                source: self.peek(),
+                cause: format!("Expect a loop after the label '{}'.", label.lexeme),
+                code: Some(&codes::INVALID_JUMP),
+            })
+        }
+    }
+
+    fn jump_statement(&mut self) -> Result<Statement, ParserError> {
+        //!Parses `break` or `continue`, with an optional label naming the loop they apply to
+        let keyword: Token = self.previous();
+        let label = if self.check(TokenType::Identifier) {
+            Some(self.advance())
+        } else {
+            None
+        };
+        self.consume(
+            TokenType::Semicolon,
+            &format!("Expect ';' after '{}'.", keyword.lexeme),
+        )?;
+
+        //Reported without abandoning the statement, nothing after it is misparsed
+        if self.loops.is_empty() {
+            self.errors.push(ParserError {
+                source: keyword.clone(),
+                cause: format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+                code: Some(&codes::INVALID_JUMP),
+            });
+        } else if let Some(ref label) = label
+            && !self.loops.contains(&Some(label.lexeme.clone()))
+        {
+            self.errors.push(ParserError {
+                source: label.clone(),
+                cause: format!("No enclosing loop is labeled '{}'.", label.lexeme),
+                code: Some(&codes::INVALID_JUMP),
+            });
+        }
+
+        Ok(if keyword.token_type == TokenType::Break {
+            Statement::Break(BreakStatement { keyword, label })
+        } else {
+            Statement::Continue(ContinueStatement { keyword, label })
+        })
     }
 
     // This desugars into a while loop with statements outside it
-    fn for_statement(&mut self) -> Result<Statement, ParserError> {
-        let _ = self.consume(TokenType::LeftParen, "Expect '(' after for.");
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Statement, ParserError> {
+        let keyword: Token = self.previous();
+        self.consume(TokenType::LeftParen, "Expect '(' after for.")?;
+
+        if self.check(TokenType::Var)
+            && self.check_next(TokenType::Identifier)
+            && self.check_third(TokenType::In)
+        {
+            return self.for_in_statement(keyword, label);
+        }
 
         let initializer: Option<Statement> = if self.match_token_type(vec![TokenType::Semicolon]) {
             None
@@ -90,33 +156,23 @@ impl Parser {
             Some(self.expression()?)
         };
 
-        let _ = self.consume(TokenType::Semicolon, "Expect ';' after increment part.");
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
 
         let mut increment: Option<Expression> = None;
         if !self.check(TokenType::RightParen) {
             increment = Some(self.expression()?);
         }
 
-        let _ = self.consume(TokenType::RightParen, "Expect ')' after condition block.");
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
-        let mut body = self.statement()?;
-
-        if increment.is_some() {
-            let statements: Vec<Statement> = vec![
-                body,
-                Statement::Expression(ExpressionStatement {
-                    expression: increment.unwrap(),
-                }),
-            ];
-
-            body = Statement::Block(BlockStatement { statements });
-        }
+        let body = self.loop_body(&label)?;
 
         //Is there a condition Present? if not just eval to true every time
         let condition = if condition.is_none() {
             Some(Expression::Literal(Box::new(
                 crate::types::expression::Literal {
                     value: LiteralType::Boolean(true),
+                    span: keyword.span,
                 },
             )))
         } else {
@@ -124,75 +180,174 @@ impl Parser {
         };
 
         //No Error path needed since all errors are pre-empted
-        body = Statement::While(WhileStatement {
+        let mut body = Statement::While(WhileStatement {
+            keyword,
+            label,
             condition: condition.expect("Condition has been set to None"),
             body: Box::new(body),
+            increment,
         });
 
-        if initializer.is_some() {
+        if let Some(initializer) = initializer {
+            let span = initializer.span().to(body.span());
             body = Statement::Block(BlockStatement {
-                statements: vec![initializer.unwrap(), body],
+                statements: vec![initializer, body],
+                span,
             });
         }
 
         Ok(body)
     }
 
+    fn for_in_statement(
+        &mut self,
+        keyword: Token,
+        label: Option<Token>,
+    ) -> Result<Statement, ParserError> {
+        //!Parses the rest of `for (var name in iterable) body`, ranges only exist here
+        self.advance();
+        let name = self.advance();
+        self.advance();
+
+        let start = self.expression()?;
+        let iterable = if self.match_token_type(vec![TokenType::DotDot, TokenType::DotDotEqual]) {
+            Iterable::Range {
+                start,
+                operator: self.previous(),
+                end: self.expression()?,
+            }
+        } else {
+            Iterable::Value(start)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for-in clause.")?;
+
+        let body = Box::new(self.loop_body(&label)?);
+        Ok(Statement::ForIn(ForInStatement {
+            keyword,
+            label,
+            name,
+            iterable,
+            body,
+        }))
+    }
+
     ///Evaluates the expression in the Syntax!
     fn expression_statement(&mut self) -> Result<Statement, ParserError> {
         let expression = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after expression")?;
         Ok(Statement::Expression(ExpressionStatement { expression }))
     }
 
     fn block_statement(&mut self) -> Result<Statement, ParserError> {
+        let left_brace = self.previous();
         let mut statements: Vec<Statement> = vec![];
         while !(self.check(TokenType::RightBrace) || self.is_at_end()) {
-            statements.push(self.declaration()?);
+            //A broken statement is already recorded, keep going to find any others in the block
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
 
-        let _ = self.consume(TokenType::RightBrace, "Expect '}' to match '{'.");
+        self.consume(TokenType::RightBrace, "Expect '}' to match '{'.")?;
 
-        Ok(Statement::Block(BlockStatement { statements }))
+        let span = left_brace.span.to(self.previous().span);
+        Ok(Statement::Block(BlockStatement { statements, span }))
     }
 
     fn statement(&mut self) -> Result<Statement, ParserError> {
         if self.match_token_type(vec![TokenType::If]) {
-            let returned = self.if_statement();
-            error_check!(returned);
-            returned
+            self.if_statement()
         }
         /* else if self.match_token_type(vec![TokenType::Print]) {
             self.print_statement()
         } */
         else if self.match_token_type(vec![TokenType::Return]) {
-            let returned = self.return_statement();
-            error_check!(returned);
-            returned
+            self.return_statement()
         } else if self.match_token_type(vec![TokenType::While]) {
-            let returned = self.while_statement();
-            error_check!(returned);
-            returned
+            self.while_statement(None)
         } else if self.match_token_type(vec![TokenType::For]) {
-            let returned = self.for_statement();
-            error_check!(returned);
-            returned
+            self.for_statement(None)
+        } else if self.match_token_type(vec![TokenType::Break, TokenType::Continue]) {
+            self.jump_statement()
+        } else if self.match_token_type(vec![TokenType::Try]) {
+            self.try_statement()
+        } else if self.match_token_type(vec![TokenType::Throw]) {
+            self.throw_statement()
+        } else if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+            self.labeled_statement()
         } else if self.match_token_type(vec![TokenType::LeftBrace]) {
-            let returned = self.block_statement();
-            error_check!(returned);
-            returned
+            self.block_statement()
         } else {
-            let returned = self.expression_statement();
-            error_check!(returned);
-            returned
+            self.expression_statement()
         }
     }
 
-    fn variable_decalration(&mut self) -> Result<Statement, ParserError> {
-        let name = self.consume(TokenType::Identifier, "Expected Identifier for Variable");
-        error_check!(name);
+    fn try_statement(&mut self) -> Result<Statement, ParserError> {
+        let keyword = self.previous();
+        let body = self.clause_body("try")?;
+
+        let catch = if self.match_token_type(vec![TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after catch.")?;
+            let name =
+                self.consume(TokenType::Identifier, "Expect a name for the caught value.")?;
+            self.consume(
+                TokenType::RightParen,
+                "Expect ')' after the caught value's name.",
+            )?;
+            Some(CatchClause {
+                name,
+                body: self.clause_body("catch")?,
+            })
+        } else {
+            None
+        };
+
+        let finally = if self.match_token_type(vec![TokenType::Finally]) {
+            Some(self.clause_body("finally")?)
+        } else {
+            None
+        };
 
-        //Error was handeled at runtime, we can now expect the name to be present
-        let name = name.expect("Variable Identifier is erroring");
+        if catch.is_none() && finally.is_none() {
+            return Err(ParserError {
+                source: self.peek(),
+                cause: String::from("Expect 'catch' or 'finally' after try block."),
+                code: Some(&codes::EXPECTED_HANDLER),
+            });
+        }
+
+        Ok(Statement::Try(TryStatement {
+            span: keyword.span.to(self.previous().span),
+            keyword,
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    fn clause_body(&mut self, clause: &str) -> Result<Vec<Statement>, ParserError> {
+        //!Parses the braces every part of a `try` needs, even for a single statement
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' after {clause}."),
+        )?;
+        match self.block_statement()? {
+            Statement::Block(block) => Ok(block.statements),
+            _ => unreachable!("block_statement always produces a block"),
+        }
+    }
+
+    fn throw_statement(&mut self) -> Result<Statement, ParserError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after thrown value")?;
+
+        Ok(Statement::Throw(ThrowStatement { keyword, value }))
+    }
+
+    fn variable_decalration(&mut self) -> Result<Statement, ParserError> {
+        let keyword = self.previous();
+        let name = self.consume(TokenType::Identifier, "Expected Identifier for Variable")?;
 
         let initalizer: Option<Expression> = if self.match_token_type(vec![TokenType::Equal]) {
             Some(self.expression()?)
@@ -200,56 +355,54 @@ impl Parser {
             None
         };
 
-        let consumed = self.consume(TokenType::Semicolon, "Expected \";\" following statement");
-        // println!("{:?}", consumed);
-        // This allows for loops to have assignment statements inside their heads without the
-        // parser throwing a fit at the user
-        if let Err(error) = consumed
-            && error.source.token_type != TokenType::For
-            && error.source.lexeme.to_lowercase() != "print"
-        {
-            Parser::error(error.source, &error.cause);
-        }
+        //Also ends the initializer clause of a `for`, so no special casing is needed there
+        self.consume(TokenType::Semicolon, "Expected \";\" following statement")?;
 
-        let statement = VariableStatement { name, initalizer };
+        let statement = VariableStatement {
+            keyword,
+            name,
+            initalizer,
+        };
 
         Ok(Statement::Variable(statement))
     }
 
-    fn function_declaration(&mut self, kind: String) -> Result<Statement, ParserError> {
-        let mut name: Result<Token, ParserError> =
-            self.consume(TokenType::Identifier, &format!("Expect {kind} name"));
-        //Grab token and check if it's a left paren, if so treat as anonymous, otherwise send the
-        //error back up
-        if let Err(err) = name {
-            if err.source.token_type == TokenType::LeftParen {
-                //Anonymous function name scheme
-                name = Ok(Parser::get_random_name_token(err.source));
-            } else {
-                return Err(err);
-            }
-        }
-        //Either we will have a name after getting through or we won't
-        let name = name.expect("Expected Name after checks");
+    fn function_declaration(
+        &mut self,
+        keyword: Option<Token>,
+        kind: String,
+    ) -> Result<Statement, ParserError> {
+        let name = self.consume(TokenType::Identifier, &format!("Expect {kind} name"))?;
+        let (parameters, body) = self.function_signature_and_body(&kind)?;
+
+        let start = keyword.as_ref().unwrap_or(&name).span;
+        Ok(Statement::Function(FunctionStatement {
+            span: start.to(self.previous().span),
+            keyword,
+            name,
+            body,
+            parameters,
+        }))
+    }
+
+    ///Parses `(params) { body }`, shared between named functions, methods and lambdas
+    pub(crate) fn function_signature_and_body(
+        &mut self,
+        kind: &str,
+    ) -> Result<(Vec<Token>, Vec<Statement>), ParserError> {
         let mut parameters: Vec<Token> = vec![];
 
-        let _ = self.consume(TokenType::LeftParen, &format!("Expect ( after {kind}"));
+        self.consume(TokenType::LeftParen, &format!("Expect ( after {kind}"))?;
         if !self.match_token_type(vec![TokenType::RightParen]) {
             while {
                 if parameters.len() + 1 > u64::MAX.try_into().unwrap() {
-                    Parser::error(
-                        self.peek(),
-                        &format!(
-                            "Cannot have more than {} parameters in a function",
-                            u64::MAX
-                        ),
-                    );
                     return Err(ParserError {
                         source: self.peek(),
                         cause: format!(
                             "Cannot have more than {} parameters in a function",
                             u64::MAX
                         ),
+                        code: None,
                     });
                 }
                 parameters.push(self.consume(TokenType::Identifier, "Expected Parameter name")?);
@@ -260,12 +413,14 @@ impl Parser {
                 &format!("Expected ) after parameters for {kind}"),
             )?;
         }
-        let consumed = self.consume(
+        self.consume(
             TokenType::LeftBrace,
-            "Expected \'{\' after function statement",
-        );
-        error_check!(consumed);
-        let body = self.block_statement()?;
+            &format!("Expected \'{{\' before {kind} body"),
+        )?;
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let body = self.block_statement();
+        self.loops = enclosing_loops;
+        let body = body?;
         let body: Vec<Statement> = match body {
             Statement::Block(block) => block.statements,
             _ => {
@@ -273,54 +428,71 @@ impl Parser {
                 vec![]
             }
         };
-        Ok(Statement::Function(FunctionStatement {
-            name,
-            body,
-            parameters,
-        }))
+
+        Ok((parameters, body))
     }
 
-    pub(crate) fn declaration(&mut self) -> Result<Statement, ParserError> {
-        if self.match_token_type(vec![TokenType::Fun]) {
-            self.function_declaration(String::from("function"))
-        } else if self.match_token_type(vec![TokenType::Var]) {
-            let result = self.variable_decalration();
+    fn class_declaration(&mut self) -> Result<Statement, ParserError> {
+        let keyword = self.previous();
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
 
-            if let Err(err) = result {
-                println!("{err}");
-                self.synchronize();
-                return Err(err);
-            }
-
-            result
+        let superclass: Option<Expression> = if self.match_token_type(vec![TokenType::Less]) {
+            let superclass_name = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            Some(Expression::Variable(Box::new(
+                crate::types::expression::Variable {
+                    name: superclass_name,
+                    binding: None,
+                },
+            )))
         } else {
-            let result = self.statement();
+            None
+        };
 
-            if let Err(err) = result {
-                println!("{err}");
-                self.synchronize();
-                return Err(err);
-            }
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
 
-            result
+        let mut methods: Vec<FunctionStatement> = vec![];
+        while !(self.check(TokenType::RightBrace) || self.is_at_end()) {
+            //Methods are declared like functions, just without the leading `fun`
+            match self.function_declaration(None, String::from("method"))? {
+                Statement::Function(method) => methods.push(method),
+                _ => unreachable!("function_declaration always produces a function"),
+            }
         }
+
+        let right_brace = self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Statement::Class(ClassStatement {
+            span: keyword.span.to(right_brace.span),
+            keyword,
+            name,
+            superclass,
+            methods,
+        }))
     }
 
-    fn get_random_name_token(token: Token) -> Token {
-        let mut rand = rand_chacha::ChaCha8Rng::seed_from_u64(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Failure converting from SystemTime")
-                .as_nanos() as u64,
-        );
-        let string_buf: &mut [u8] = &mut [0; 4];
-        rand.fill_bytes(string_buf);
-        let name: String = String::from_utf8_lossy(string_buf).to_string();
-        Token {
-            token_type: TokenType::Identifier,
-            lexeme: name,
-            literal: None,
-            line: token.line,
+    ///Parses one declaration, on a syntax error the error is recorded and the parser skips to
+    ///the next statement, handing back nothing
+    pub(crate) fn declaration(&mut self) -> Option<Statement> {
+        let start = self.current;
+        let result = if self.match_token_type(vec![TokenType::Class]) {
+            self.class_declaration()
+        } else if self.check(TokenType::Fun) && self.check_next(TokenType::Identifier) {
+            //A `fun` without a name is a lambda, which is parsed as an expression
+            let keyword = self.advance();
+            self.function_declaration(Some(keyword), String::from("function"))
+        } else if self.match_token_type(vec![TokenType::Var]) {
+            self.variable_decalration()
+        } else {
+            self.statement()
+        };
+
+        match result {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.errors.push(error);
+                self.synchronize(start);
+                None
+            }
         }
     }
 }
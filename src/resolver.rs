@@ -0,0 +1,506 @@
+use crate::{
+    diagnostics::codes::{
+        self,
+        ErrorCode,
+    },
+    interpreter::{
+        InterpreterVisitor,
+        Visitable,
+    },
+    parser::statement::{
+        StatementVisitor,
+        Visitable as StatementVisitable,
+    },
+    types::{
+        Expression,
+        ParserError,
+        expression::{
+            Call,
+            *,
+        },
+        statement::*,
+        token::Token,
+    },
+};
+use std::collections::HashMap;
+
+///What kind of function body the resolver is currently inside of
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+///What kind of class body the resolver is currently inside of
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+///A local the resolver has seen, `defined` is false while its initializer is being resolved
+struct Local {
+    slot: usize,
+    defined: bool,
+}
+
+///Static pass run between the Parser and the Interpreter, binds every local variable use to the
+///enviroment and slot it will live in at runtime and rejects programs that misuse scope
+pub(crate) struct Resolver {
+    scopes: Vec<HashMap<String, Local>>,
+    function_type: FunctionType,
+    class_type: ClassType,
+    errors: Vec<ParserError>,
+}
+
+impl Resolver {
+    pub(crate) fn new() -> Resolver {
+        Resolver {
+            scopes: vec![],
+            function_type: FunctionType::None,
+            class_type: ClassType::None,
+            errors: vec![],
+        }
+    }
+
+    ///Resolves a whole program, handing back every error found rather than stopping at the first
+    pub(crate) fn resolve(mut self, statements: &mut [Statement]) -> Result<(), Vec<ParserError>> {
+        self.resolve_statements(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &mut Expression) {
+        expression.accept(self);
+    }
+
+    fn error(&mut self, token: &Token, code: &'static ErrorCode, message: &str) {
+        self.errors.push(ParserError {
+            source: token.clone(),
+            cause: message.to_string(),
+            code: Some(code),
+        });
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        //Globals are late bound, so they are free to be redeclared
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(
+                name,
+                &codes::ALREADY_DECLARED,
+                &format!("'{}' is already declared in this scope.", name.lexeme),
+            );
+            return;
+        }
+
+        let slot = scope.len();
+        scope.insert(
+            name.lexeme.clone(),
+            Local {
+                slot,
+                defined: false,
+            },
+        );
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme))
+        {
+            local.defined = true;
+        }
+    }
+
+    ///Declares a name the interpreter injects itself, like `this` and `super`
+    fn define_implicit(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let slot = scope.len();
+            scope.insert(
+                name.to_string(),
+                Local {
+                    slot,
+                    defined: true,
+                },
+            );
+        }
+    }
+
+    ///Walks the scopes from innermost out, `None` means the name is left to the globals
+    fn resolve_local(&self, name: &str) -> Option<Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                scope.get(name).map(|local| Binding {
+                    depth,
+                    slot: local.slot,
+                })
+            })
+    }
+
+    fn resolve_function(
+        &mut self,
+        parameters: &[Token],
+        body: &mut [Statement],
+        function_type: FunctionType,
+    ) {
+        let enclosing_function = self.function_type;
+        self.function_type = function_type;
+
+        //Parameters and the body share one scope, matching the enviroment a call creates
+        self.begin_scope();
+        for parameter in parameters {
+            self.declare(parameter);
+            self.define(parameter);
+        }
+        self.resolve_statements(body);
+        self.end_scope();
+
+        self.function_type = enclosing_function;
+    }
+}
+
+impl StatementVisitor<()> for Resolver {
+    fn visit_expression_statement(&mut self, expression: &mut ExpressionStatement) {
+        self.resolve_expression(&mut expression.expression);
+    }
+
+    fn visit_variable_statement(&mut self, var: &mut VariableStatement) {
+        self.declare(&var.name);
+        if let Some(ref mut initalizer) = var.initalizer {
+            self.resolve_expression(initalizer);
+        }
+        self.define(&var.name);
+    }
+
+    fn visit_if_statement(&mut self, if_statement: &mut IfStatement) {
+        self.resolve_expression(&mut if_statement.condition);
+        if_statement.then_branch.accept(self);
+        if let Some(ref mut else_branch) = *if_statement.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &mut WhileStatement) {
+        self.resolve_expression(&mut while_statement.condition);
+        while_statement.body.accept(self);
+        if let Some(ref mut increment) = while_statement.increment {
+            self.resolve_expression(increment);
+        }
+    }
+
+    fn visit_for_in_statement(&mut self, for_in: &mut ForInStatement) {
+        //The iterable is evaluated once, before the loop variable exists
+        match for_in.iterable {
+            Iterable::Value(ref mut iterable) => self.resolve_expression(iterable),
+            Iterable::Range {
+                ref mut start,
+                ref mut end,
+                ..
+            } => {
+                self.resolve_expression(start);
+                self.resolve_expression(end);
+            }
+        }
+
+        self.begin_scope();
+        self.declare(&for_in.name);
+        self.define(&for_in.name);
+        for_in.body.accept(self);
+        self.end_scope();
+    }
+
+    //Jumps only name loops, which the parser already checked
+    fn visit_break_statement(&mut self, _break_statement: &mut BreakStatement) {}
+
+    fn visit_continue_statement(&mut self, _continue_statement: &mut ContinueStatement) {}
+
+    fn visit_try_statement(&mut self, try_statement: &mut TryStatement) {
+        //Each clause runs in its own enviroment, the caught value is the catch's first slot
+        self.begin_scope();
+        self.resolve_statements(&mut try_statement.body);
+        self.end_scope();
+
+        if let Some(ref mut catch) = try_statement.catch {
+            self.begin_scope();
+            self.declare(&catch.name);
+            self.define(&catch.name);
+            self.resolve_statements(&mut catch.body);
+            self.end_scope();
+        }
+
+        if let Some(ref mut finally) = try_statement.finally {
+            self.begin_scope();
+            self.resolve_statements(finally);
+            self.end_scope();
+        }
+    }
+
+    fn visit_throw_statement(&mut self, throw: &mut ThrowStatement) {
+        self.resolve_expression(&mut throw.value);
+    }
+
+    fn visit_block_statement(&mut self, block_statement: &mut BlockStatement) {
+        self.begin_scope();
+        self.resolve_statements(&mut block_statement.statements);
+        self.end_scope();
+    }
+
+    fn visit_function_statement(&mut self, function_statement: &mut FunctionStatement) {
+        //Defined before the body so the function can call itself
+        self.declare(&function_statement.name);
+        self.define(&function_statement.name);
+
+        self.resolve_function(
+            &function_statement.parameters,
+            &mut function_statement.body,
+            FunctionType::Function,
+        );
+    }
+
+    fn visit_return_statement(&mut self, return_statement: &mut ReturnStatement) {
+        if self.function_type == FunctionType::None {
+            self.error(
+                &return_statement.keyword,
+                &codes::INVALID_RETURN,
+                "Can't return from top-level code.",
+            );
+        }
+
+        if let Some(ref mut value) = return_statement.value {
+            if self.function_type == FunctionType::Initializer {
+                self.error(
+                    &return_statement.keyword,
+                    &codes::INVALID_RETURN,
+                    "Can't return a value from an initializer.",
+                );
+            }
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_class_statement(&mut self, class_statement: &mut ClassStatement) {
+        let enclosing_class = self.class_type;
+        self.class_type = ClassType::Class;
+
+        self.declare(&class_statement.name);
+        self.define(&class_statement.name);
+
+        if let Some(ref mut superclass) = class_statement.superclass {
+            if let Expression::Variable(ref variable) = *superclass
+                && variable.name.lexeme == class_statement.name.lexeme
+            {
+                self.error(
+                    &variable.name,
+                    &codes::INVALID_SUPERCLASS,
+                    "A class can't inherit from itself.",
+                );
+            }
+
+            self.class_type = ClassType::Subclass;
+            self.resolve_expression(superclass);
+
+            //Mirrors the enviroment holding `super` that the interpreter wraps methods in
+            self.begin_scope();
+            self.define_implicit("super");
+        }
+
+        //Mirrors the enviroment holding `this` that binding a method creates
+        self.begin_scope();
+        self.define_implicit("this");
+
+        for method in &mut class_statement.methods {
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(&method.parameters, &mut method.body, function_type);
+        }
+
+        self.end_scope();
+
+        if class_statement.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.class_type = enclosing_class;
+    }
+}
+
+impl InterpreterVisitor<()> for Resolver {
+    fn visit_grouping(&mut self, group: &mut Grouping) {
+        self.resolve_expression(&mut group.expression);
+    }
+
+    fn visit_binary(&mut self, bin: &mut Binary) {
+        self.resolve_expression(&mut bin.left);
+        self.resolve_expression(&mut bin.right);
+    }
+
+    fn visit_unary(&mut self, unary: &mut Unary) {
+        self.resolve_expression(&mut unary.operand);
+    }
+
+    fn visit_literal(&mut self, _lit: &mut Literal) {}
+
+    fn visit_ternary(&mut self, tern: &mut Ternary) {
+        self.resolve_expression(&mut tern.evaluator);
+        self.resolve_expression(&mut tern.left);
+        self.resolve_expression(&mut tern.right);
+    }
+
+    fn visit_variable(&mut self, var: &mut Variable) {
+        if let Some(scope) = self.scopes.last()
+            && let Some(local) = scope.get(&var.name.lexeme)
+            && !local.defined
+        {
+            self.error(
+                &var.name,
+                &codes::OWN_INITIALIZER,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+
+        var.binding = self.resolve_local(&var.name.lexeme);
+    }
+
+    fn visit_assignment(&mut self, assign: &mut Assignment) {
+        self.resolve_expression(&mut assign.value);
+        assign.binding = self.resolve_local(&assign.name.lexeme);
+    }
+
+    fn visit_logical(&mut self, logical: &mut Logical) {
+        self.resolve_expression(&mut logical.left);
+        self.resolve_expression(&mut logical.right);
+    }
+
+    fn visit_call(&mut self, call: &mut Call) {
+        self.resolve_expression(&mut call.callee);
+        for argument in &mut call.arguments {
+            self.resolve_expression(argument);
+        }
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda) {
+        self.resolve_function(&lambda.parameters, &mut lambda.body, FunctionType::Function);
+    }
+
+    fn visit_interpolation(&mut self, interpolation: &mut Interpolation) {
+        for part in &mut interpolation.parts {
+            self.resolve_expression(part);
+        }
+    }
+
+    fn visit_list(&mut self, list: &mut List) {
+        for element in &mut list.elements {
+            self.resolve_expression(element);
+        }
+    }
+
+    fn visit_map(&mut self, map: &mut Map) {
+        for (key, value) in &mut map.entries {
+            self.resolve_expression(key);
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_index(&mut self, index: &mut Index) {
+        self.resolve_expression(&mut index.object);
+        self.resolve_expression(&mut index.index);
+    }
+
+    fn visit_setindex(&mut self, set: &mut SetIndex) {
+        self.resolve_expression(&mut set.object);
+        self.resolve_expression(&mut set.index);
+        self.resolve_expression(&mut set.value);
+    }
+
+    fn visit_compound(&mut self, compound: &mut Compound) {
+        //The target is read as well as written, so it resolves like any other expression
+        self.resolve_expression(&mut compound.target);
+        self.resolve_expression(&mut compound.value);
+    }
+
+    fn visit_slice(&mut self, slice: &mut Slice) {
+        self.resolve_expression(&mut slice.object);
+        for bound in [&mut slice.start, &mut slice.end].into_iter().flatten() {
+            self.resolve_expression(bound);
+        }
+    }
+
+    fn visit_get(&mut self, get: &mut Get) {
+        //Properties are looked up dynamically, only the object needs resolving
+        self.resolve_expression(&mut get.object);
+    }
+
+    fn visit_set(&mut self, set: &mut Set) {
+        self.resolve_expression(&mut set.value);
+        self.resolve_expression(&mut set.object);
+    }
+
+    fn visit_this(&mut self, this: &mut This) {
+        if self.class_type == ClassType::None {
+            self.error(
+                &this.keyword,
+                &codes::INVALID_THIS,
+                "Can't use 'this' outside of a class.",
+            );
+            return;
+        }
+
+        this.binding = self.resolve_local("this");
+    }
+
+    fn visit_super(&mut self, sup: &mut Super) {
+        match self.class_type {
+            ClassType::None => {
+                self.error(
+                    &sup.keyword,
+                    &codes::INVALID_SUPER,
+                    "Can't use 'super' outside of a class.",
+                );
+                return;
+            }
+            ClassType::Class => {
+                self.error(
+                    &sup.keyword,
+                    &codes::INVALID_SUPER,
+                    "Can't use 'super' in a class with no superclass.",
+                );
+                return;
+            }
+            ClassType::Subclass => {}
+        }
+
+        sup.binding = self.resolve_local("super");
+    }
+}
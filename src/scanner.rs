@@ -1,4 +1,8 @@
 use crate::{
+    diagnostics::{
+        Diagnostic,
+        codes,
+    },
     macros::{
         new_character,
         new_literal,
@@ -6,19 +10,51 @@ use crate::{
     types::{
         LiteralType,
         TokenType,
+        span::{
+            FileId,
+            Span,
+        },
         token::Token,
     },
 };
 
 pub struct Scanner<'scanner> {
     source: &'scanner str,
+    file: FileId,
+    ///Byte offset the token being scanned began at
+    start: u32,
     current: u32,
     pub line: u32,
+    ///Byte offset the current line began at, columns are measured from here
+    line_start: u32,
+    ///Line and column of `start`, multi-line tokens are reported where they began
+    start_line: u32,
+    start_column: u32,
+    ///Reported together with the parser's errors, so everything comes out in source order
+    pub(crate) errors: Vec<Diagnostic>,
+    ///Strings whose `${` is still open, innermost last
+    interpolations: Vec<Interpolation>,
+}
+
+///How the string being read was opened, remembered across `${..}` so the rest reads the same way
+#[derive(Clone, Copy)]
+struct StringKind {
+    multiline: bool,
+    raw: bool,
+    ///Indentation stripped from each line of a multiline string
+    indent: usize,
+}
+
+///A `${` whose closing `}` hasn't been reached yet
+struct Interpolation {
+    kind: StringKind,
+    ///Braces opened inside the embedded expression, like a block in a lambda, that are still open
+    braces: u32,
 }
 
 #[allow(clippy::manual_range_contains)]
 impl Scanner<'_> {
-    pub fn new(source: &str, current: Option<u32>, line: Option<u32>) -> Scanner<'_> {
+    pub fn new(source: &str, file: FileId, current: Option<u32>, line: Option<u32>) -> Scanner<'_> {
         let mut current = current;
         if current.is_none() {
             current = Some(0);
@@ -29,13 +65,63 @@ impl Scanner<'_> {
             line = Some(1);
         }
 
+        let current = current.expect("No number given");
+        let line = line.expect("No number given");
         Scanner {
             source,
-            current: current.expect("No number given"),
-            line: line.expect("No number given"),
+            file,
+            start: current,
+            current,
+            line,
+            line_start: current,
+            start_line: line,
+            start_column: 1,
+            errors: vec![],
+            interpolations: vec![],
+        }
+    }
+
+    ///Span from the start of the token being scanned up to the cursor
+    fn span(&self) -> Span {
+        Span {
+            file: self.file,
+            start: self.start,
+            end: self.current,
+            line: self.start_line,
+            column: self.start_column,
+        }
+    }
+
+    ///Span from `start` up to the cursor, for pointing inside the token being scanned
+    fn span_from(&self, start: u32) -> Span {
+        let column = self.source[self.line_start as usize..start as usize]
+            .chars()
+            .count();
+        Span {
+            start,
+            line: self.line,
+            column: u32::try_from(column).expect("Line too long to span") + 1,
+            ..self.span()
         }
     }
 
+    ///Marks the cursor as the start of the next token
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        //Columns count characters, not bytes, so they match what an editor shows
+        let column = self.source[self.line_start as usize..self.current as usize]
+            .chars()
+            .count();
+        self.start_column = u32::try_from(column).expect("Line too long to span") + 1;
+    }
+
+    ///Moves onto the next line, the newline itself must already be consumed
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     //Advance the cursor then return resulting token
     fn scan_token(&mut self) -> Option<Token> {
         //!Basically a shitty hashmap, but too much time invested to make in a functional style,
@@ -46,7 +132,7 @@ impl Scanner<'_> {
                 None
             }
             '\n' => {
-                self.line += 1;
+                self.newline();
                 None
             }
             '/' => {
@@ -57,69 +143,143 @@ impl Scanner<'_> {
                 } else if self.peek() == '/' {
                     self.single_line_comment();
                     None
+                } else if self.peek() == '=' {
+                    let _ = self.advance();
+                    new_character!(TokenType::SlashEqual, "/=", self.span())
                 } else {
                     //Literal Slash
-                    new_character!(TokenType::Slash, "/", self.line)
+                    new_character!(TokenType::Slash, "/", self.span())
+                }
+            }
+            '(' => new_character!(TokenType::LeftParen, "(", self.span()),
+            ')' => new_character!(TokenType::RightParen, ")", self.span()),
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.braces += 1;
+                }
+                new_character!(TokenType::LeftBrace, "{", self.span())
+            }
+            '}' => match self.interpolations.last_mut() {
+                //Ends an embedded expression, so the rest is more of the string
+                Some(interpolation) if interpolation.braces == 0 => {
+                    let kind = interpolation.kind;
+                    self.interpolations.pop();
+                    self.string_part(kind, false)
+                }
+                Some(interpolation) => {
+                    interpolation.braces -= 1;
+                    new_character!(TokenType::RightBrace, "}", self.span())
+                }
+                None => new_character!(TokenType::RightBrace, "}", self.span()),
+            },
+            '[' => new_character!(TokenType::LeftBracket, "[", self.span()),
+            ']' => new_character!(TokenType::RightBracket, "]", self.span()),
+            '.' => {
+                if self.peek() == '.' {
+                    let _ = self.advance();
+                    if self.peek() == '=' {
+                        let _ = self.advance();
+                        new_character!(TokenType::DotDotEqual, "..=", self.span())
+                    } else {
+                        new_character!(TokenType::DotDot, "..", self.span())
+                    }
+                } else {
+                    new_character!(TokenType::Dot, ".", self.span())
+                }
+            }
+            '-' => {
+                if self.peek() == '-' {
+                    let _ = self.advance();
+                    new_character!(TokenType::MinusMinus, "--", self.span())
+                } else if self.peek() == '=' {
+                    let _ = self.advance();
+                    new_character!(TokenType::MinusEqual, "-=", self.span())
+                } else {
+                    new_character!(TokenType::Minus, "-", self.span())
+                }
+            }
+            '+' => {
+                if self.peek() == '+' {
+                    let _ = self.advance();
+                    new_character!(TokenType::PlusPlus, "++", self.span())
+                } else if self.peek() == '=' {
+                    let _ = self.advance();
+                    new_character!(TokenType::PlusEqual, "+=", self.span())
+                } else {
+                    new_character!(TokenType::Plus, "+", self.span())
                 }
             }
-            '(' => new_character!(TokenType::LeftParen, "(", self.line),
-            ')' => new_character!(TokenType::RightParen, ")", self.line),
-            '{' => new_character!(TokenType::LeftBrace, "{", self.line),
-            '}' => new_character!(TokenType::RightBrace, "}", self.line),
-            '.' => new_character!(TokenType::Dot, ".", self.line),
-            '-' => new_character!(TokenType::Minus, "-", self.line),
-            '+' => new_character!(TokenType::Plus, "+", self.line),
-            ';' => new_character!(TokenType::Semicolon, ";", self.line),
-            '*' => new_character!(TokenType::Star, "*", self.line),
-            '?' => new_character!(TokenType::Question, "?", self.line),
-            ':' => new_character!(TokenType::Colon, ":", self.line),
-            ',' => new_character!(TokenType::Comma, ",", self.line),
+            ';' => new_character!(TokenType::Semicolon, ";", self.span()),
+            '*' => {
+                if self.peek() == '*' {
+                    let _ = self.advance();
+                    new_character!(TokenType::StarStar, "**", self.span())
+                } else if self.peek() == '=' {
+                    let _ = self.advance();
+                    new_character!(TokenType::StarEqual, "*=", self.span())
+                } else {
+                    new_character!(TokenType::Star, "*", self.span())
+                }
+            }
+            '%' => new_character!(TokenType::Percent, "%", self.span()),
+            '?' => new_character!(TokenType::Question, "?", self.span()),
+            ':' => new_character!(TokenType::Colon, ":", self.span()),
+            ',' => new_character!(TokenType::Comma, ",", self.span()),
             '!' => {
                 if self.peek() == '=' {
                     //Discard and return combo character
                     let _ = self.advance();
-                    new_character!(TokenType::BangEqual, "!=", self.line)
+                    new_character!(TokenType::BangEqual, "!=", self.span())
                 } else {
-                    new_character!(TokenType::Bang, "!", self.line)
+                    new_character!(TokenType::Bang, "!", self.span())
                 }
             }
             '=' => {
                 if self.peek() == '=' {
                     //Discard and return combo character
                     let _ = self.advance();
-                    new_character!(TokenType::EqualEqual, "==", self.line)
+                    new_character!(TokenType::EqualEqual, "==", self.span())
                 } else {
-                    new_character!(TokenType::Equal, "=", self.line)
+                    new_character!(TokenType::Equal, "=", self.span())
                 }
             }
             '<' => {
                 if self.peek() == '=' {
                     //Discard and return combo character
                     let _ = self.advance();
-                    new_character!(TokenType::LessEqual, "<=", self.line)
+                    new_character!(TokenType::LessEqual, "<=", self.span())
                 } else {
-                    new_character!(TokenType::Less, "<", self.line)
+                    new_character!(TokenType::Less, "<", self.span())
                 }
             }
             '>' => {
                 if self.peek() == '=' {
                     //Discard "=" and return combo character
                     let _ = self.advance();
-                    new_character!(TokenType::GreaterEqual, ">=", self.line)
+                    new_character!(TokenType::GreaterEqual, ">=", self.span())
                 } else {
                     //We don't advance here so the scanner can pull it in as something else on the
                     //next iteration
-                    new_character!(TokenType::Greater, ">", self.line)
+                    new_character!(TokenType::Greater, ">", self.span())
                 }
             }
 
-            'a'..='z' | 'A'..='Z' | '_' => self.keywords(),
-
             '0'..='9' => self.numbers(),
 
-            '"' => self.strings(),
+            '"' => self.strings(false),
+
+            'r' if self.peek() == '"' => {
+                self.advance();
+                self.strings(true)
+            }
+
+            chara if is_identifier_start(chara) => self.keywords(),
             chara => {
-                crate::error(self.line, format!("Unexpected Token: '{chara}'"));
+                self.errors.push(
+                    Diagnostic::error(format!("Unexpected Token: '{chara}'"), self.span())
+                        .with_code(Some(&codes::UNEXPECTED_CHARACTER))
+                        .with_label("not valid here"),
+                );
                 None
             }
         }
@@ -130,12 +290,21 @@ impl Scanner<'_> {
         let mut in_comment = true;
         let mut current_char = self.advance();
         while in_comment {
+            if self.is_at_end() {
+                self.errors.push(
+                    Diagnostic::error("Unterminated block comment.", self.span())
+                        .with_code(Some(&codes::UNTERMINATED_COMMENT))
+                        .with_label("comment starts here")
+                        .with_help("close it with a `*/`"),
+                );
+                return;
+            }
             if current_char == '*' && self.peek() == '/' {
                 in_comment = false;
             } else {
                 current_char = self.advance();
                 if current_char == '\n' {
-                    self.line += 1;
+                    self.newline();
                 }
             }
         }
@@ -147,193 +316,499 @@ impl Scanner<'_> {
         let mut in_comment = true;
         let _ = self.advance();
         while in_comment {
+            //A comment on the last line may not have a newline to end it
+            if self.is_at_end() {
+                return;
+            }
             let current_char = self.advance();
             if current_char == '\n' {
                 in_comment = false;
             }
         }
-        self.line += 1;
+        self.newline();
     }
 
-    fn strings(&mut self) -> Option<Token> {
-        //!Evaluate strings and handle funky cases
+    fn strings(&mut self, raw: bool) -> Option<Token> {
+        //!Evaluate strings and handle funky cases. The opening `"` is already consumed, `"""`
+        //!opens a multiline string and raw strings keep every backslash as written
+        let multiline = self.peek() == '"' && self.peek_next() == '"';
+        let mut kind = StringKind {
+            multiline,
+            raw,
+            indent: 0,
+        };
+        if !multiline {
+            return self.string_part(kind, false);
+        }
+
+        //Over the other two quotes
+        self.advance();
+        self.advance();
+        kind.indent = self.shared_indent(raw);
+
+        //A line break straight after the opening quotes isn't part of the string
+        let rest = &self.source[self.current as usize..];
+        if let Some(line_end) = rest.find('\n')
+            && rest[..line_end].trim().is_empty()
+        {
+            self.current += line_end as u32 + 1;
+            self.newline();
+            return self.string_part(kind, true);
+        }
+        self.string_part(kind, false)
+    }
+
+    fn shared_indent(&self, raw: bool) -> usize {
+        //!How much indentation every line of the `"""` string at the cursor shares, worked out up
+        //!front so it can be stripped while reading, even across `${..}`
+        let rest = &self.source[self.current as usize..];
+        let mut end = rest.len();
+        let mut characters = rest.char_indices();
+        while let Some((index, character)) = characters.next() {
+            if character == '\\' && !raw {
+                characters.next();
+            } else if rest[index..].starts_with("\"\"\"") {
+                end = index;
+                break;
+            }
+        }
+
+        //The line holding the opening quotes is never indented, the one holding the closing quotes
+        //only counts if there is something before them
+        let mut lines: Vec<&str> = rest[..end].split('\n').skip(1).collect();
+        if lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.chars()
+                    .take_while(|character| matches!(character, ' ' | '\t'))
+                    .count()
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn string_part(&mut self, kind: StringKind, at_line_start: bool) -> Option<Token> {
+        //!Reads up to and over the closing quotes, or up to a `${`, decoding escapes unless the
+        //!string is raw. A multiline string loses `kind.indent` from each line and the line break
+        //!before its closing quotes
+        let closing = if kind.multiline { "\"\"\"" } else { "\"" };
         let mut result = String::from("");
-        let mut current_char: char;
+        let mut at_line_start = at_line_start;
+        //Where the current line's text begins in `result`, and how much indent it has lost so far
+        let mut line_begin: usize = 0;
+        let mut stripped = 0;
 
-        //while in_string
         loop {
-            current_char = self.advance();
-
-            //Escape Charaters & Endings
-            if current_char == '\\' {
-                let next_char = self.peek();
-                match next_char {
-                    '"' => {
-                        result.push(next_char);
-                        //Over the Literal "
-                        self.advance();
-                        continue;
-                    }
-                    'n' => {
-                        result.push('\n');
-                        self.advance();
-                        continue;
-                    }
-                    't' => {
-                        result.push('\t');
-                        self.advance();
-                        continue;
-                    }
-                    _ => {}
+            if self.source[self.current as usize..].starts_with(closing) {
+                self.current += closing.len() as u32;
+                if kind.multiline && at_line_start {
+                    result.truncate(line_begin.saturating_sub(1));
                 }
+                return new_literal!(
+                    TokenType::String,
+                    result.clone(),
+                    LiteralType::String(result),
+                    self.span()
+                );
             }
-            if (current_char == '"') && !self.is_at_end() {
-                //in_string = false;
-                break;
-            }
-
             if self.is_at_end() {
-                crate::error(self.line, String::from("Unterminated String"));
-                // in_string = false;
+                self.errors.push(
+                    Diagnostic::error("Unterminated String", self.span())
+                        .with_code(Some(&codes::UNTERMINATED_STRING))
+                        .with_label("string starts here")
+                        .with_help(format!("close it with a `{closing}`")),
+                );
                 return None;
             }
+            if !kind.raw && self.source[self.current as usize..].starts_with("${") {
+                self.current += 2;
+                //The `}` closing the embedded expression picks the string back up
+                self.interpolations.push(Interpolation { kind, braces: 0 });
+                return new_literal!(
+                    TokenType::Interpolation,
+                    result.clone(),
+                    LiteralType::String(result),
+                    self.span()
+                );
+            }
+
+            let char_start = self.current;
+            let current_char = self.advance();
+            match current_char {
+                '\n' => {
+                    self.newline();
+                    result.push(current_char);
+                    at_line_start = true;
+                    line_begin = result.len();
+                    stripped = 0;
+                }
+                '\\' if !kind.raw => {
+                    at_line_start = false;
+                    match self.escape(char_start) {
+                        Some(escaped) => result.push(escaped),
+                        //Keep going so the rest of the string is still checked
+                        None => {
+                            if self.source[..self.current as usize].ends_with('\n') {
+                                self.newline();
+                            }
+                        }
+                    }
+                }
+                ' ' | '\t' if at_line_start && stripped < kind.indent => stripped += 1,
+                ' ' | '\t' if at_line_start => result.push(current_char),
+                _ => {
+                    at_line_start = false;
+                    result.push(current_char);
+                }
+            }
+        }
+    }
 
-            //Add to result
-            result.push(current_char);
+    fn escape(&mut self, start: u32) -> Option<char> {
+        //!Decodes the escape sequence after an already consumed `\\` found at `start`, recording
+        //!an error if it isn't one
+        match self.decode_escape() {
+            Ok(escaped) => Some(escaped),
+            Err((label, help)) => {
+                let mut diagnostic =
+                    Diagnostic::error("Invalid escape sequence", self.span_from(start))
+                        .with_code(Some(&codes::INVALID_ESCAPE))
+                        .with_label(label);
+                if let Some(help) = help {
+                    diagnostic = diagnostic.with_help(help);
+                }
+                self.errors.push(diagnostic);
+                None
+            }
         }
+    }
 
-        new_literal!(
-            TokenType::String,
-            result.clone(),
-            LiteralType::String(result),
-            self.line
-        )
+    fn decode_escape(&mut self) -> Result<char, (String, Option<&'static str>)> {
+        //!The character an escape stands for, or why it doesn't stand for one
+        match self.advance() {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '\'' => Ok('\''),
+            '$' => Ok('$'),
+            //`\x41`, limited to ASCII so a single byte can't form half a character
+            'x' => {
+                let mut value = 0;
+                for _ in 0..2 {
+                    let Some(digit) = self.peek().to_digit(16) else {
+                        let label = String::from("`\\x` needs two hexadecimal digits");
+                        return Err((label, None));
+                    };
+                    self.advance();
+                    value = value * 16 + digit;
+                }
+                if value > 0x7F {
+                    let label = String::from(
+                        "`\\x` only goes up to `\\x7F`, use `\\u{..}` for anything else",
+                    );
+                    return Err((label, None));
+                }
+                Ok(char::from(value as u8))
+            }
+            //`\u{1F600}`, one to six hex digits naming any Unicode scalar value
+            'u' => {
+                if self.peek() != '{' {
+                    let label = String::from("`\\u` needs its digits in braces, like `\\u{1F600}`");
+                    return Err((label, None));
+                }
+                self.advance();
+                let mut value: u32 = 0;
+                let mut count = 0;
+                while let Some(digit) = self.peek().to_digit(16) {
+                    self.advance();
+                    value = value.saturating_mul(16).saturating_add(digit);
+                    count += 1;
+                }
+                if self.peek() != '}' {
+                    let label = String::from("`\\u{` is missing its closing `}`");
+                    return Err((label, None));
+                }
+                self.advance();
+                if count == 0 || count > 6 {
+                    let label = String::from("`\\u{..}` takes one to six hexadecimal digits");
+                    return Err((label, None));
+                }
+                char::from_u32(value)
+                    .ok_or_else(|| (format!("`{value:X}` is not a Unicode scalar value"), None))
+            }
+            '\0' => Err((String::from("string ends in the middle of an escape"), None)),
+            other => {
+                let label = format!("unknown escape `\\{other}`");
+                Err((label, Some("write `\\\\` for a literal backslash")))
+            }
+        }
     }
 
     fn keywords(&mut self) -> Option<Token> {
         //!Reads word until whitespace or something illegal in an identifier and checks if it is
         //!reserved, if not creates an indentifier token
-        let mut current_char: char = self.source.as_bytes()[(self.current - 1) as usize] as char;
-        let mut word_built = String::from("");
-
-        //Read in full word
-        while is_ascii_ident(current_char) && (self.current <= self.source.len() as u32) {
-            word_built.push(current_char);
-            self.current += 1;
-            current_char = self.source.as_bytes()[(self.current - 1) as usize] as char
+        //Read in full word, the first character is already consumed
+        while is_identifier_continue(self.peek()) {
+            self.advance();
         }
-        self.current -= 1;
+        let word_built = self.source[self.start as usize..self.current as usize].to_string();
         let matching = word_built.to_ascii_lowercase();
         //Match to keywords, if we don't have the keyword reserved, then its
         //probably a variable
         match matching.as_str() {
-            "and" => new_character!(TokenType::And, word_built.as_str(), self.line),
-            "class" => new_character!(TokenType::Class, word_built.as_str(), self.line),
-            "else" => new_character!(TokenType::Else, word_built.as_str(), self.line),
-            "fun" => new_character!(TokenType::Fun, word_built.as_str(), self.line),
-            "for" => new_character!(TokenType::For, word_built.as_str(), self.line),
-            "if" => new_character!(TokenType::If, word_built.as_str(), self.line),
-            "or" => new_character!(TokenType::Or, word_built.as_str(), self.line),
-            // "print" => new_character!(TokenType::Print, word_built.as_str(), self.line),
-            "return" => new_character!(TokenType::Return, word_built.as_str(), self.line),
-            "super" => new_character!(TokenType::Super, word_built.as_str(), self.line),
-            "this" => new_character!(TokenType::This, word_built.as_str(), self.line),
-            "var" => new_character!(TokenType::Var, word_built.as_str(), self.line),
-            "while" => new_character!(TokenType::While, word_built.as_str(), self.line),
+            "and" => new_character!(TokenType::And, word_built.as_str(), self.span()),
+            "break" => new_character!(TokenType::Break, word_built.as_str(), self.span()),
+            "catch" => new_character!(TokenType::Catch, word_built.as_str(), self.span()),
+            "class" => new_character!(TokenType::Class, word_built.as_str(), self.span()),
+            "continue" => new_character!(TokenType::Continue, word_built.as_str(), self.span()),
+            "else" => new_character!(TokenType::Else, word_built.as_str(), self.span()),
+            "finally" => new_character!(TokenType::Finally, word_built.as_str(), self.span()),
+            "fun" => new_character!(TokenType::Fun, word_built.as_str(), self.span()),
+            "for" => new_character!(TokenType::For, word_built.as_str(), self.span()),
+            "if" => new_character!(TokenType::If, word_built.as_str(), self.span()),
+            "in" => new_character!(TokenType::In, word_built.as_str(), self.span()),
+            "or" => new_character!(TokenType::Or, word_built.as_str(), self.span()),
+            // "print" => new_character!(TokenType::Print, word_built.as_str(), self.span()),
+            "return" => new_character!(TokenType::Return, word_built.as_str(), self.span()),
+            "super" => new_character!(TokenType::Super, word_built.as_str(), self.span()),
+            "this" => new_character!(TokenType::This, word_built.as_str(), self.span()),
+            "throw" => new_character!(TokenType::Throw, word_built.as_str(), self.span()),
+            "try" => new_character!(TokenType::Try, word_built.as_str(), self.span()),
+            "var" => new_character!(TokenType::Var, word_built.as_str(), self.span()),
+            "while" => new_character!(TokenType::While, word_built.as_str(), self.span()),
             "nil" => new_literal!(
                 TokenType::Nil,
                 word_built.as_str(),
                 LiteralType::Nil,
-                self.line
+                self.span()
             ),
             "false" => new_literal!(
                 TokenType::False,
                 word_built.as_str(),
                 LiteralType::Boolean(false),
-                self.line
+                self.span()
             ),
             "true" => new_literal!(
                 TokenType::True,
                 word_built.as_str(),
                 LiteralType::Boolean(true),
-                self.line
+                self.span()
             ),
-            _ => new_character!(TokenType::Identifier, word_built, self.line),
+            _ => new_character!(TokenType::Identifier, word_built, self.span()),
         }
     }
 
     fn numbers(&mut self) -> Option<Token> {
-        //! Evaluate numbers, which are internally stored as an `f64`
-        let mut current_char: char = self.source.as_bytes()[(self.current - 1) as usize] as char;
-        let mut result_string: String = String::from("");
+        //! Evaluate numbers, which are internally stored as an `f64`. Besides plain decimals this
+        //! reads `0x`, `0b` and `0o` prefixes, `_` separators and exponents like `6.02e23`
+        //The first digit is already consumed
+        let radix = match (&self.source[self.start as usize..], self.peek()) {
+            (leading, 'x' | 'X') if leading.starts_with('0') => 16,
+            (leading, 'b' | 'B') if leading.starts_with('0') => 2,
+            (leading, 'o' | 'O') if leading.starts_with('0') => 8,
+            _ => 10,
+        };
+
+        let mut value = if radix == 10 {
+            self.decimal()
+        } else {
+            //Over the prefix
+            self.advance();
+            self.prefixed(radix)
+        };
 
-        while is_ascii_num(current_char) {
-            result_string.push(current_char);
-            current_char = self.advance();
+        //Letters straight after a number are a typo rather than the start of a name, so they
+        //belong to the bad literal
+        if value.is_ok() && is_identifier_continue(self.peek()) {
+            value = Err(format!(
+                "`{}` is not a {} digit",
+                self.peek(),
+                radix_name(radix)
+            ));
+        }
+        while is_identifier_continue(self.peek()) {
+            self.advance();
         }
 
-        //Decimal Stuff
-        if current_char == '.' && is_ascii_num(self.peek()) {
-            //Only let one decimal point be read in per number
-            result_string.push('.');
-            current_char = self.advance();
-            while is_ascii_num(current_char) {
-                result_string.push(current_char);
-                current_char = self.advance();
+        let result_string = self.source[self.start as usize..self.current as usize].to_string();
+        let value = value.and_then(|value| {
+            if value.is_finite() {
+                Ok(value)
+            } else {
+                Err(String::from("too large to store"))
             }
-        }
+        });
 
-        //Skip the dot at the end, just in case the user slips up / forgets
-        if current_char == '.' {
-            self.current += 1;
+        //Skip the dot at the end, just in case the user slips up / forgets, unless it starts a
+        //range
+        if self.peek() == '.' && self.peek_next() != '.' {
+            self.advance();
         }
 
-        //Undiscard next character if we have not used it
-        if !is_ascii_num(current_char) {
-            self.current -= 1;
-        }
+        //Still hand back a token so the parser doesn't pile more errors on top, nothing runs with
+        //a scanner error anyway
+        let value = value.unwrap_or_else(|label| {
+            self.errors.push(
+                Diagnostic::error(format!("Invalid number `{result_string}`"), self.span())
+                    .with_code(Some(&codes::INVALID_NUMBER))
+                    .with_label(label),
+            );
+            0.0
+        });
 
         new_literal!(
             TokenType::Number,
             result_string.to_owned(),
-            LiteralType::Number(result_string.parse::<f64>().unwrap()),
-            self.line
+            LiteralType::Number(value),
+            self.span()
         )
     }
 
+    fn decimal(&mut self) -> Result<f64, String> {
+        //!Reads the rest of a base 10 number, optionally with a fraction and an exponent
+        self.digits(10);
+
+        //Decimal Stuff
+        if self.peek() == '.' && is_ascii_num(self.peek_next()) {
+            //Only let one decimal point be read in per number
+            self.advance();
+            self.digits(10);
+        }
+
+        if matches!(self.peek(), 'e' | 'E') {
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
+                self.advance();
+            }
+            if !is_ascii_num(self.peek()) {
+                return Err(String::from("exponent has no digits"));
+            }
+            self.digits(10);
+        }
+
+        let text = &self.source[self.start as usize..self.current as usize];
+        check_separators(text, 10)?;
+        Ok(text
+            .replace('_', "")
+            .parse::<f64>()
+            .expect("Scanned decimal is always valid"))
+    }
+
+    fn prefixed(&mut self, radix: u32) -> Result<f64, String> {
+        //!Reads the digits after a `0x`, `0b` or `0o` prefix
+        let digits_start = self.current as usize;
+        self.digits(radix);
+        let digits = &self.source[digits_start..self.current as usize];
+
+        if !digits.chars().any(|digit| digit != '_') {
+            return Err(format!("no {} digits after the prefix", radix_name(radix)));
+        }
+        check_separators(digits, radix)?;
+
+        //Folded into a float directly, so long literals lose precision instead of overflowing
+        Ok(digits
+            .chars()
+            .filter_map(|digit| digit.to_digit(radix))
+            .fold(0.0, |total, digit| {
+                total * f64::from(radix) + f64::from(digit)
+            }))
+    }
+
+    fn digits(&mut self, radix: u32) {
+        //!Runs through digits of the given base and `_` separators
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         //! Checks if we are end of token stream by counting number of chars
         self.current >= self.source.len().try_into().unwrap()
     }
 
     fn advance(&mut self) -> char {
-        //! Moves to next char, defines end of string otherwise
-        if !self.is_at_end() {
-            self.current += 1;
-            self.source.as_bytes()[(self.current - 1) as usize] as char
-        } else {
-            '\0'
+        //! Moves past the next character, `current` stays a byte offset so it steps by however
+        //! many bytes the character takes up. Defines end of string otherwise
+        match self.source[self.current as usize..].chars().next() {
+            Some(next) => {
+                self.current += next.len_utf8() as u32;
+                next
+            }
+            None => '\0',
         }
     }
 
     fn peek(&self) -> char {
-        self.source.as_bytes()[(self.current) as usize] as char
+        self.source[self.current as usize..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
+    }
+
+    fn peek_next(&self) -> char {
+        //! Looks one character past `peek`
+        self.source[self.current as usize..]
+            .chars()
+            .nth(1)
+            .unwrap_or('\0')
     }
 
     pub(crate) fn scan_tokens(&mut self) -> Vec<Token> {
         //!Extract tokens from source, essentially a "start" or "do a thing" function
         let mut tokens: Vec<Token> = vec![];
         while !self.is_at_end() {
+            self.begin_token();
             if let Some(current_token) = self.scan_token() {
                 tokens.push(current_token);
             };
         }
+
+        //Push Final EOF token
+        self.begin_token();
+        tokens.push(Token::new(TokenType::Eof, "", None, self.span()));
+
         tokens
     }
 }
-fn is_ascii_ident(ch: char) -> bool {
-    //!Defines the rules for what is allowed in an Identifier
-    ch.is_ascii_lowercase() || ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_'
+fn is_identifier_start(ch: char) -> bool {
+    //!Identifiers follow Unicode's XID rules, like Rust's, so `größe` and `名前` are both names
+    ch == '_' || unicode_ident::is_xid_start(ch)
+}
+
+fn is_identifier_continue(ch: char) -> bool {
+    //!Defines the rules for what is allowed in an Identifier after its first character
+    unicode_ident::is_xid_continue(ch)
+}
+
+fn check_separators(digits: &str, radix: u32) -> Result<(), String> {
+    //!`_` may only sit between digits, `1_000` is fine but `1_` and `1_.5` aren't
+    let mut characters = digits.chars().peekable();
+    while let Some(character) = characters.next() {
+        if character == '_'
+            && !characters
+                .peek()
+                .is_some_and(|next| *next == '_' || next.is_digit(radix))
+        {
+            return Err(String::from("`_` must be followed by a digit"));
+        }
+    }
+    Ok(())
+}
+
+fn radix_name(radix: u32) -> &'static str {
+    match radix {
+        2 => "binary",
+        8 => "octal",
+        16 => "hexadecimal",
+        _ => "decimal",
+    }
 }
 
 #[allow(clippy::manual_range_contains)]
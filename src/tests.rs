@@ -2,16 +2,60 @@
 //! possible
 
 #[cfg(test)]
-use crate::run;
+use crate::{
+    Args,
+    Backend,
+    ExitCode,
+    STACK_SIZE,
+    diagnostics::{
+        Diagnostic,
+        add_source,
+        codes,
+    },
+    emit_llvm,
+    frontend,
+    interpreter::Interpreter,
+    ir::{
+        compiler::Compiler,
+        opcode::OpCode,
+        value::Value,
+        vm::Vm,
+    },
+    parser::Parser,
+    resolver::Resolver,
+    run,
+    run_file,
+    run_with,
+    scanner::Scanner,
+    types::{
+        Expression,
+        LiteralType,
+        span::Span,
+        statement::Statement,
+    },
+};
+use clap::Parser as _;
 use std::{
+    collections::HashMap,
     fs,
+    io::Write,
     path::Path,
+    process::{
+        Command,
+        Stdio,
+    },
+    thread,
 };
 
-const PREFIX: &'static str = "./src/tests/";
+const PREFIX: &str = "./src/tests/";
 
 #[allow(dead_code)]
 fn run_file_test(filepath: &str) {
+    run_file_test_on(&mut Interpreter::new(), filepath)
+}
+
+///Runs a test script on the given backend, asserting it finishes cleanly
+fn run_file_test_on(backend: &mut dyn Backend, filepath: &str) {
     let files = &(format!("{PREFIX}{filepath}"));
 
     let file_path = Path::new(files);
@@ -20,29 +64,26 @@ fn run_file_test(filepath: &str) {
         panic!("File not provieded")
     }
 
-    let unvalidated_extension = file_path.extension();
-    if unvalidated_extension.is_some() {
-        let extension = unvalidated_extension.unwrap();
-
-        if extension != "lux" {
-            panic!("Please provide a *.lux source file");
-        }
+    if let Some(extension) = file_path.extension()
+        && extension != "lux"
+    {
+        panic!("Please provide a *.lux source file");
     }
 
-    let source = fs::read_to_string(file_path);
-
     //Check source for OS Errors
-    if source.is_err() {
-        let error = source.unwrap_err();
-        panic!("File Error: {error}");
-    }
-
-    let valid_source = source.expect("Expected Valid Source");
+    let valid_source = match fs::read_to_string(file_path) {
+        Ok(source) => source,
+        Err(error) => panic!("File Error: {error}"),
+    };
 
     //Run the code
-    match run(&valid_source) {
-        Ok(_) => {
-            //exit(0);
+    match run_with(backend, &valid_source, false) {
+        Ok(code) => {
+            assert_eq!(
+                code,
+                ExitCode::Okay as i32,
+                "{filepath} did not run cleanly"
+            );
         }
         Err(err) => {
             panic!("{err}");
@@ -50,6 +91,82 @@ fn run_file_test(filepath: &str) {
     };
 }
 
+///Runs `source` on the interpreter and the VM, asserting both finish cleanly and that each
+///`(global, text)` case displays as `text` on both
+fn assert_both_backends(source: &str, cases: &[(&str, &str)]) {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        run_with(&mut interpreter, source, false).unwrap(),
+        ExitCode::Okay as i32,
+        "the interpreter did not run cleanly"
+    );
+    let mut vm = Vm::new();
+    assert_eq!(
+        run_with(&mut vm, source, false).unwrap(),
+        ExitCode::Okay as i32,
+        "the vm did not run cleanly"
+    );
+
+    for (name, text) in cases {
+        let value = interpreter.enviroment.borrow().get(name).unwrap();
+        assert_eq!(value.to_string(), *text, "{name} on the interpreter");
+        assert_eq!(
+            vm.global(name).unwrap().to_string(),
+            *text,
+            "{name} on the vm"
+        );
+    }
+}
+
+///Runs each source on a fresh interpreter and VM, asserting both stop with the same rendered
+///diagnostic, so the message, code, notes and underlined span all agree
+fn assert_same_diagnostic(sources: &[&str]) {
+    for source in sources {
+        let mut statements = frontend("<test>", source).unwrap();
+        let Err(error) = Interpreter::new().execute_statements(&mut statements, false) else {
+            panic!("{source} should fail on the interpreter");
+        };
+
+        let mut statements = frontend("<test>", source).unwrap();
+        let mut vm = Vm::new();
+        let Err(fault) = vm.execute(&mut statements, false) else {
+            panic!("{source} should fail on the vm");
+        };
+        assert_eq!(
+            vm.diagnostic(fault).render(false),
+            Diagnostic::from(&error).render(false),
+            "{source}"
+        );
+    }
+}
+
+///Asserts each source is turned away before anything runs, by the scanner, parser or resolver
+fn assert_rejected(sources: &[&str]) {
+    for source in sources {
+        assert_eq!(
+            run(source).unwrap(),
+            ExitCode::DataErr as i32,
+            "{source} should be rejected"
+        );
+    }
+}
+
+///Runs each source on a fresh interpreter and VM, asserting both stop with a runtime error
+fn assert_both_fail(sources: &[&str]) {
+    for source in sources {
+        assert_eq!(
+            run_with(&mut Interpreter::new(), source, false).unwrap(),
+            ExitCode::RuntimeErr as i32,
+            "{source} should fail on the interpreter"
+        );
+        assert_eq!(
+            run_with(&mut Vm::new(), source, false).unwrap(),
+            ExitCode::RuntimeErr as i32,
+            "{source} should fail on the vm"
+        );
+    }
+}
+
 #[test]
 fn control_test() {
     run_file_test("control_test.lux");
@@ -79,3 +196,1284 @@ fn comparison_test() {
 fn function_test() {
     run_file_test("function_test.lux")
 }
+
+#[test]
+fn class_test() {
+    run_file_test("class_test.lux")
+}
+
+#[test]
+fn lambda_test() {
+    run_file_test("lambda_test.lux")
+}
+
+#[test]
+fn return_test() {
+    run_file_test("return_test.lux")
+}
+
+#[test]
+fn repl_persistence_test() {
+    //Each line of the REPL is a separate run against the same interpreter
+    let mut interpreter = Interpreter::new();
+    run_with(&mut interpreter, "var x = 1;", true).unwrap();
+    run_with(&mut interpreter, "fun bump() { return x + 1; }", true).unwrap();
+    run_with(&mut interpreter, "x = bump();", true).unwrap();
+
+    assert_eq!(
+        interpreter.enviroment.borrow().get("x"),
+        Ok(LiteralType::Number(2.0))
+    );
+}
+
+#[test]
+fn repl_after_file_test() {
+    //`rlux -f file -n` hands the file's globals over to the prompt
+    let mut interpreter = Interpreter::new();
+    run_file(&mut interpreter, format!("{PREFIX}lambda_test.lux"));
+    run_with(&mut interpreter, "var r = makeAdder(1)(1);", true).unwrap();
+
+    assert_eq!(
+        interpreter.enviroment.borrow().get("r"),
+        Ok(LiteralType::Number(2.0))
+    );
+}
+
+#[test]
+fn repl_echo_test() {
+    //Lines that only store a value stay quiet, everything else echoes what it evaluated to
+    let lines = [
+        ("var xs = [1];", false),
+        ("xs[0] = 2;", false),
+        ("xs[0] += 3;", false),
+        ("var i = 0;", false),
+        ("i += 3;", false),
+        ("i++;", false),
+        ("--i;", false),
+        ("i;", true),
+        ("xs;", true),
+    ];
+
+    let mut interpreter = Interpreter::new();
+    interpreter.captured = Some(String::new());
+    for (line, echoes) in lines {
+        run_with(&mut interpreter, line, true).unwrap();
+        let echoed = interpreter.captured.replace(String::new()).unwrap();
+        assert_eq!(!echoed.is_empty(), echoes, "{line} on the interpreter");
+    }
+
+    let mut globals = HashMap::new();
+    for (line, echoes) in lines {
+        let mut statements = frontend("repl_echo", line).unwrap();
+        let function = Compiler::new(&mut globals, true).compile(&mut statements);
+        let echoed = function
+            .chunk
+            .code
+            .iter()
+            .any(|op| matches!(op, OpCode::Echo));
+        assert_eq!(echoed, echoes, "{line} on the vm");
+    }
+}
+
+#[test]
+fn closure_test() {
+    run_file_test("closure_test.lux")
+}
+
+#[test]
+fn closure_counter_test() {
+    //Closures share the enviroment they captured, so the counter sees its own increments
+    let mut interpreter = Interpreter::new();
+    run_with(
+        &mut interpreter,
+        "fun makeCounter() {
+            var i = 0;
+            fun count() {
+                i = i + 1;
+                return i;
+            }
+            return count;
+        }
+        var counter = makeCounter();
+        counter();
+        var second = counter();",
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        interpreter.enviroment.borrow().get("second"),
+        Ok(LiteralType::Number(2.0))
+    );
+}
+
+#[test]
+fn closure_equality_test() {
+    //Functions are only equal to themselves, a fresh closure or bound method never is
+    let source = "fun mk() { var c = 0; return fun () { c = c + 1; return c; }; }
+        var fresh = mk() == mk();
+        var f = mk();
+        var same = f == f;
+        class A { m() {} }
+        var a = A();
+        var bound = a.m == a.m;";
+
+    assert_both_backends(
+        source,
+        &[("fresh", "false"), ("same", "true"), ("bound", "false")],
+    );
+}
+
+#[test]
+fn closure_scope_test() {
+    //Both calls must print "global", the block's `a` is declared after `showA` closes over it
+    run_file_test("closure_scope_test.lux")
+}
+
+#[test]
+fn resolver_error_test() {
+    //Each of these is rejected before anything runs
+    assert_rejected(&[
+        "{ var a = 1; var a = 2; }",
+        "{ var a = a; }",
+        "return 1;",
+        "class A { init() { return 1; } }",
+        "println(this);",
+        "class A { f() { return super.f(); } }",
+        "class A < A {}",
+    ]);
+}
+
+#[test]
+fn parse_error_test() {
+    //Every syntax error is collected in one pass, recovery doesn't invent extra ones
+    let source = "var a = ;\nprintln(1)\nfun f( {}\nclass A { m() { return 1 } }\nvar ok = 1;";
+    let file = add_source("<test>", source);
+    let tokens = Scanner::new(source, file, None, None).scan_tokens();
+    let errors = Parser::new(tokens, 0).parse().unwrap_err();
+    let positions: Vec<(u32, u32)> = errors
+        .iter()
+        .map(|error| (error.source.span.line, error.source.span.column))
+        .collect();
+    assert_eq!(positions, vec![(1, 9), (3, 1), (3, 8), (4, 26)]);
+    assert_eq!(errors[0].cause, "Expected expression, found ';'.");
+
+    //A program with a syntax error anywhere doesn't run at all
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        run_with(
+            &mut interpreter,
+            "var a = 1;\nprintln(a +);\nvar b = @;",
+            false
+        )
+        .unwrap(),
+        ExitCode::DataErr as i32
+    );
+    assert!(interpreter.enviroment.borrow().get("a").is_err());
+}
+
+#[test]
+fn runtime_error_test() {
+    //The first runtime error stops the program, the interpreter stays usable afterwards
+    let mut interpreter = Interpreter::new();
+    for source in [
+        "-true;",
+        "!1;",
+        "1 + nil;",
+        "1 < \"a\";",
+        "println(missing);",
+        "missing = 1;",
+        "var a = 1; a();",
+        "fun f(a) {} f();",
+        "class A {} A().b;",
+        "var n = 1; n.b = 2;",
+        "var B = 1; class A < B {}",
+        "if (1) {}",
+        "while (nil) {}",
+        "1 or true;",
+        "1 ? 2 : 3;",
+        "{ var a = 1; println(a + nil); }",
+    ] {
+        assert_eq!(
+            run_with(&mut interpreter, source, false).unwrap(),
+            ExitCode::RuntimeErr as i32,
+            "{source} should fail at runtime"
+        );
+    }
+
+    run_with(&mut interpreter, "var ok = 1 + 1;", false).unwrap();
+    assert_eq!(
+        interpreter.enviroment.borrow().get("ok"),
+        Ok(LiteralType::Number(2.0))
+    );
+}
+
+#[test]
+fn runtime_error_aborts_test() {
+    //Nothing after the failing statement runs
+    let mut interpreter = Interpreter::new();
+    run_with(&mut interpreter, "var a = 1; a = a + nil; a = 3;", false).unwrap();
+
+    assert_eq!(
+        interpreter.enviroment.borrow().get("a"),
+        Ok(LiteralType::Number(1.0))
+    );
+}
+
+#[test]
+fn span_test() {
+    //Spans cover exactly the source of each node, columns count from 1
+    let source = "var x = 1;\n  x = (x + 20) * 3;\nfun f() { return x; }\nclass A { m() {} }";
+    let statements = frontend("<test>", source).unwrap();
+    let text = |span: Span| &source[span.start as usize..span.end as usize];
+
+    let Statement::Expression(ref statement) = statements[1] else {
+        panic!("expected an expression statement");
+    };
+    let Expression::Assignment(ref assign) = statement.expression else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(text(statements[1].span()), "x = (x + 20) * 3");
+    assert_eq!((assign.name.span.line, assign.name.span.column), (2, 3));
+
+    let Expression::Binary(ref product) = assign.value else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(text(product.left.span()), "(x + 20)");
+    assert_eq!(product.left.span().column, 7);
+    assert_eq!(text(product.right.span()), "3");
+
+    //Declarations start at their keyword, methods have none and start at their name
+    assert_eq!(text(statements[0].span()), "var x = 1");
+    assert_eq!(text(statements[2].span()), "fun f() { return x; }");
+    assert_eq!(statements[2].span().line, 3);
+    let Statement::Class(ref class) = statements[3] else {
+        panic!("expected a class");
+    };
+    assert_eq!(text(statements[3].span()), "class A { m() {} }");
+    assert_eq!(text(class.methods[0].span), "m() {}");
+}
+
+#[test]
+fn utf8_test() {
+    //Non-ASCII text survives scanning, spans stay byte offsets while columns count characters
+    let mut interpreter = Interpreter::new();
+    run_with(&mut interpreter, "var größe = \"Grüße, 世界 🎉\";", false).unwrap();
+    assert_eq!(
+        interpreter.enviroment.borrow().get("größe"),
+        Ok(LiteralType::String(String::from("Grüße, 世界 🎉")))
+    );
+
+    let source = "var 名前 = \"é\" + 1; // ✓";
+    let tokens = Scanner::new(source, add_source("<test>", source), None, None).scan_tokens();
+    let name = tokens[1].span;
+    assert_eq!(tokens[1].lexeme, "名前");
+    assert_eq!(&source[name.start as usize..name.end as usize], "名前");
+    assert_eq!(
+        tokens[3].literal,
+        Some(LiteralType::String(String::from("é")))
+    );
+    assert_eq!(
+        (tokens[4].lexeme.as_str(), tokens[4].span.column),
+        ("+", 14)
+    );
+    assert_eq!(tokens.len(), 8);
+
+    //Block comments may hold any text, but one left open is an error rather than eating the file
+    assert_eq!(
+        run("/* größe ✓ */ var a = 1;").unwrap(),
+        ExitCode::Okay as i32
+    );
+    assert_rejected(&["var a = 1; /* never closed", "/* 世界", "/*"]);
+}
+
+#[test]
+fn number_literal_test() {
+    let mut interpreter = Interpreter::new();
+    for (source, expected) in [
+        ("0xFF", 255.0),
+        ("0b1010", 10.0),
+        ("0o17", 15.0),
+        ("0x_dead_BEEF", 3_735_928_559.0),
+        ("1_000_000", 1_000_000.0),
+        ("6.02e23", 6.02e23),
+        ("1.5E-3", 0.0015),
+        ("2e+2", 200.0),
+    ] {
+        run_with(&mut interpreter, &format!("var n = {source};"), false).unwrap();
+        assert_eq!(
+            interpreter.enviroment.borrow().get("n"),
+            Ok(LiteralType::Number(expected)),
+            "{source}"
+        );
+    }
+
+    //Malformed literals are reported instead of panicking
+    for source in [
+        "0xFG", "0b102", "0o8", "0x", "1_", "1__", "1_.5", "1e", "1e+", "12abc", "1e999",
+    ] {
+        assert_eq!(
+            run(&format!("var n = {source};")).unwrap(),
+            ExitCode::DataErr as i32,
+            "{source} should not scan"
+        );
+    }
+}
+
+#[test]
+fn string_literal_test() {
+    let mut interpreter = Interpreter::new();
+    for (source, expected) in [
+        (r#""a\tb\\c\"d\'e\r\0""#, "a\tb\\c\"d'e\r\0"),
+        (r#""\x41\u{e9}\u{1F600}""#, "Aé😀"),
+        (r#"r"C:\path\new""#, r"C:\path\new"),
+        ("\"two\nlines\"", "two\nlines"),
+        ("\"\"\"one line\"\"\"", "one line"),
+        //Shared indentation and the blank first and last lines go, deeper indents stay
+        (
+            "\"\"\"\n    Roses are red,\n      violets\\tblue\n\n    \"\"\"",
+            "Roses are red,\n  violets\tblue\n",
+        ),
+        ("r\"\"\"\n  raw \\n\n  \"\"\"", "raw \\n"),
+    ] {
+        run_with(&mut interpreter, &format!("var s = {source};"), false).unwrap();
+        assert_eq!(
+            interpreter.enviroment.borrow().get("s"),
+            Ok(LiteralType::String(String::from(expected))),
+            "{source}"
+        );
+    }
+
+    for source in [
+        r#""\q""#,
+        r#""\x80""#,
+        r#""\x4""#,
+        r#""\u41""#,
+        r#""\u{}""#,
+        r#""\u{1234567}""#,
+        r#""\u{D800}""#,
+        r#""\u{41""#,
+        "\"\"\"unterminated\"",
+    ] {
+        assert_eq!(
+            run(&format!("var s = {source};")).unwrap(),
+            ExitCode::DataErr as i32,
+            "{source} should not scan"
+        );
+    }
+}
+
+#[test]
+fn string_interpolation_test() {
+    //Embedded expressions are formatted like `print` on both backends, nesting included
+    let source = r#"var name = "Ada";
+        var count = 2;
+        var greeting = "Hello ${name}, you have ${count + 1} items";
+        var nested = "${"<${name}>"} ${nil} ${count > 1} ${ fun () { return "{}"; }() }";
+        var escaped = "\${name}" + r" ${name}";
+        var letter = """
+            Dear ${name},
+              you owe ${count * 10}
+            """;"#;
+    let expected = [
+        ("greeting", "Hello Ada, you have 3 items"),
+        ("nested", "<Ada> NIL true {}"),
+        ("escaped", "${name} ${name}"),
+        ("letter", "Dear Ada,\n  you owe 20"),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    assert_rejected(&[
+        "var s = \"${}\";",
+        "var s = \"${1 2}\";",
+        "var s = \"${1\";",
+        "var s = \"${1 + }\";",
+    ]);
+}
+
+#[test]
+fn list_test() {
+    //Lists are shared by reference, slices copy and equality looks at the elements
+    let source = r#"var xs = [1, 2, 3,];
+        var alias = xs;
+        alias[0] = 10;
+        push(xs, "four");
+        var popped = pop(xs);
+        push(xs, [4]);
+        var first = xs[0];
+        var nested = xs[3][0];
+        var middle = xs[1:3];
+        var head = xs[:1];
+        var tail = xs[2:];
+        middle[0] = 0;
+        var same = [1, [2]] == [1, [2]];
+        var different = [1] == ["1"];
+        var sizes = [len(xs), len("héllo"), len([])];
+        var printed = "${xs} ${["a", nil]}";
+        var cyclic = [];
+        push(cyclic, cyclic);
+        push(cyclic, [cyclic]);
+        var twin = [];
+        push(twin, twin);
+        push(twin, [twin]);
+        var cycles_equal = cyclic == twin;
+        var cycles_differ = cyclic == [cyclic, 1];"#;
+    let expected = [
+        ("xs", "[10, 2, 3, [4]]"),
+        ("alias", "[10, 2, 3, [4]]"),
+        ("popped", "four"),
+        ("first", "10"),
+        ("nested", "4"),
+        ("middle", "[0, 3]"),
+        ("head", "[10]"),
+        ("tail", "[3, [4]]"),
+        ("same", "true"),
+        ("different", "false"),
+        ("sizes", "[4, 5, 0]"),
+        ("printed", "[10, 2, 3, [4]] [\"a\", NIL]"),
+        ("cyclic", "[[...], [[...]]]"),
+        ("cycles_equal", "true"),
+        ("cycles_differ", "false"),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    assert_both_fail(&[
+        "[1][1];",
+        "[1][-1];",
+        "[1][0.5];",
+        "[1][\"0\"];",
+        "1[0];",
+        "var xs = [1]; xs[1] = 2;",
+        "[1, 2][2:1];",
+        "[1, 2][0:3];",
+        "pop([]);",
+        "push(1, 2);",
+        "len(1);",
+        "[1] + \"a\";",
+    ]);
+
+    assert_rejected(&["var xs = [1, 2;", "var x = xs[0;", "var x = xs[];"]);
+}
+
+#[test]
+fn map_test() {
+    //Maps share like lists, keys iterate in sorted order and can be strings, numbers or booleans
+    let source = r#"var config = {"name": "rlux", "version": 4, 1: "one", true: nil,};
+        var alias = config;
+        alias["debug"] = false;
+        config["version"] = config["version"] + 1;
+        var name = config["name"];
+        var has_debug = has(config, "debug");
+        var removed = remove(config, 1);
+        var has_one = has(config, 1);
+        var size = len(config);
+        var names = keys(config);
+        var empty = {};
+        var zero = {0: "a"}[-0];
+        var same = {"a": [1], "b": {}} == {"b": {}, "a": [1]};
+        var different = {"a": 1} == {"a": "1"};
+        var printed = "${config} ${empty}";
+        var nested = { "inner": { "x": 1 } }["inner"]["x"];
+        var cyclic = {};
+        cyclic["self"] = cyclic;
+        cyclic["list"] = [cyclic];
+        var twin = {};
+        twin["self"] = twin;
+        twin["list"] = [twin];
+        var cycles_equal = cyclic == twin;
+        twin["extra"] = 1;
+        var cycles_differ = cyclic == twin;"#;
+    let expected = [
+        (
+            "alias",
+            "{true: NIL, \"debug\": false, \"name\": \"rlux\", \"version\": 5}",
+        ),
+        ("name", "rlux"),
+        ("has_debug", "true"),
+        ("removed", "one"),
+        ("has_one", "false"),
+        ("size", "4"),
+        ("names", "[true, \"debug\", \"name\", \"version\"]"),
+        ("zero", "a"),
+        ("same", "true"),
+        ("different", "false"),
+        (
+            "printed",
+            "{true: NIL, \"debug\": false, \"name\": \"rlux\", \"version\": 5} {}",
+        ),
+        ("nested", "1"),
+        ("cyclic", "{\"list\": [{...}], \"self\": {...}}"),
+        ("cycles_equal", "true"),
+        ("cycles_differ", "false"),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    assert_both_fail(&[
+        "var x = {\"a\": 1}[\"b\"];",
+        "var x = {}[nil];",
+        "var m = {}; m[[1]] = 2;",
+        "var m = {[1]: 2};",
+        "remove({}, \"a\");",
+        "has([], 1);",
+        "keys([]);",
+        "var x = {}[0:1];",
+    ]);
+
+    //Both backends point at the same place, a bad key in a literal is reported at its brace
+    assert_same_diagnostic(&[
+        "var x = {\"a\": 1}[\"b\"];",
+        "var m = {}; m[[1]] = 2;",
+        "var m = {[1]: 2};",
+        "var m = {\"a\": 1,\n    nil: 2};",
+    ]);
+
+    assert_rejected(&[
+        "var m = {\"a\" 1};",
+        "var m = {\"a\": 1;",
+        "var m = {1, 2};",
+    ]);
+}
+
+#[test]
+fn loop_control_test() {
+    //`continue` in a `for` still runs the increment, labels reach past inner loops, and locals
+    //declared in a loop body are cleaned up however it is left
+    let source = "var evens = 0;
+        for (var i = 0; i < 10; i = i + 1) {
+            if (i == 7) break;
+            var odd = i == 1 or i == 3 or i == 5;
+            if (odd) continue;
+            evens = evens + 1;
+        }
+        var pairs = \"\";
+        outer: for (var a = 0; a < 3; a = a + 1) {
+            var b = 0;
+            while (true) {
+                b = b + 1;
+                if (b > a) continue outer;
+                if (a == 2) break outer;
+                pairs = pairs + a + b;
+            }
+        }
+        var count = 0;
+        while (count < 5) {
+            var captured = count;
+            count = count + 1;
+            fun keep() { return captured; }
+            if (count == 2) continue;
+            if (count == 4) break;
+        }
+        fun first_over(limit) {
+            for (var n = 0; ; n = n + 1) {
+                if (n * n > limit) return n;
+            }
+        }
+        var root = first_over(50);";
+    let expected = [
+        ("evens", "4"),
+        ("pairs", "11"),
+        ("count", "4"),
+        ("root", "8"),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    assert_rejected(&[
+        "break;",
+        "if (true) continue;",
+        "while (true) { fun f() { break; } }",
+        "while (true) { break missing; }",
+        "inner: while (true) {} while (true) { continue inner; }",
+        "label: var a = 1;",
+        "while (true) { break }",
+    ]);
+}
+
+#[test]
+fn for_in_test() {
+    //Lists are read live so pushing extends the loop, maps walk their keys in order, and every
+    //pass gets its own variable for closures to capture
+    let source = "var seen = \"\";
+        var xs = [1, nil];
+        for (var x in xs) {
+            seen = seen + x + \" \";
+            if (len(xs) < 3) push(xs, 3);
+        }
+        var keys = \"\";
+        for (var k in {\"b\": 1, \"a\": 2, 1: 3}) keys = keys + k;
+        var chars = 0;
+        for (var c in \"héllo\") chars = chars + 1;
+        var sum = 0;
+        for (var i in 1..4) sum = sum + i;
+        for (var i in 1..=4) sum = sum + i;
+        for (var i in 3..1) sum = 0;
+        fun countdown(n) {
+            fun next() {
+                if (n == 0) return nil;
+                n = n - 1;
+                return n;
+            }
+            return next;
+        }
+        var counted = \"\";
+        for (var n in countdown(3)) counted = counted + n;
+        class Evens {
+            init(limit) { this.current = 0; this.limit = limit; }
+            next() {
+                if (this.current > this.limit) return nil;
+                this.current = this.current + 2;
+                return this.current - 2;
+            }
+        }
+        var evens = \"\";
+        for (var e in Evens(6)) evens = evens + e;
+        var fs = [];
+        for (var i in 0..3) { fun f() { return i; } push(fs, f); }
+        var captured = \"\";
+        for (var f in fs) captured = captured + f();
+        var pairs = \"\";
+        outer: for (var a in 0..3) {
+            for (var b in \"xyz\") {
+                if (b == \"y\") continue outer;
+                if (a == 2) break outer;
+                pairs = pairs + a + b;
+            }
+        }
+        fun find(list, wanted) {
+            for (var item in list) { if (item == wanted) return true; }
+            return false;
+        }
+        var found = find([1, 2, 3], 2) and !find([1, 2, 3], 5);";
+    let expected = [
+        ("seen", "1 NIL 3 "),
+        ("keys", "1ab"),
+        ("chars", "5"),
+        ("sum", "16"),
+        ("counted", "210"),
+        ("evens", "0246"),
+        ("captured", "012"),
+        ("pairs", "0x1x"),
+        ("found", "true"),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    assert_both_fail(&[
+        "for (var x in 5) {}",
+        "for (var x in 1..\"a\") {}",
+        "class A {} for (var x in A()) {}",
+    ]);
+}
+
+#[test]
+fn exception_test() {
+    //Runtime errors arrive as maps, `finally` runs however its try is left and can override a
+    //return, and faults unwind through calls and loops to the nearest handler
+    let source = "var thrown = nil;
+        try { throw \"boom\"; } catch (e) { thrown = e; }
+        var message = nil;
+        var line = nil;
+        try {
+            var n = 1 +
+                nil;
+        } catch (e) {
+            message = e[\"message\"];
+            line = e[\"line\"];
+        }
+        var log = \"\";
+        fun early() {
+            try { return \"body\"; } finally { log = log + \"early \"; }
+        }
+        var returned = early();
+        fun overridden() {
+            try { return 1; } finally { return 2; }
+        }
+        var replaced = overridden();
+        fun deep(n) {
+            if (n == 0) throw {\"depth\": n};
+            return deep(n - 1);
+        }
+        var depth = nil;
+        try { deep(20); } catch (e) { depth = e[\"depth\"]; }
+        var order = \"\";
+        try {
+            try { throw \"a\"; } catch (e) { throw e + \"b\"; } finally { order = order + \"f\"; }
+        } catch (e) {
+            order = order + e;
+        }
+        var steps = \"\";
+        outer: for (var i in 0..4) {
+            try {
+                for (var j in 0..2) {
+                    try {
+                        if (i == 1) continue outer;
+                        if (i == 3) break outer;
+                        steps = steps + i + j;
+                    } finally {
+                        steps = steps + \".\";
+                    }
+                }
+            } finally {
+                steps = steps + \"|\";
+            }
+        }
+        var handled = 0;
+        var attempts = 0;
+        while (attempts < 3) {
+            attempts = attempts + 1;
+            try {
+                var local = attempts;
+                if (local != 2) println(missing);
+            } catch (e) {
+                handled = handled + 1;
+            }
+        }";
+    let expected = [
+        ("thrown", "boom"),
+        (
+            "message",
+            "Operands must be two numbers or start with a string.",
+        ),
+        ("line", "6"),
+        ("returned", "body"),
+        ("log", "early "),
+        ("replaced", "2"),
+        ("depth", "0"),
+        ("order", "fab"),
+        ("steps", "00.01.|.|20.21.|.|"),
+        ("handled", "2"),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    //Anything nobody catches still ends the program, after the finally blocks on the way out
+    assert_both_fail(&[
+        "throw \"unhandled\";",
+        "try { throw 1; } finally { var cleaned = true; }",
+        "try { 1 + nil; } catch (e) { throw e; }",
+    ]);
+
+    assert_rejected(&[
+        "try { }",
+        "try print(1); catch (e) {}",
+        "try { } catch { }",
+        "try { } catch (e) print(e);",
+        "throw;",
+    ]);
+}
+
+#[test]
+fn operator_test() {
+    //`**` binds tighter than unary minus and to the right, and compound targets evaluate their
+    //object and index once while postfix forms give back the old value
+    let source = "var modulo = 7 % 3 + -7 % 3 * 10;
+        var power = 2 ** 3 ** 2 + -2 ** 2 + 2 ** -1;
+        var mixed = 1 + 2 * 3 % 4;
+        var i = 10;
+        i += 5; i -= 3; i *= 2; i /= 8;
+        var post = i++;
+        var pre = ++i;
+        var down = i-- + --i;
+        var text = \"a\";
+        text += 1;
+        var loops = 0;
+        for (var k = 0; k < 5; k++) loops += k;
+        class Point {}
+        var point = Point();
+        point.x = 1;
+        point.x += 10;
+        var old = point.x++;
+        var calls = 0;
+        fun at() { calls++; return 1; }
+        var xs = [1, 2, 3];
+        xs[at()] *= 5;
+        var element = xs[at()]--;
+        var m = {\"a\": 1};
+        m[\"a\"] += 2;
+        var grown = m[\"a\"]++ + 10;
+        var stepped = \"\";
+        try { var s = \"a\"; s++; } catch (e) { stepped = e[\"message\"]; }
+        var labels = [nil];
+        try { --labels[0]; } catch (e) { stepped = stepped + \" \" + e[\"message\"]; }";
+    let expected = [
+        ("modulo", "-9"),
+        ("power", "508.5"),
+        ("mixed", "3"),
+        ("i", "3"),
+        ("post", "3"),
+        ("pre", "5"),
+        ("down", "8"),
+        ("text", "a1"),
+        ("loops", "10"),
+        ("old", "11"),
+        ("calls", "2"),
+        ("element", "10"),
+        ("xs", "[1, 9, 3]"),
+        ("grown", "13"),
+        ("m", "{\"a\": 4}"),
+        (
+            "stepped",
+            "Only numbers can be incremented or decremented. Only numbers can be incremented or \
+             decremented.",
+        ),
+    ];
+
+    assert_both_backends(source, &expected);
+
+    assert_both_fail(&[
+        "var s = \"a\"; s++;",
+        "var x = nil; x -= 1;",
+        "var m = {}; m[\"a\"] += 1;",
+        "class A {} var a = A(); a.missing += 1;",
+        "2 % nil;",
+    ]);
+
+    assert_rejected(&[
+        "1 += 2;",
+        "var a = 1; (a)++;",
+        "++1;",
+        "var a = 1; a++ = 2;",
+    ]);
+}
+
+#[test]
+fn diagnostic_render_test() {
+    let file = add_source("render.lux", "var a = 1;\n\tprintln(a + nil);\n");
+    let span = Span {
+        file,
+        start: 20,
+        end: 27,
+        line: 2,
+        column: 10,
+    };
+    let rendered = Diagnostic::error("Operands must be numbers.", span)
+        .with_label("here")
+        .with_note("a note")
+        .with_help("some help")
+        .render(false);
+
+    assert_eq!(
+        rendered,
+        "error: Operands must be numbers.
+ --> render.lux:2:10
+  |
+2 | \tprintln(a + nil);
+  | \t        ^^^^^^^ here
+  = note: a note
+  = help: some help
+"
+    );
+}
+
+#[test]
+fn diagnostic_json_test() {
+    let file = add_source("dir\\json.lux", "println(\"a\" + nil);");
+    let span = Span {
+        file,
+        start: 12,
+        end: 13,
+        line: 1,
+        column: 13,
+    };
+    let rendered = Diagnostic::error("Can't add \"a\"\tto nil.", span)
+        .with_note("first")
+        .with_note("second")
+        .render_json();
+
+    assert_eq!(
+        rendered,
+        r#"{"severity":"error","code":null,"message":"Can't add \"a\"\tto nil.","file":"dir\\json.lux","line":1,"column":13,"span":{"start":12,"end":13},"label":null,"notes":["first","second"],"help":null}"#
+    );
+}
+
+#[test]
+fn error_code_test() {
+    //Codes are unique and `--explain` finds them whatever the case
+    for (index, error_code) in codes::ALL.iter().enumerate() {
+        assert!(
+            codes::ALL[index + 1..]
+                .iter()
+                .all(|other| other.code != error_code.code),
+            "{} is used twice",
+            error_code.code
+        );
+        assert_eq!(
+            codes::lookup(&error_code.code.to_lowercase()),
+            Some(error_code)
+        );
+    }
+    assert_eq!(codes::lookup("E9999"), None);
+
+    let file = add_source("<test>", "1 + nil;");
+    let rendered = Diagnostic::error(
+        "Operands must be numbers.",
+        Span {
+            file,
+            start: 2,
+            end: 3,
+            line: 1,
+            column: 3,
+        },
+    )
+    .with_code(Some(&codes::OPERANDS_MUST_BE_NUMBERS));
+    assert!(rendered.render(false).starts_with("error[E0011]: "));
+    assert!(rendered.render_json().contains("\"code\":\"E0011\""));
+}
+
+#[test]
+fn runtime_error_code_test() {
+    //Every runtime error carries a code, message and notes, the same ones on both backends,
+    //recursion needs the CLI's stack to overflow cleanly
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(|| {
+            for (source, expected) in [
+                ("1 - nil;", &codes::OPERANDS_MUST_BE_NUMBERS),
+                ("1 + true;", &codes::INVALID_ADDITION),
+                ("fun f() {} \"a\" + f;", &codes::INVALID_CONCATENATION),
+                ("throw 1;", &codes::UNCAUGHT_EXCEPTION),
+                ("println(missing);", &codes::UNDEFINED_VARIABLE),
+                ("missing = 1;", &codes::UNDEFINED_VARIABLE),
+                ("var a = 1; a();", &codes::NOT_CALLABLE),
+                ("fun f(a) {} f();", &codes::WRONG_ARGUMENT_COUNT),
+                ("-true;", &codes::OPERAND_MUST_BE_NUMBER),
+                ("var a = \"a\"; a++;", &codes::INVALID_STEP),
+                ("if (1) {}", &codes::BOOLEAN_REQUIRED),
+                ("var a = nil or 1;", &codes::BOOLEAN_REQUIRED),
+                ("var a = 1 and true;", &codes::BOOLEAN_REQUIRED),
+                ("var n = 1; n.b = 2;", &codes::NOT_AN_INSTANCE),
+                ("class A {} A().b;", &codes::UNDEFINED_PROPERTY),
+                ("var B = 1; class A < B {}", &codes::INVALID_SUPERCLASS),
+                ("fun f() { f(); } f();", &codes::STACK_OVERFLOW),
+                ("1[0];", &codes::NOT_INDEXABLE),
+                ("[1][\"a\"];", &codes::INVALID_INDEX),
+                ("[1][5];", &codes::INDEX_OUT_OF_RANGE),
+                ("var m = {}; m[nil] = 1;", &codes::INVALID_KEY),
+                ("var m = {\"a\": 1}; m[\"b\"];", &codes::MISSING_KEY),
+                ("push([1]);", &codes::WRONG_ARGUMENT_COUNT),
+                ("pop([]);", &codes::INVALID_ARGUMENT),
+                ("for (var x in 1) {}", &codes::NOT_ITERABLE),
+                ("for (var x in 1..\"a\") {}", &codes::INVALID_RANGE),
+            ] {
+                let mut statements = frontend("<test>", source).unwrap();
+                let Err(error) = Interpreter::new().execute_statements(&mut statements, false)
+                else {
+                    panic!("{source} should fail on the interpreter");
+                };
+                assert_eq!(error.code, expected, "{source} on the interpreter");
+
+                let mut statements = frontend("<test>", source).unwrap();
+                let Err(fault) = Vm::new().execute(&mut statements, false) else {
+                    panic!("{source} should fail on the vm");
+                };
+                assert_eq!(fault.code, expected, "{source} on the vm");
+                assert_eq!(fault.message, error.message, "{source}");
+                assert_eq!(fault.notes, error.notes, "{source}");
+                assert_eq!(codes::lookup(expected.code), Some(expected));
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    //Scoping and jump mistakes are caught before running, each with its own code
+    for (source, expected) in [
+        ("{ var a = 1; var a = 2; }", &codes::ALREADY_DECLARED),
+        ("{ var a = a; }", &codes::OWN_INITIALIZER),
+        ("return 1;", &codes::INVALID_RETURN),
+        ("class A { init() { return 1; } }", &codes::INVALID_RETURN),
+        ("println(this);", &codes::INVALID_THIS),
+        (
+            "class A { f() { return super.f(); } }",
+            &codes::INVALID_SUPER,
+        ),
+        ("class A < A {}", &codes::INVALID_SUPERCLASS),
+        ("break;", &codes::INVALID_JUMP),
+        ("while (true) { break outer; }", &codes::INVALID_JUMP),
+        ("try {}", &codes::EXPECTED_HANDLER),
+    ] {
+        let file = add_source("<test>", source);
+        let tokens = Scanner::new(source, file, None, None).scan_tokens();
+        let errors = match Parser::new(tokens, 0).parse() {
+            Ok(mut statements) => Resolver::new().resolve(&mut statements).unwrap_err(),
+            Err(errors) => errors,
+        };
+        assert_eq!(errors[0].code, Some(expected), "{source}");
+    }
+}
+
+#[test]
+fn vm_file_test() {
+    //Every script must run cleanly on the VM too
+    for filepath in [
+        "class_test.lux",
+        "closure_scope_test.lux",
+        "closure_test.lux",
+        "comparison_test.lux",
+        "control_test.lux",
+        "for_loop_test.lux",
+        "function_test.lux",
+        "lambda_test.lux",
+        "math_test.lux",
+        "return_test.lux",
+        "scope_test.lux",
+    ] {
+        run_file_test_on(&mut Vm::new(), filepath);
+    }
+}
+
+#[test]
+fn vm_closure_counter_test() {
+    //Upvalues are shared, so the counter sees its own increments after makeCounter returns
+    let mut vm = Vm::new();
+    run_with(
+        &mut vm,
+        "fun makeCounter() {
+            var i = 0;
+            fun count() {
+                i = i + 1;
+                return i;
+            }
+            return count;
+        }
+        var counter = makeCounter();
+        counter();
+        var second = counter();",
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(vm.global("second"), Some(Value::Number(2.0)));
+}
+
+#[test]
+fn vm_repl_persistence_test() {
+    //Global slots are kept between compilations so each line sees the last one's definitions
+    let mut vm = Vm::new();
+    run_with(&mut vm, "var x = 1;", true).unwrap();
+    run_with(&mut vm, "fun bump() { return x + 1; }", true).unwrap();
+    run_with(&mut vm, "x = bump();", true).unwrap();
+
+    assert_eq!(vm.global("x"), Some(Value::Number(2.0)));
+}
+
+#[test]
+fn vm_runtime_error_test() {
+    //The VM stops at the first runtime error and stays usable afterwards
+    let mut vm = Vm::new();
+    for source in [
+        "-true;",
+        "1 + nil;",
+        "println(missing);",
+        "var a = 1; a();",
+        "fun f(a) {} f();",
+        "class A {} A().b;",
+        "if (1) {}",
+        "fun f() { f(); } f();",
+    ] {
+        assert_eq!(
+            run_with(&mut vm, source, false).unwrap(),
+            ExitCode::RuntimeErr as i32,
+            "{source} should fail at runtime"
+        );
+    }
+
+    run_with(&mut vm, "var ok = 1 + 1;", false).unwrap();
+    assert_eq!(vm.global("ok"), Some(Value::Number(2.0)));
+}
+
+#[test]
+fn stack_overflow_test() {
+    //Runaway recursion is a catchable error at the same depth on both backends, the tree-walker
+    //needs the CLI's stack to get that deep
+    let source = "var reached = 0;
+        var message = nil;
+        fun down(n) { reached = n; return 1 + down(n + 1); }
+        try { down(0); } catch (e) { message = e[\"message\"]; }";
+
+    let overflow = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let mut interpreter = Interpreter::new();
+            assert_eq!(
+                run_with(&mut interpreter, source, false).unwrap(),
+                ExitCode::Okay as i32
+            );
+            let mut vm = Vm::new();
+            assert_eq!(
+                run_with(&mut vm, source, false).unwrap(),
+                ExitCode::Okay as i32
+            );
+
+            for name in ["reached", "message"] {
+                let value = interpreter.enviroment.borrow().get(name).unwrap();
+                assert_eq!(
+                    value.to_string(),
+                    vm.global(name).unwrap().to_string(),
+                    "{name} differs between the backends"
+                );
+            }
+            interpreter
+                .enviroment
+                .borrow()
+                .get("message")
+                .unwrap()
+                .to_string()
+        })
+        .unwrap();
+    assert_eq!(overflow.join().unwrap(), "Stack overflow.");
+
+    //Uncaught, it ends the program like any other runtime error
+    assert_eq!(
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| run_with(&mut Interpreter::new(), "fun f() { f(); } f();", false))
+            .unwrap()
+            .join()
+            .unwrap()
+            .unwrap(),
+        ExitCode::RuntimeErr as i32
+    );
+}
+
+#[test]
+fn llvm_emit_test() {
+    let module = emit_llvm(
+        "<test>",
+        "var x = 1;
+        fun add(a, b) { return a + b; }
+        if (x < 2) { println(add(x, 2)); } else { println(\"hi\"); }
+        x += 3 % 2;",
+    )
+    .unwrap();
+
+    for expected in [
+        "%Value = type { i32, double, i8* }",
+        "define i32 @main()",
+        "define internal %Value @\"fn.1.add\"(%Value %p0, %Value %p1)",
+        "@\"g.x\" = internal global %Value",
+        "call %Value @lux_add(",
+        "call %Value @lux_less(",
+        "call %Value @lux_mod(",
+        "c\"hi\\00\"",
+        "call i8* @lux_callee(",
+    ] {
+        assert!(
+            module.contains(expected),
+            "missing `{expected}` in:\n{module}"
+        );
+    }
+}
+
+#[test]
+fn llvm_flag_test() {
+    //`--ir` and `-i` predate `--emit-llvm` and still spell the same option
+    for flag in ["--emit-llvm", "--ir", "-i"] {
+        let args = Args::try_parse_from(["rlux", "-f", "a.lux", flag, "a.ll"]).unwrap();
+        assert_eq!(args.emit_llvm.as_deref(), Some("a.ll"), "{flag}");
+    }
+}
+
+#[test]
+fn llvm_run_test() {
+    //Runs the native module through `lli` when it's installed and compares what it prints with
+    //the tree-walker's output, byte for byte
+    let Ok(mut lli) = Command::new("lli")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    else {
+        eprintln!("lli is not on PATH, skipping llvm_run_test");
+        return;
+    };
+
+    let source = fs::read_to_string(format!("{PREFIX}llvm_test.lux")).unwrap();
+    let module = emit_llvm("llvm_test.lux", &source).unwrap();
+    lli.stdin
+        .take()
+        .unwrap()
+        .write_all(module.as_bytes())
+        .unwrap();
+    let native = lli.wait_with_output().unwrap();
+    assert!(native.status.success(), "lli failed on:\n{module}");
+
+    let mut interpreter = Interpreter::new();
+    interpreter.captured = Some(String::new());
+    assert_eq!(
+        run_with(&mut interpreter, &source, false).unwrap(),
+        ExitCode::Okay as i32
+    );
+    assert_eq!(
+        String::from_utf8(native.stdout).unwrap(),
+        interpreter.captured.unwrap()
+    );
+}
+
+#[test]
+fn llvm_runtime_error_test() {
+    //Native runtime errors land on stderr with the same code and exit status as the interpreters,
+    //runaway recursion included
+    for (source, code, message, line) in [
+        (
+            "println(1);\nprintln(-\"x\");",
+            "E0021",
+            "Operand must be a number.",
+            2,
+        ),
+        ("fun f() { f(); }\nf();", "E0027", "Stack overflow.", 1),
+    ] {
+        let Ok(mut lli) = Command::new("lli")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        else {
+            eprintln!("lli is not on PATH, skipping llvm_runtime_error_test");
+            return;
+        };
+
+        let module = emit_llvm("error.lux", source).unwrap();
+        lli.stdin
+            .take()
+            .unwrap()
+            .write_all(module.as_bytes())
+            .unwrap();
+        let native = lli.wait_with_output().unwrap();
+        assert_eq!(
+            native.status.code(),
+            Some(ExitCode::RuntimeErr as i32),
+            "{source}"
+        );
+        let stderr = String::from_utf8(native.stderr).unwrap();
+        assert!(
+            stderr.starts_with(&format!(
+                "error[{code}]: {message}\n --> error.lux:{line}\n"
+            )),
+            "{source}: {stderr}"
+        );
+        let stdout = String::from_utf8(native.stdout).unwrap();
+        assert!(!stdout.contains("error"), "{source}: {stdout}");
+    }
+}
+
+#[test]
+fn llvm_unsupported_test() {
+    //Anything the backend can't lower is reported rather than silently miscompiled
+    for source in [
+        "class A {}",
+        "fun outer() { var a = 1; fun inner() { return a; } }",
+        "var xs = [1, 2];",
+        "var m = {\"a\": 1};",
+        "for (var c in \"abc\") {}",
+        "try { } finally { }",
+    ] {
+        assert_eq!(
+            emit_llvm("<test>", source).unwrap_err(),
+            ExitCode::DataErr as i32,
+            "{source} should not lower"
+        );
+    }
+}
@@ -2,9 +2,22 @@ mod display_traits;
 pub mod expression;
 pub mod lux_functions;
 pub mod math_traits;
+pub mod span;
 pub mod statement;
 pub mod token;
 
+use crate::diagnostics::codes::{
+    self,
+    ErrorCode,
+    Failure,
+};
+use lux_functions::map::MapKey;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::Rc,
+};
+
 //TODO: Find a replacement for Strings that allows for Copy to be implemented
 //TODO: Replace "String" with a Box<str> or Cow<str> which will reduce velocity but give memory compaction
 #[derive(Clone, Debug)]
@@ -13,6 +26,11 @@ pub(crate) enum LiteralType {
     Boolean(bool),
     String(String),
     Callable(lux_functions::Functions),
+    Instance(Rc<RefCell<lux_functions::class::LuxInstance>>),
+    ///Shared, so every variable holding the list sees changes made through any of them
+    List(Rc<RefCell<Vec<LiteralType>>>),
+    ///Shared like lists, keys are kept sorted
+    Map(Rc<RefCell<BTreeMap<lux_functions::map::MapKey, LiteralType>>>),
     Nil, //This will be wrapped in an option,
 }
 
@@ -23,12 +41,15 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Dot,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Percent,
     Question,
     Colon,
     Comma,
@@ -42,27 +63,45 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    DotDot,
+    DotDotEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
+    StarStar,
 
     //Literals
     Identifier,
     String,
+    ///The part of a string before a `${`, the embedded expression's tokens follow it
+    Interpolation,
     Number,
 
     //Keywords
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    In,
     Nil,
     Or,
     // Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -82,21 +121,96 @@ pub enum Expression {
     Assignment(Box<expression::Assignment>),
     Call(Box<expression::Call>),
     Lambda(Box<expression::Lambda>),
+    Interpolation(Box<expression::Interpolation>),
+    List(Box<expression::List>),
+    Map(Box<expression::Map>),
+    Index(Box<expression::Index>),
+    SetIndex(Box<expression::SetIndex>),
+    Compound(Box<expression::Compound>),
+    Slice(Box<expression::Slice>),
+    Get(Box<expression::Get>),
+    Set(Box<expression::Set>),
+    This(Box<expression::This>),
+    Super(Box<expression::Super>),
 }
 
 #[derive(Clone)]
 pub struct ParserError {
     pub source: token::Token,
     pub cause: String,
+    pub(crate) code: Option<&'static ErrorCode>,
 }
 
+///Raised while a program runs, aborts it and reports `message` at `span`
 #[derive(Clone, Debug)]
 pub struct RuntimeError {
-    pub source: token::Token,
+    ///Where the offending token is
+    pub span: span::Span,
+    pub message: String,
+    pub(crate) code: &'static ErrorCode,
+    ///Extra context printed below the error
+    pub notes: Vec<String>,
+    ///What a `throw` statement threw, `None` for errors raised by the runtime itself
+    pub(crate) thrown: Option<LiteralType>,
 }
 
-#[derive(Clone, Debug)]
-pub enum LuxErrors {
-    ParserError(ParserError),
-    RuntimeError(RuntimeError),
+impl RuntimeError {
+    pub(crate) fn new(
+        source: &token::Token,
+        code: &'static ErrorCode,
+        message: impl Into<String>,
+    ) -> RuntimeError {
+        RuntimeError {
+            span: source.span,
+            message: message.into(),
+            code,
+            notes: vec![],
+            thrown: None,
+        }
+    }
+
+    ///An error whose message is just the code's title
+    pub(crate) fn coded(source: &token::Token, code: &'static ErrorCode) -> RuntimeError {
+        RuntimeError::new(source, code, code.title)
+    }
+
+    ///Reports what a helper shared with the VM failed with at `source`
+    pub(crate) fn failed(source: &token::Token, failure: Failure) -> RuntimeError {
+        RuntimeError::new(source, failure.code, failure.message)
+    }
+
+    pub(crate) fn with_note(mut self, note: impl Into<String>) -> RuntimeError {
+        self.notes.push(note.into());
+        self
+    }
+
+    ///Raised by `throw`, it only reaches the user when no `catch` handles it
+    pub(crate) fn thrown(source: &token::Token, value: LiteralType) -> RuntimeError {
+        RuntimeError {
+            thrown: Some(value.clone()),
+            ..RuntimeError::new(
+                source,
+                &codes::UNCAUGHT_EXCEPTION,
+                format!("Uncaught exception: {value}"),
+            )
+        }
+    }
+
+    ///What a `catch` clause receives, the thrown value or a map describing a runtime error
+    pub(crate) fn caught(self) -> LiteralType {
+        if let Some(value) = self.thrown {
+            return value;
+        }
+
+        let mut error = BTreeMap::new();
+        error.insert(
+            MapKey::String(String::from("message")),
+            LiteralType::String(self.message),
+        );
+        error.insert(
+            MapKey::String(String::from("line")),
+            LiteralType::Number(self.span.line as f64),
+        );
+        LiteralType::Map(Rc::new(RefCell::new(error)))
+    }
 }
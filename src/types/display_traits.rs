@@ -6,6 +6,7 @@ use crate::{
 use std::{
     error::Error,
     fmt::Display as DisplayTrait,
+    rc::Rc,
 };
 
 //Token Display implementation moved to token.rs because of private field implementation
@@ -28,10 +29,69 @@ impl DisplayTrait for types::LiteralType {
             Self::String(string) => write!(f, "{string}"),
             Self::Nil => write!(f, "NIL"),
             Self::Callable(function) => write!(f, "{function}"),
+            Self::Instance(instance) => write!(f, "<{} instance>", instance.borrow().class.name),
+            Self::List(_) | Self::Map(_) => write_collection(f, self, &mut Vec::new()),
         }
     }
 }
 
+///Writes a list or map, `seen` holds the ones currently being written so one that contains itself
+///prints as `[...]` or `{...}` instead of recursing forever
+fn write_collection(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &types::LiteralType,
+    seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+    match value {
+        types::LiteralType::List(list) => {
+            let pointer = Rc::as_ptr(list) as *const ();
+            if seen.contains(&pointer) {
+                return write!(f, "[...]");
+            }
+            seen.push(pointer);
+            write!(f, "[")?;
+            for (position, element) in list.borrow().iter().enumerate() {
+                if position > 0 {
+                    write!(f, ", ")?;
+                }
+                write_nested(f, element, seen)?;
+            }
+            seen.pop();
+            write!(f, "]")
+        }
+        types::LiteralType::Map(map) => {
+            let pointer = Rc::as_ptr(map) as *const ();
+            if seen.contains(&pointer) {
+                return write!(f, "{{...}}");
+            }
+            seen.push(pointer);
+            write!(f, "{{")?;
+            for (position, (key, value)) in map.borrow().iter().enumerate() {
+                if position > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{key}: ")?;
+                write_nested(f, value, seen)?;
+            }
+            seen.pop();
+            write!(f, "}}")
+        }
+        _ => write!(f, "{value}"),
+    }
+}
+
+///Writes a value held by a list or map, strings are quoted so `["1"]` and `[1]` print differently
+fn write_nested(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &types::LiteralType,
+    seen: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+    match value {
+        types::LiteralType::String(string) => write!(f, "\"{string}\""),
+        _ => write_collection(f, value, seen),
+    }
+}
+
 impl DisplayTrait for types::lux_functions::Functions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -39,13 +99,20 @@ impl DisplayTrait for types::lux_functions::Functions {
             Self::Clock(_) => write!(f, "<fn Clock>"),
             Self::Print(_) => write!(f, "<fn Print>"),
             Self::Println(_) => write!(f, "<fn Println>"),
+            Self::Len(_) => write!(f, "<fn Len>"),
+            Self::Push(_) => write!(f, "<fn Push>"),
+            Self::Pop(_) => write!(f, "<fn Pop>"),
+            Self::Has(_) => write!(f, "<fn Has>"),
+            Self::Remove(_) => write!(f, "<fn Remove>"),
+            Self::Keys(_) => write!(f, "<fn Keys>"),
+            Self::Class(class) => write!(f, "<class {}>", class.name),
         }
     }
 }
 
 impl DisplayTrait for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Error on: {}", self.source)
+        write!(f, "Error on line {}: {}", self.span.line, self.message)
     }
 }
 impl DisplayTrait for UserFunction {
@@ -60,6 +127,9 @@ impl DisplayTrait for types::TokenType {
             Self::LeftBrace => write!(f, "Left Brace"),
             Self::RightBrace => write!(f, "Right Brace"),
 
+            Self::LeftBracket => write!(f, "Left Bracket"),
+            Self::RightBracket => write!(f, "Right Bracket"),
+
             Self::LeftParen => write!(f, "Left Parentheses"),
             Self::RightParen => write!(f, "Right Parentheses"),
 
@@ -69,6 +139,7 @@ impl DisplayTrait for types::TokenType {
             Self::Semicolon => write!(f, "Semicolon"),
             Self::Slash => write!(f, "Slash"),
             Self::Star => write!(f, "Star"),
+            Self::Percent => write!(f, "Percent"),
             Self::Question => write!(f, "Question"),
             Self::Colon => write!(f, "Colon"),
             Self::Comma => write!(f, "Comma"),
@@ -81,25 +152,42 @@ impl DisplayTrait for types::TokenType {
             Self::Greater => write!(f, "Greater"),
             Self::Less => write!(f, "Less"),
             Self::LessEqual => write!(f, "Less Equal"),
+            Self::DotDot => write!(f, "Dot Dot"),
+            Self::DotDotEqual => write!(f, "Dot Dot Equal"),
+            Self::PlusEqual => write!(f, "Plus Equal"),
+            Self::MinusEqual => write!(f, "Minus Equal"),
+            Self::StarEqual => write!(f, "Star Equal"),
+            Self::SlashEqual => write!(f, "Slash Equal"),
+            Self::PlusPlus => write!(f, "Plus Plus"),
+            Self::MinusMinus => write!(f, "Minus Minus"),
+            Self::StarStar => write!(f, "Star Star"),
 
             Self::Identifier => write!(f, "Identifier"),
             Self::String => write!(f, "String"),
+            Self::Interpolation => write!(f, "Interpolation"),
             Self::Number => write!(f, "Number"),
 
             Self::And => write!(f, "And"),
+            Self::Break => write!(f, "Break"),
+            Self::Catch => write!(f, "Catch"),
             Self::Class => write!(f, "Class"),
+            Self::Continue => write!(f, "Continue"),
             Self::Else => write!(f, "Else"),
             Self::False => write!(f, "False"),
+            Self::Finally => write!(f, "Finally"),
             Self::Fun => write!(f, "Fun"),
             Self::For => write!(f, "For"),
             Self::If => write!(f, "If"),
+            Self::In => write!(f, "In"),
             Self::Nil => write!(f, "Nil"),
             Self::Or => write!(f, "Or"),
             // Self::Print => write!(f, "Print"),
             Self::Return => write!(f, "Return"),
             Self::Super => write!(f, "Super"),
             Self::This => write!(f, "This"),
+            Self::Throw => write!(f, "Throw"),
             Self::True => write!(f, "True"),
+            Self::Try => write!(f, "Try"),
             Self::Var => write!(f, "Var"),
             Self::While => write!(f, "While"),
             Self::Eof => write!(f, "Eof"),
@@ -117,7 +205,7 @@ impl DisplayTrait for types::ParserError {
         write!(
             f,
             "Parser Error occured on Token: \n\t {:?} at line {} because {}",
-            self.source, self.source.line, self.cause
+            self.source, self.source.span.line, self.cause
         )
     }
 }
@@ -185,7 +273,52 @@ impl DisplayTrait for crate::types::Expression {
                 write!(f, "(Call: callee:{})", call.callee)
             }
             Self::Lambda(lambda) => {
-                write!(f, "(Lambda: paren:{})",lambda.paren)
+                write!(f, "(Lambda: parameters:{})", lambda.parameters.len())
+            }
+            Self::Interpolation(interpolation) => {
+                write!(f, "(Interpolation: parts:{})", interpolation.parts.len())
+            }
+            Self::List(list) => {
+                write!(f, "(List: elements:{})", list.elements.len())
+            }
+            Self::Map(map) => {
+                write!(f, "(Map: entries:{})", map.entries.len())
+            }
+            Self::Index(index) => {
+                write!(f, "(Index: object:{}, index:{})", index.object, index.index)
+            }
+            Self::SetIndex(set) => {
+                write!(
+                    f,
+                    "(SetIndex: object:{}, index:{}, value:{})",
+                    set.object, set.index, set.value
+                )
+            }
+            Self::Compound(compound) => {
+                write!(
+                    f,
+                    "(Compound: target:{}, operator:{}, value:{})",
+                    compound.target, compound.operator, compound.value
+                )
+            }
+            Self::Slice(slice) => {
+                write!(f, "(Slice: object:{})", slice.object)
+            }
+            Self::Get(get) => {
+                write!(f, "(Get: object:{}, name:{})", get.object, get.name)
+            }
+            Self::Set(set) => {
+                write!(
+                    f,
+                    "(Set: object:{}, name:{}, value:{})",
+                    set.object, set.name, set.value
+                )
+            }
+            Self::This(this) => {
+                write!(f, "(This: keyword:{})", this.keyword)
+            }
+            Self::Super(sup) => {
+                write!(f, "(Super: method:{})", sup.method)
             }
         }
     }
@@ -239,6 +372,20 @@ impl DisplayTrait for crate::types::statement::Statement {
             Self::Function(func) => {
                 write!(f, "(Func declaration: {})", func.name)
             }
+            Self::Class(class) => {
+                write!(f, "(Class declaration: {})", class.name)
+            }
+            Self::ForIn(for_in) => {
+                write!(
+                    f,
+                    "(For In Statement: name: {}, body: {})",
+                    for_in.name, for_in.body
+                )
+            }
+            Self::Break(_) => write!(f, "(Break Statement)"),
+            Self::Continue(_) => write!(f, "(Continue Statement)"),
+            Self::Try(_) => write!(f, "(Try Statement)"),
+            Self::Throw(throw) => write!(f, "(Throw Statement: {})", throw.value),
             _ => todo!("Unimplemented Display on Statement"),
         }
     }
@@ -1,4 +1,5 @@
 use super::{
+    span::Span,
     token::Token,
     *,
 };
@@ -6,6 +7,8 @@ use super::{
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Ternary {
     pub(crate) evaluator: Expression,
+    ///The `?`, where a non-boolean evaluator is reported
+    pub(crate) question: Token,
     pub(crate) left: Expression,
     pub(crate) right: Expression,
 }
@@ -13,6 +16,7 @@ pub(crate) struct Ternary {
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Literal {
     pub(crate) value: LiteralType,
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -31,17 +35,29 @@ pub struct Binary {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Grouping {
     pub(crate) expression: Expression,
+    ///Includes both parentheses
+    pub(crate) span: Span,
+}
+
+///Where a local variable lives, filled in by the resolver: `depth` enviroments up the chain from
+///the one executing, at index `slot` within it. Globals are left unresolved
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Binding {
+    pub(crate) depth: usize,
+    pub(crate) slot: usize,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Variable {
     pub(crate) name: Token,
+    pub(crate) binding: Option<Binding>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Assignment {
     pub(crate) name: Token,
     pub(crate) value: Expression,
+    pub(crate) binding: Option<Binding>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -59,8 +75,114 @@ pub struct Call {
     pub(crate) arguments: Vec<Expression>,
 }
 
+///Anonymous function, `fun (a, b) { ... }`
 #[derive(Clone, Debug, PartialEq)]
 pub struct Lambda {
-    pub(crate) paren: Token,
-    pub(crate) arguments: Vec<Expression>,
+    pub(crate) keyword: Token,
+    pub(crate) parameters: Vec<Token>,
+    pub(crate) body: Vec<statement::Statement>,
+    ///From `fun` to the closing brace
+    pub(crate) span: Span,
+}
+
+///String with embedded expressions, `"Hello ${name}!"`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interpolation {
+    ///The literal pieces and the embedded expressions, in order, each is formatted and joined
+    pub(crate) parts: Vec<Expression>,
+    ///From the opening to the closing quote
+    pub(crate) span: Span,
+}
+
+///List literal, `[1, 2, 3]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct List {
+    pub(crate) elements: Vec<Expression>,
+    ///The opening `[`
+    pub(crate) bracket: Token,
+    ///From `[` to `]`
+    pub(crate) span: Span,
+}
+
+///Map literal, `{"a": 1, "b": 2}`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    ///Key and value expressions in the order they were written
+    pub(crate) entries: Vec<(Expression, Expression)>,
+    ///The opening `{`
+    pub(crate) brace: Token,
+    ///From `{` to `}`
+    pub(crate) span: Span,
+}
+
+///Reads one element of a list or the value under a map key, `object[index]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    pub(crate) object: Expression,
+    pub(crate) index: Expression,
+    ///The closing `]`, errors are reported here
+    pub(crate) bracket: Token,
+}
+
+///Replaces one element of a list or the value under a map key, `object[index] = value`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetIndex {
+    pub(crate) object: Expression,
+    pub(crate) index: Expression,
+    pub(crate) bracket: Token,
+    pub(crate) value: Expression,
+}
+
+///Reads a variable, property or element, combines it with `value` and writes the result back,
+///`x += value`, `++x` and `x--`. The target's object and index are evaluated once
+#[derive(Clone, Debug, PartialEq)]
+pub struct Compound {
+    ///A `Variable`, `Get` or `Index` expression
+    pub(crate) target: Expression,
+    ///The binary operator applied, spanning the `+=`, `++` or `--` that was written
+    pub(crate) operator: Token,
+    pub(crate) value: Expression,
+    ///Evaluates to the old value instead of the new one, `x++`
+    pub(crate) postfix: bool,
+    ///Written as `++` or `--`, which only apply to numbers
+    pub(crate) step: bool,
+}
+
+///Copies part of a list, `object[start:end]`, either bound can be left out
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slice {
+    pub(crate) object: Expression,
+    pub(crate) start: Option<Expression>,
+    pub(crate) end: Option<Expression>,
+    ///The closing `]`, errors are reported here
+    pub(crate) bracket: Token,
+}
+
+///Property access on an instance, `object.name`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Get {
+    pub(crate) object: Expression,
+    pub(crate) name: Token,
+}
+
+///Property assignment on an instance, `object.name = value`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Set {
+    pub(crate) object: Expression,
+    pub(crate) name: Token,
+    pub(crate) value: Expression,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct This {
+    pub(crate) keyword: Token,
+    pub(crate) binding: Option<Binding>,
+}
+
+///Superclass method lookup, `super.method`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Super {
+    pub(crate) keyword: Token,
+    pub(crate) method: Token,
+    pub(crate) binding: Option<Binding>,
 }
@@ -1,7 +1,17 @@
-use super::Expression;
-use crate::interpreter::Interpreter;
+use super::{
+    LiteralType,
+    token::Token,
+};
+use crate::interpreter::{
+    Interpreter,
+    RuntimeResult,
+};
+use std::rc::Rc;
 
+pub(crate) mod class;
 pub(crate) mod clock;
+pub(crate) mod list;
+pub(crate) mod map;
 pub(crate) mod print;
 pub(crate) mod user;
 
@@ -9,8 +19,9 @@ pub(crate) trait Callable {
     fn call(
         &mut self,
         interpreter: &mut Interpreter,
-        arguments: Vec<Expression>,
-    ) -> Option<Expression>;
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType>;
     fn arity(&self) -> u64;
 }
 
@@ -19,5 +30,12 @@ pub(crate) enum Functions {
     Clock(clock::Clock),
     Print(print::Print),
     Println(print::Println),
+    Len(list::Len),
+    Push(list::Push),
+    Pop(list::Pop),
+    Has(map::Has),
+    Remove(map::Remove),
+    Keys(map::Keys),
     User(user::UserFunction),
+    Class(Rc<class::LuxClass>),
 }
@@ -0,0 +1,116 @@
+use super::{
+    Callable,
+    Interpreter,
+    user::UserFunction,
+};
+use crate::{
+    interpreter::RuntimeResult,
+    types::{
+        LiteralType,
+        token::Token,
+    },
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{
+        Debug,
+        Formatter,
+    },
+    rc::Rc,
+};
+
+/// Runtime representation of a `class` declaration, calling it constructs a new instance
+pub(crate) struct LuxClass {
+    pub(crate) name: String,
+    pub(crate) superclass: Option<Rc<LuxClass>>,
+    pub(crate) methods: HashMap<String, UserFunction>,
+}
+
+impl LuxClass {
+    ///Looks up a method on this class, then walks up the inheritance chain
+    pub(crate) fn find_method(&self, name: &str) -> Option<&UserFunction> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(method);
+        }
+
+        if let Some(ref superclass) = self.superclass {
+            superclass.find_method(name)
+        } else {
+            None
+        }
+    }
+}
+
+///Classes are only ever equal to themselves
+impl PartialEq for LuxClass {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for LuxClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "LuxClass: {}", self.name)
+    }
+}
+
+impl Callable for Rc<LuxClass> {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let instance = Rc::new(RefCell::new(LuxInstance {
+            class: Rc::clone(self),
+            fields: HashMap::new(),
+        }));
+
+        //Run the constructor if one was declared, it always returns the instance
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(Rc::clone(&instance))
+                .call(interpreter, paren, arguments)?;
+        }
+
+        Ok(LiteralType::Instance(instance))
+    }
+
+    fn arity(&self) -> u64 {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
+    }
+}
+
+/// A single object created by calling a class
+pub(crate) struct LuxInstance {
+    pub(crate) class: Rc<LuxClass>,
+    pub(crate) fields: HashMap<String, LiteralType>,
+}
+
+impl LuxInstance {
+    ///Fields shadow methods, methods are bound to the instance they are pulled off of
+    pub(crate) fn get(instance: &Rc<RefCell<LuxInstance>>, name: &str) -> Option<LiteralType> {
+        let borrowed = instance.borrow();
+        if let Some(field) = borrowed.fields.get(name) {
+            return Some(field.clone());
+        }
+
+        borrowed.class.find_method(name).map(|method| {
+            LiteralType::Callable(super::Functions::User(method.bind(Rc::clone(instance))))
+        })
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: LiteralType) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
+
+impl Debug for LuxInstance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "LuxInstance: {}", self.class.name)
+    }
+}
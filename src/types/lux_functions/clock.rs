@@ -1,13 +1,12 @@
-use super::{
-    Callable,
-    Expression,
-};
+use super::Callable;
 use crate::{
-    interpreter::Interpreter,
+    interpreter::{
+        Interpreter,
+        RuntimeResult,
+    },
     types::{
-        Expression::Literal,
         LiteralType,
-        expression::Literal as LiteralStruct,
+        token::Token,
     },
 };
 use std::time::SystemTime;
@@ -20,16 +19,15 @@ impl Callable for Clock {
     fn call(
         &mut self,
         interpreter: &mut Interpreter,
-        arguments: Vec<Expression>,
-    ) -> Option<Expression> {
-        Some(Literal(Box::new(LiteralStruct {
-            value: LiteralType::Number(
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .expect("Died getting Unix Time")
-                    .as_secs() as f64,
-            ),
-        })))
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        Ok(LiteralType::Number(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("Died getting Unix Time")
+                .as_secs() as f64,
+        ))
     }
     fn arity(&self) -> u64 {
         0
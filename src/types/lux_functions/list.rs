@@ -0,0 +1,148 @@
+use super::Callable;
+use crate::{
+    diagnostics::codes::{
+        self,
+        Failure,
+    },
+    interpreter::{
+        Interpreter,
+        RuntimeResult,
+    },
+    types::{
+        LiteralType,
+        RuntimeError,
+        token::Token,
+    },
+};
+use std::ops::Range;
+
+///Turns an index into a position in a list of `length` elements, both backends share the checks
+pub(crate) fn position(index: f64, length: usize) -> Result<usize, Failure> {
+    if index.fract() != 0.0 || index < 0.0 {
+        return Err(Failure::new(
+            &codes::INVALID_INDEX,
+            format!("List index must be a whole number that isn't negative, got {index}."),
+        ));
+    }
+    if index >= length as f64 {
+        return Err(Failure::new(
+            &codes::INDEX_OUT_OF_RANGE,
+            format!("Index {index} is out of range for a list of length {length}."),
+        ));
+    }
+    Ok(index as usize)
+}
+
+///The elements `list[start:end]` copies, a missing bound means the matching end of the list
+pub(crate) fn slice_bounds(
+    start: Option<f64>,
+    end: Option<f64>,
+    length: usize,
+) -> Result<Range<usize>, Failure> {
+    let (start, end) = (start.unwrap_or(0.0), end.unwrap_or(length as f64));
+    for bound in [start, end] {
+        if bound.fract() != 0.0 || bound < 0.0 {
+            return Err(Failure::new(
+                &codes::INVALID_INDEX,
+                format!("Slice bounds must be whole numbers that aren't negative, got {bound}."),
+            ));
+        }
+    }
+    if start > end || end > length as f64 {
+        return Err(Failure::new(
+            &codes::INDEX_OUT_OF_RANGE,
+            format!("Slice {start}:{end} is out of range for a list of length {length}."),
+        ));
+    }
+    Ok(start as usize..end as usize)
+}
+
+///Number of elements in a list, entries in a map or characters in a string
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Len {}
+
+#[allow(unused_variables)]
+impl Callable for Len {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        match &arguments[0] {
+            LiteralType::List(list) => Ok(LiteralType::Number(list.borrow().len() as f64)),
+            LiteralType::Map(map) => Ok(LiteralType::Number(map.borrow().len() as f64)),
+            LiteralType::String(string) => Ok(LiteralType::Number(string.chars().count() as f64)),
+            _ => Err(RuntimeError::new(
+                paren,
+                &codes::INVALID_ARGUMENT,
+                "len expects a list, map or string.",
+            )),
+        }
+    }
+    fn arity(&self) -> u64 {
+        1
+    }
+}
+
+///Appends to the end of a list in place
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Push {}
+
+#[allow(unused_variables)]
+impl Callable for Push {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        mut arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let value = arguments.pop().expect("Arity guarantees two arguments");
+        match &arguments[0] {
+            LiteralType::List(list) => {
+                list.borrow_mut().push(value);
+                Ok(LiteralType::Nil)
+            }
+            _ => Err(RuntimeError::new(
+                paren,
+                &codes::INVALID_ARGUMENT,
+                "push expects a list.",
+            )),
+        }
+    }
+    fn arity(&self) -> u64 {
+        2
+    }
+}
+
+///Removes and returns the last element of a list
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Pop {}
+
+#[allow(unused_variables)]
+impl Callable for Pop {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        match &arguments[0] {
+            LiteralType::List(list) => list.borrow_mut().pop().ok_or_else(|| {
+                RuntimeError::new(
+                    paren,
+                    &codes::INVALID_ARGUMENT,
+                    "Can't pop from an empty list.",
+                )
+            }),
+            _ => Err(RuntimeError::new(
+                paren,
+                &codes::INVALID_ARGUMENT,
+                "pop expects a list.",
+            )),
+        }
+    }
+    fn arity(&self) -> u64 {
+        1
+    }
+}
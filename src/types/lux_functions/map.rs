@@ -0,0 +1,192 @@
+use super::Callable;
+use crate::{
+    diagnostics::codes::{
+        self,
+        Failure,
+    },
+    interpreter::{
+        Interpreter,
+        RuntimeResult,
+    },
+    types::{
+        LiteralType,
+        RuntimeError,
+        token::Token,
+    },
+};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::Display,
+    rc::Rc,
+};
+
+///The values a map can be keyed by, both backends convert into this so keys sort the same way
+#[derive(Clone, Debug)]
+pub(crate) enum MapKey {
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+
+impl MapKey {
+    ///`0` and `-0` are the same number, so they have to be the same key
+    pub(crate) fn number(number: f64) -> MapKey {
+        MapKey::Number(if number == 0.0 { 0.0 } else { number })
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            MapKey::Boolean(_) => 0,
+            MapKey::Number(_) => 1,
+            MapKey::String(_) => 2,
+        }
+    }
+}
+
+///Keys sort booleans first, then numbers, then strings, which is the order maps iterate in
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MapKey::Boolean(left), MapKey::Boolean(right)) => left.cmp(right),
+            (MapKey::Number(left), MapKey::Number(right)) => left.total_cmp(right),
+            (MapKey::String(left), MapKey::String(right)) => left.cmp(right),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+///Quoted like strings inside a list, so error messages show which key was missing
+impl Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapKey::Boolean(boolean) => write!(f, "{boolean}"),
+            MapKey::Number(number) => write!(f, "{number}"),
+            MapKey::String(string) => write!(f, "\"{string}\""),
+        }
+    }
+}
+
+impl TryFrom<&LiteralType> for MapKey {
+    type Error = Failure;
+    fn try_from(value: &LiteralType) -> Result<Self, Self::Error> {
+        match value {
+            LiteralType::Boolean(boolean) => Ok(MapKey::Boolean(*boolean)),
+            LiteralType::Number(number) => Ok(MapKey::number(*number)),
+            LiteralType::String(string) => Ok(MapKey::String(string.clone())),
+            _ => Err(Failure::from(&codes::INVALID_KEY)),
+        }
+    }
+}
+
+impl From<&MapKey> for LiteralType {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Boolean(boolean) => LiteralType::Boolean(*boolean),
+            MapKey::Number(number) => LiteralType::Number(*number),
+            MapKey::String(string) => LiteralType::String(string.clone()),
+        }
+    }
+}
+
+pub(crate) fn missing(key: &MapKey) -> Failure {
+    Failure::new(&codes::MISSING_KEY, format!("Key {key} isn't in the map."))
+}
+
+fn map_operand<'a>(
+    name: &str,
+    argument: &'a LiteralType,
+    paren: &Token,
+) -> RuntimeResult<&'a Rc<RefCell<BTreeMap<MapKey, LiteralType>>>> {
+    match argument {
+        LiteralType::Map(map) => Ok(map),
+        _ => Err(RuntimeError::new(
+            paren,
+            &codes::INVALID_ARGUMENT,
+            format!("{name} expects a map."),
+        )),
+    }
+}
+
+fn key_operand(argument: &LiteralType, paren: &Token) -> RuntimeResult<MapKey> {
+    MapKey::try_from(argument).map_err(|failure| RuntimeError::failed(paren, failure))
+}
+
+///Whether a map holds a key
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Has {}
+
+#[allow(unused_variables)]
+impl Callable for Has {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let map = map_operand("has", &arguments[0], paren)?;
+        let key = key_operand(&arguments[1], paren)?;
+        Ok(LiteralType::Boolean(map.borrow().contains_key(&key)))
+    }
+    fn arity(&self) -> u64 {
+        2
+    }
+}
+
+///Deletes a key from a map, returning the value it held
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Remove {}
+
+#[allow(unused_variables)]
+impl Callable for Remove {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let map = map_operand("remove", &arguments[0], paren)?;
+        let key = key_operand(&arguments[1], paren)?;
+        let removed = map.borrow_mut().remove(&key);
+        removed.ok_or_else(|| RuntimeError::failed(paren, missing(&key)))
+    }
+    fn arity(&self) -> u64 {
+        2
+    }
+}
+
+///A new list of a map's keys in iteration order
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Keys {}
+
+#[allow(unused_variables)]
+impl Callable for Keys {
+    fn call(
+        &mut self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let map = map_operand("keys", &arguments[0], paren)?;
+        let keys = map.borrow().keys().map(LiteralType::from).collect();
+        Ok(LiteralType::List(Rc::new(RefCell::new(keys))))
+    }
+    fn arity(&self) -> u64 {
+        1
+    }
+}
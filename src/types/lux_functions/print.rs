@@ -1,25 +1,29 @@
-use crate::types::{
-    Expression,
-    lux_functions::{
-        Callable,
-        Interpreter,
+use crate::{
+    interpreter::RuntimeResult,
+    types::{
+        LiteralType,
+        lux_functions::{
+            Callable,
+            Interpreter,
+        },
+        token::Token,
     },
 };
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Print {}
 
+#[allow(unused_variables)]
 impl Callable for Print {
     fn call(
         &mut self,
         interpreter: &mut Interpreter,
-        arguments: Vec<Expression>,
-    ) -> Option<Expression> {
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
         //We are garunteeing that only 1 argument will ever be passed
-        let mut arg = arguments[0].clone();
-        let printable: String = interpreter.evaluate(&mut arg).to_string();
-        print!("{printable}");
-        None
+        interpreter.output(&arguments[0].to_string());
+        Ok(LiteralType::Nil)
     }
     fn arity(&self) -> u64 {
         1
@@ -29,17 +33,17 @@ impl Callable for Print {
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) struct Println {}
 
+#[allow(unused_variables)]
 impl Callable for Println {
     fn call(
         &mut self,
         interpreter: &mut Interpreter,
-        arguments: Vec<Expression>,
-    ) -> Option<Expression> {
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
         //We are garunteeing that only 1 argument will ever be passed
-        let mut arg = arguments[0].clone();
-        let printable: String = interpreter.evaluate(&mut arg).to_string();
-        println!("{printable}");
-        None
+        interpreter.output(&format!("{}\n", arguments[0]));
+        Ok(LiteralType::Nil)
     }
     fn arity(&self) -> u64 {
         1
@@ -1,34 +1,47 @@
 use super::{
     Callable,
     Interpreter,
+    class::LuxInstance,
 };
 use crate::{
-    enviroment::Enviroment,
+    diagnostics::codes,
+    enviroment::{
+        Enviroment,
+        EnviromentRef,
+    },
+    interpreter::{
+        Completion,
+        RuntimeResult,
+    },
+    ir::vm::FRAMES_MAX,
     types::{
-        lux_functions::Functions,
         statement::*,
+        token::Token,
         *,
     },
 };
 use std::{
-    collections::HashMap,
+    cell::RefCell,
     fmt::{
         Debug,
         Formatter,
     },
-    panic::catch_unwind,
+    rc::Rc,
 };
 
 #[derive(Clone)]
 /// This is the User-Defined Function Capability and encapsulates all non-native functions
 pub(crate) struct UserFunction {
-    pub(crate) closure: Enviroment,
+    pub(crate) closure: EnviromentRef,
     pub(crate) declaration: Box<FunctionStatement>,
+    ///Set on a class's `init` method so calls always hand back `this`
+    pub(crate) is_initializer: bool,
 }
 
 impl PartialEq for UserFunction {
     fn eq(&self, other: &Self) -> bool {
-        self.declaration == other.declaration
+        //Two closures over one declaration are still different functions
+        Rc::ptr_eq(&self.closure, &other.closure) && self.declaration == other.declaration
     }
 }
 
@@ -38,61 +51,62 @@ impl Debug for UserFunction {
     }
 }
 
+impl UserFunction {
+    ///Produces a copy of this method whose enclosing enviroment has `this` set to `instance`
+    pub(crate) fn bind(&self, instance: Rc<RefCell<LuxInstance>>) -> UserFunction {
+        let enviroment = Enviroment::new(Some(Rc::clone(&self.closure)));
+        enviroment
+            .borrow_mut()
+            .define("this", LiteralType::Instance(instance));
+
+        UserFunction {
+            closure: enviroment,
+            declaration: self.declaration.clone(),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+#[allow(unused_variables)]
 impl Callable for UserFunction {
     fn call(
         &mut self,
         interpreter: &mut Interpreter,
-        mut arguments: Vec<Expression>,
-    ) -> Option<Expression> {
-        //Enabling Recursion
-        let function = self.clone();
-        let (params, body, function_name) = (
-            &self.declaration.parameters,
-            self.declaration.body.clone(),
-            &self.declaration.name.lexeme,
-        );
+        paren: &Token,
+        arguments: Vec<LiteralType>,
+    ) -> RuntimeResult<LiteralType> {
+        let (params, mut body) = (&self.declaration.parameters, self.declaration.body.clone());
 
-        let mut enviroment: Enviroment = Enviroment {
-            enclosing: Some(Box::new(function.closure.clone())),
-            variable_map: HashMap::new(),
-        };
-
-        for i in 0..params.len() {
-            enviroment.define(&params[i].lexeme, interpreter.evaluate(&mut arguments[i]));
+        //Each call gets a fresh scope on top of the live enviroment the function closed over,
+        //recursion works because the function is already defined in that enviroment
+        let enviroment = Enviroment::new(Some(Rc::clone(&self.closure)));
+        for (param, argument) in params.iter().zip(arguments) {
+            enviroment.borrow_mut().define(&param.lexeme, argument);
         }
 
-        let function = Functions::User(function);
-        //Define this function in it's own enviroment
-        enviroment.define(function_name, LiteralType::Callable(function));
-
-        // let ret = Interpreter::execute_block_in_env( body.clone(), function_enviroment);
-        let ret = catch_unwind(|| Interpreter::execute_block_in_env(body, enviroment));
+        //The script itself is the VM's first frame, so its limit counts one call fewer
+        if interpreter.depth + 1 == FRAMES_MAX {
+            return Err(RuntimeError::coded(paren, &codes::STACK_OVERFLOW));
+        }
+        interpreter.depth += 1;
+        let completion = interpreter.execute_block_in_env(&mut body, enviroment);
+        interpreter.depth -= 1;
 
-        match ret {
-            Err(thing) => {
-                if thing.is::<crate::types::statement::Statement>() {
-                    if let Ok(return_statement) =
-                        thing.downcast::<crate::types::statement::Statement>()
-                    {
-                        match *return_statement {
-                            Statement::Return(ret_val) => ret_val.value,
-                            _ => None,
-                        }
-                    } else {
-                        panic!("Not a Return Statement")
-                    }
-                } else {
-                    panic!("Uncaught")
-                }
-            }
-            Ok(value) => {
-                // println!("Returned nothing");
-                if value.is_some() {
-                    panic!("Returned Nothing from function that has a value");
-                }
-                value
+        let returned = match completion? {
+            Completion::Return(value) => value,
+            Completion::Normal => LiteralType::Nil,
+            Completion::Break(_) | Completion::Continue(_) => {
+                unreachable!("The parser rejects break and continue outside of a loop")
             }
+        };
+
+        //Initializers always hand back the instance they were bound to
+        if self.is_initializer {
+            //`this` is the only thing in the enviroment `bind` created
+            return Ok(self.closure.borrow().get_at(0, 0));
         }
+
+        Ok(returned)
     }
 
     fn arity(&self) -> u64 {
@@ -1,10 +1,15 @@
 use crate::{
+    diagnostics::codes::{
+        self,
+        ErrorCode,
+    },
     macros::boolean_op,
     types::{
         LiteralType,
         TokenType,
     },
 };
+use std::rc::Rc;
 /*
  * Nested Matches v. Tuple Matching
  *
@@ -19,26 +24,11 @@ macro_rules! number_op {
     ($self:expr, $rhs:expr, $op:tt) => {
         match $self {
             Self::Number(left_num) => match $rhs {
-                Self::Number(right_num) => return LiteralType::Number(left_num $op right_num),
-                _ => {
-                    eprintln!(
-                        "Error: Type Mismatch! \n\tReturned \"{}\" from {}!",
-                        $self,
-                        stringify!($op)
-                    );
-                    return LiteralType::Number(left_num);
-                },
+                Self::Number(right_num) => Ok(LiteralType::Number(left_num $op right_num)),
+                _ => Err(&codes::OPERANDS_MUST_BE_NUMBERS),
             },
-            _ => {
-                eprintln!(
-                "Error: Type Mismatch! \n\tReturned \"{}\" from {}!",
-                $self,
-                stringify!($op)
-            );
-            return $self;
-        },
+            _ => Err(&codes::OPERANDS_MUST_BE_NUMBERS),
         }
-
     };
 }
 
@@ -51,55 +41,40 @@ impl PartialEq for TokenType {
 
 ///Add Literals Together if possible, concatonate if string
 impl std::ops::Add for LiteralType {
-    type Output = LiteralType;
+    type Output = Result<LiteralType, &'static ErrorCode>;
     fn add(self, rhs: Self) -> Self::Output {
         match self {
             /*
              * if left is number and right is number, add them together
              */
             Self::Number(left_num) => match rhs {
-                Self::Number(right_num) => LiteralType::Number(left_num + right_num),
-                _ => {
-                    eprintln!(
-                        "Error: Type Mismatch! \n\tReturned {left_num} from a Number while trying to add!"
-                    );
-                    LiteralType::Number(left_num)
-                }
+                Self::Number(right_num) => Ok(LiteralType::Number(left_num + right_num)),
+                _ => Err(&codes::INVALID_ADDITION),
             },
 
             /*
              *if left is String and Right is string, concatonate
              */
             Self::String(left_string) => match rhs {
-                Self::String(right_string) => LiteralType::String(left_string + &right_string),
-                Self::Boolean(boolean) => LiteralType::String(left_string + &boolean.to_string()),
-                Self::Number(num) => LiteralType::String(left_string + &num.to_string()),
-                Self::Nil => LiteralType::String(left_string + &Self::Nil.to_string()),
-                Self::Callable(function) => {
-                    eprintln!(
-                        "Error: Type Mismatch! \n\tCannot add {function:?} function to {left_string}!"
-                    );
-                    LiteralType::String(String::from("Function"))
+                Self::String(right_string) => Ok(LiteralType::String(left_string + &right_string)),
+                Self::Boolean(boolean) => {
+                    Ok(LiteralType::String(left_string + &boolean.to_string()))
+                }
+                Self::Number(num) => Ok(LiteralType::String(left_string + &num.to_string())),
+                Self::Nil => Ok(LiteralType::String(left_string + &Self::Nil.to_string())),
+                Self::Callable(_) | Self::Instance(_) | Self::List(_) | Self::Map(_) => {
+                    Err(&codes::INVALID_CONCATENATION)
                 }
             },
 
-            /*
-             *   Return yourself if something unexpected happens.
-             *   This give all programs written in rlux a little more resilience at the cost of predicatbility.
-             */
-            _ => {
-                eprintln!(
-                    "Error: Type Mismatch! \n\tReturned \"{self}\" while trying to add!"
-                );
-                self
-            }
+            _ => Err(&codes::INVALID_ADDITION),
         }
     }
 }
 
 ///Subtract two literals, if possible
 impl std::ops::Sub for LiteralType {
-    type Output = LiteralType;
+    type Output = Result<LiteralType, &'static ErrorCode>;
     fn sub(self, rhs: Self) -> Self::Output {
         number_op!(self,rhs, -)
     }
@@ -107,7 +82,7 @@ impl std::ops::Sub for LiteralType {
 
 ///Multiplies two literal nums together
 impl std::ops::Mul for LiteralType {
-    type Output = LiteralType;
+    type Output = Result<LiteralType, &'static ErrorCode>;
     fn mul(self, rhs: Self) -> Self::Output {
         //if left is number and right is number, multiply them together
         number_op!(self,rhs,*)
@@ -116,134 +91,148 @@ impl std::ops::Mul for LiteralType {
 
 ///Divide literals if possible
 impl std::ops::Div for LiteralType {
-    type Output = LiteralType;
+    type Output = Result<LiteralType, &'static ErrorCode>;
     fn div(self, rhs: Self) -> Self::Output {
         number_op!(self,rhs,/)
     }
 }
 
+///Remainder of dividing two literal nums, takes the sign of the left operand
+impl std::ops::Rem for LiteralType {
+    type Output = Result<LiteralType, &'static ErrorCode>;
+    fn rem(self, rhs: Self) -> Self::Output {
+        number_op!(self,rhs,%)
+    }
+}
+
+///Raises a literal num to the power of another, `**` has no `std::ops` trait
+impl LiteralType {
+    pub(crate) fn power(self, rhs: Self) -> Result<LiteralType, &'static ErrorCode> {
+        match (self, rhs) {
+            (Self::Number(base), Self::Number(exponent)) => {
+                Ok(LiteralType::Number(base.powf(exponent)))
+            }
+            _ => Err(&codes::OPERANDS_MUST_BE_NUMBERS),
+        }
+    }
+}
+
 /// ==, != is derived
 impl PartialEq for LiteralType {
-    /// For each path we extract both values and directly compare them to one another via Rust
+    /// For each path we extract both values and directly compare them to one another via Rust,
+    /// values of different types are never equal
     fn eq(&self, other: &Self) -> bool {
-        //How does this all make perfect sense to me?
-        //also how did I manage to exhaust all the paths by emulating C?
-        //
         //This approach also alerts me when a new type needs to be added
         match self {
             Self::Boolean(left_boolean) => match other {
                 Self::Boolean(right_boolean) => *left_boolean == *right_boolean,
-                _ => {
-                    /*Type Mismatch*/
-                    println!(
-                        "Error: Type Mismatch! \n\tReturned false from a boolean while trying to check equality!"
-                    );
-                    false
-                }
+                _ => false,
             },
             Self::Number(left_num) => match other {
                 Self::Number(right_num) => *left_num == *right_num,
-                _ => {
-                    /*Type Mismatch*/
-                    println!(
-                        "Error: Type Mismatch! \n\tReturned false from a Number while trying to check equality!"
-                    );
-                    false
-                }
+                _ => false,
             },
             //Rust has String comparison built-in?
-            Self::String(left_string) => {
-                match other {
-                    Self::String(right_string) => *left_string == *right_string,
-                    _ => {
-                        /*Type Mismatch*/
-                        eprintln!(
-                            "Error: Type Mismatch! \n\tReturned false from a String while trying to check equality!"
-                        );
-                        false
-                    }
-                }
-            }
+            Self::String(left_string) => match other {
+                Self::String(right_string) => *left_string == *right_string,
+                _ => false,
+            },
             //If both are Nil, true else false
             Self::Nil => matches!(other, Self::Nil),
-            Self::Callable(left_function) => {
-                if let Self::Callable(right_function) = other {
-                    *left_function == *right_function
-                } else {
-                    /*Type Mismatch*/
-                    eprintln!(
-                        "Error: Type Mismatch! \n\tReturned false from a String while trying to check equality!"
-                    );
-                    false
+            Self::Callable(left_function) => match other {
+                Self::Callable(right_function) => *left_function == *right_function,
+                _ => false,
+            },
+            //Instances are only equal when they are the same object
+            Self::Instance(left_instance) => match other {
+                Self::Instance(right_instance) => {
+                    std::rc::Rc::ptr_eq(left_instance, right_instance)
                 }
-            }
+                _ => false,
+            },
+            //Lists compare element by element, a list always equals itself
+            Self::List(_) => match other {
+                Self::List(_) => collections_equal(self, other, &mut Vec::new()),
+                _ => false,
+            },
+            //Maps are equal when they hold the same keys with equal values
+            Self::Map(_) => match other {
+                Self::Map(_) => collections_equal(self, other, &mut Vec::new()),
+                _ => false,
+            },
         }
     }
 }
 
-///Type Casting from Literal to Rust boolean
-impl From<LiteralType> for bool {
-    fn from(value: LiteralType) -> Self {
-        match value {
-            LiteralType::Boolean(ret) => ret,
-            _ => panic!("Coecercing Non-Bool into Bool"),
-        }
+///>=, >, <=, <, only numbers are ordered
+impl LiteralType {
+    pub(crate) fn greater(&self, other: &Self) -> Result<LiteralType, &'static ErrorCode> {
+        boolean_op!(self, other, >)
     }
-}
-///Type casting from literal to Rust float
-impl From<LiteralType> for f64 {
-    fn from(value: LiteralType) -> Self {
-        match value {
-            LiteralType::Number(number) => number,
-            _ => panic!("Coecercing Non-number into Number"),
-        }
+    pub(crate) fn greater_equal(&self, other: &Self) -> Result<LiteralType, &'static ErrorCode> {
+        boolean_op!(self, other, >=)
     }
-}
-
-///Type casting from Literal to Rust String
-impl From<LiteralType> for String {
-    fn from(value: LiteralType) -> Self {
-        match value {
-            LiteralType::String(stringy) => stringy,
-            _ => panic!("Coecercing Non-string into string"),
-        }
+    pub(crate) fn less(&self, other: &Self) -> Result<LiteralType, &'static ErrorCode> {
+        boolean_op!(self, other, <)
+    }
+    pub(crate) fn less_equal(&self, other: &Self) -> Result<LiteralType, &'static ErrorCode> {
+        boolean_op!(self, other, <=)
     }
 }
 
-///>=, >, <=, <
-impl PartialOrd for LiteralType {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-    //Only numbers are ordered
-    fn ge(&self, other: &Self) -> bool {
-        boolean_op!(self,other, >=)
-    }
-    fn gt(&self, other: &Self) -> bool {
-        boolean_op!(self,other, >)
-    }
-    fn le(&self, other: &Self) -> bool {
-        boolean_op!(self,other, <=)
-    }
-    fn lt(&self, other: &Self) -> bool {
-        boolean_op!(self,other, <)
+///Compares lists element by element and maps entry by entry, `comparing` holds the pairs already
+///being compared so collections that contain themselves finish instead of recursing forever, a
+///repeated pair adds no difference
+fn collections_equal(
+    left: &LiteralType,
+    right: &LiteralType,
+    comparing: &mut Vec<(*const (), *const ())>,
+) -> bool {
+    match (left, right) {
+        (LiteralType::List(left_list), LiteralType::List(right_list)) => compare_once(
+            Rc::as_ptr(left_list),
+            Rc::as_ptr(right_list),
+            comparing,
+            |comparing| {
+                let (left_list, right_list) = (left_list.borrow(), right_list.borrow());
+                left_list.len() == right_list.len()
+                    && left_list
+                        .iter()
+                        .zip(right_list.iter())
+                        .all(|(left, right)| collections_equal(left, right, comparing))
+            },
+        ),
+        (LiteralType::Map(left_map), LiteralType::Map(right_map)) => compare_once(
+            Rc::as_ptr(left_map),
+            Rc::as_ptr(right_map),
+            comparing,
+            |comparing| {
+                let (left_map, right_map) = (left_map.borrow(), right_map.borrow());
+                left_map.len() == right_map.len()
+                    && left_map.iter().zip(right_map.iter()).all(
+                        |((left_key, left), (right_key, right))| {
+                            left_key == right_key && collections_equal(left, right, comparing)
+                        },
+                    )
+            },
+        ),
+        _ => left == right,
     }
 }
 
-///Implements ordering for numeric rlux types
-impl Ord for LiteralType {
-    #[allow(clippy::comparison_chain)]
-    fn cmp(&self, other: &LiteralType) -> std::cmp::Ordering {
-        //Ripped Striaght from rust's own source
-        if *self < *other {
-            std::cmp::Ordering::Less
-        } else if *self == *other {
-            std::cmp::Ordering::Equal
-        } else {
-            std::cmp::Ordering::Greater
-        }
+///Runs `compare` unless the two collections are the same one or already being compared
+fn compare_once<T>(
+    left: *const T,
+    right: *const T,
+    comparing: &mut Vec<(*const (), *const ())>,
+    compare: impl FnOnce(&mut Vec<(*const (), *const ())>) -> bool,
+) -> bool {
+    let pair = (left as *const (), right as *const ());
+    if pair.0 == pair.1 || comparing.contains(&pair) {
+        return true;
     }
+    comparing.push(pair);
+    let equal = compare(comparing);
+    comparing.pop();
+    equal
 }
-
-///Strange Rust things are Happening
-impl Eq for LiteralType {}
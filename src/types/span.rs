@@ -0,0 +1,124 @@
+use super::{
+    Expression,
+    statement::Statement,
+};
+
+///Identifies which source a span points into
+pub(crate) type FileId = u32;
+
+///A contiguous region of one source, precise enough to point at a single sub-expression
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub file: FileId,
+    ///Byte offset of the first character
+    pub start: u32,
+    ///Byte offset just past the last character
+    pub end: u32,
+    ///1-based line of `start`
+    pub line: u32,
+    ///1-based column of `start`
+    pub column: u32,
+}
+
+impl Span {
+    ///Smallest span covering both `self` and `other`, positioned at whichever starts first
+    pub(crate) fn to(self, other: Span) -> Span {
+        let first = if self.start <= other.start {
+            self
+        } else {
+            other
+        };
+
+        Span {
+            end: self.end.max(other.end),
+            ..first
+        }
+    }
+}
+
+impl Expression {
+    ///Everything this expression was parsed from
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Expression::Grouping(group) => group.span,
+            Expression::Logical(logic) => logic.left.span().to(logic.right.span()),
+            Expression::Unary(unary) => unary.operator.span.to(unary.operand.span()),
+            Expression::Binary(bin) => bin.left.span().to(bin.right.span()),
+            Expression::Ternary(tern) => tern.evaluator.span().to(tern.right.span()),
+            Expression::Literal(lit) => lit.span,
+            Expression::Variable(var) => var.name.span,
+            Expression::Assignment(assign) => assign.name.span.to(assign.value.span()),
+            Expression::Call(call) => call.callee.span().to(call.paren.span),
+            Expression::Lambda(lambda) => lambda.span,
+            Expression::Interpolation(interpolation) => interpolation.span,
+            Expression::List(list) => list.span,
+            Expression::Map(map) => map.span,
+            Expression::Index(index) => index.object.span().to(index.bracket.span),
+            Expression::SetIndex(set) => set.object.span().to(set.value.span()),
+            Expression::Compound(compound) => compound
+                .target
+                .span()
+                .to(compound.operator.span)
+                .to(compound.value.span()),
+            Expression::Slice(slice) => slice.object.span().to(slice.bracket.span),
+            Expression::Get(get) => get.object.span().to(get.name.span),
+            Expression::Set(set) => set.object.span().to(set.value.span()),
+            Expression::This(this) => this.keyword.span,
+            Expression::Super(sup) => sup.keyword.span.to(sup.method.span),
+        }
+    }
+}
+
+impl Statement {
+    ///Everything this statement was parsed from, less any trailing `;`
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Statement::Expression(expression) => expression.expression.span(),
+            Statement::Variable(var) => match var.initalizer {
+                Some(ref initalizer) => var.keyword.span.to(initalizer.span()),
+                None => var.keyword.span.to(var.name.span),
+            },
+            Statement::While(while_statement) => {
+                let start = match while_statement.label {
+                    Some(ref label) => label.span,
+                    None => while_statement.keyword.span,
+                };
+                start.to(while_statement.body.span())
+            }
+            Statement::ForIn(for_in) => {
+                let start = match for_in.label {
+                    Some(ref label) => label.span,
+                    None => for_in.keyword.span,
+                };
+                start.to(for_in.body.span())
+            }
+            Statement::If(if_statement) => {
+                let span = if_statement
+                    .keyword
+                    .span
+                    .to(if_statement.then_branch.span());
+                match *if_statement.else_branch {
+                    Some(ref else_branch) => span.to(else_branch.span()),
+                    None => span,
+                }
+            }
+            Statement::Block(block) => block.span,
+            Statement::Function(function) => function.span,
+            Statement::Return(ret) => match ret.value {
+                Some(ref value) => ret.keyword.span.to(value.span()),
+                None => ret.keyword.span,
+            },
+            Statement::Class(class) => class.span,
+            Statement::Break(jump) => match jump.label {
+                Some(ref label) => jump.keyword.span.to(label.span),
+                None => jump.keyword.span,
+            },
+            Statement::Continue(jump) => match jump.label {
+                Some(ref label) => jump.keyword.span.to(label.span),
+                None => jump.keyword.span,
+            },
+            Statement::Try(try_statement) => try_statement.span,
+            Statement::Throw(throw) => throw.keyword.span.to(throw.value.span()),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::{
     Expression,
+    span::Span,
     token::Token,
 };
 /*
@@ -15,13 +16,28 @@ pub(crate) struct ExpressionStatement {
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct FunctionStatement {
+    ///`fun`, methods are declared without one
+    pub(crate) keyword: Option<Token>,
     pub(crate) name: Token,
     pub(crate) body: Vec<Statement>,
     pub(crate) parameters: Vec<Token>,
+    ///From `fun`, or a method's name, to the closing brace
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ClassStatement {
+    pub(crate) keyword: Token,
+    pub(crate) name: Token,
+    pub(crate) superclass: Option<Expression>,
+    pub(crate) methods: Vec<FunctionStatement>,
+    ///From `class` to the closing brace
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct IfStatement {
+    pub(crate) keyword: Token,
     pub(crate) condition: Expression,
     pub(crate) then_branch: Box<Statement>,
     pub(crate) else_branch: Box<Option<Statement>>,
@@ -29,19 +45,65 @@ pub(crate) struct IfStatement {
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct VariableStatement {
+    pub(crate) keyword: Token,
     pub(crate) name: Token,
     pub(crate) initalizer: Option<Expression>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct WhileStatement {
+    ///`while`, or `for` when desugared from a for loop
+    pub(crate) keyword: Token,
+    ///Set by `name: while ...`, a nested `break name;` or `continue name;` targets this loop
+    pub(crate) label: Option<Token>,
     pub(crate) condition: Expression,
     pub(crate) body: Box<Statement>,
+    ///A `for` loop's increment, kept out of the body so `continue` still runs it
+    pub(crate) increment: Option<Expression>,
+}
+
+///`for (var name in iterable) body`, the loop variable is fresh on every iteration
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ForInStatement {
+    pub(crate) keyword: Token,
+    pub(crate) label: Option<Token>,
+    pub(crate) name: Token,
+    pub(crate) iterable: Iterable,
+    pub(crate) body: Box<Statement>,
+}
+
+///What a `for-in` loop walks over
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Iterable {
+    ///A list, map, string, function or object with a `next` method, only known at runtime
+    Value(Expression),
+    ///`start..end`, or `start..=end` when `operator` says the end is included
+    Range {
+        start: Expression,
+        operator: Token,
+        end: Expression,
+    },
+}
+
+///Leaves the innermost loop, or the one named by `label`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BreakStatement {
+    pub(crate) keyword: Token,
+    pub(crate) label: Option<Token>,
+}
+
+///Skips to the next iteration of the innermost loop, or the one named by `label`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ContinueStatement {
+    pub(crate) keyword: Token,
+    pub(crate) label: Option<Token>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct BlockStatement {
     pub(crate) statements: Vec<Statement>,
+    ///Includes both braces, desugared blocks cover the statements they wrap
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -50,14 +112,44 @@ pub(crate) struct ReturnStatement {
     pub(crate) value: Option<Expression>,
 }
 
+///Runs `body`, handing anything it throws to `catch`, then runs `finally` however `body` was left.
+///The parser guarantees at least one of the two is present
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TryStatement {
+    pub(crate) keyword: Token,
+    pub(crate) body: Vec<Statement>,
+    pub(crate) catch: Option<CatchClause>,
+    pub(crate) finally: Option<Vec<Statement>>,
+    pub(crate) span: Span,
+}
+
+///`catch (name) { body }`, `name` holds the thrown value or an error map for runtime errors
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CatchClause {
+    pub(crate) name: Token,
+    pub(crate) body: Vec<Statement>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ThrowStatement {
+    pub(crate) keyword: Token,
+    pub(crate) value: Expression,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Statement {
     // Print(PrintStatement),
     Expression(ExpressionStatement),
     Variable(VariableStatement),
     While(WhileStatement),
+    ForIn(ForInStatement),
     If(IfStatement),
     Block(BlockStatement),
     Function(FunctionStatement),
     Return(ReturnStatement),
+    Class(ClassStatement),
+    Break(BreakStatement),
+    Continue(ContinueStatement),
+    Try(TryStatement),
+    Throw(ThrowStatement),
 }
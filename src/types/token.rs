@@ -1,6 +1,7 @@
 use super::{
     LiteralType,
     TokenType,
+    span::Span,
 };
 use std::fmt::Formatter;
 
@@ -9,7 +10,8 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<LiteralType>,
-    pub line: u32,
+    ///Where in the source the lexeme came from
+    pub span: Span,
 }
 
 impl std::fmt::Display for Token {
@@ -29,13 +31,13 @@ impl Token {
         token_type: TokenType,
         lexeme: &str,
         literal: Option<LiteralType>,
-        line: u32,
+        span: Span,
     ) -> Token {
         Token {
             token_type,
             lexeme: lexeme.to_string(),
             literal,
-            line,
+            span,
         }
     }
 }